@@ -0,0 +1,103 @@
+#![feature(proc_macro, conservative_impl_trait, generators)]
+
+#[macro_use]
+extern crate error_chain;
+
+extern crate futures_await as futures;
+extern crate organelle;
+extern crate serde_json;
+extern crate tokio_core;
+
+use std::io;
+
+use futures::prelude::*;
+use futures::unsync::mpsc;
+use organelle::{
+    probe, Axon, Constraint, Error, Impulse, Organelle, Result, Soma,
+};
+use tokio_core::reactor;
+
+/// dumps the probe data for its organelle to stdout and then stops it
+struct DumpSoma {
+    probe: Option<probe::Terminal>,
+}
+
+impl DumpSoma {
+    fn axon() -> Axon<Self> {
+        Axon::new(
+            DumpSoma { probe: None },
+            vec![],
+            vec![Constraint::One(probe::Synapse::Probe)],
+        )
+    }
+}
+
+impl Soma for DumpSoma {
+    type Synapse = probe::Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddTerminal(_, probe::Synapse::Probe, tx) => {
+                self.probe = Some(tx);
+
+                Ok(self)
+            },
+            Impulse::Start(_, main_tx, handle) => {
+                let probe = match self.probe.take() {
+                    Some(probe) => probe,
+                    None => bail!("dump soma has no probe"),
+                };
+
+                handle.spawn(dump(probe, main_tx.clone()).or_else(
+                    move |e| {
+                        main_tx
+                            .send(Impulse::Error(e))
+                            .map(|_| ())
+                            .map_err(|_| ())
+                    },
+                ));
+
+                Ok(self)
+            },
+
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+#[async]
+fn dump(
+    probe: probe::Terminal,
+    main_tx: mpsc::Sender<Impulse<probe::Synapse>>,
+) -> Result<()> {
+    let data = await!(probe.probe(probe::Settings::new().stable_order(true)))?;
+
+    serde_json::to_writer_pretty(io::stdout(), &data)?;
+    println!();
+
+    await!(
+        main_tx
+            .send(Impulse::Stop)
+            .map_err(|_| Error::from("unable to stop organelle"))
+    )?;
+
+    Ok(())
+}
+
+quick_main!(|| -> Result<()> {
+    let mut core = reactor::Core::new()?;
+    let handle = core.handle();
+
+    let mut organelle = Organelle::new(DumpSoma::axon(), handle.clone());
+
+    let dump = organelle.nucleus();
+    let probe = organelle.add_soma(probe::Soma::axon());
+
+    organelle.connect(dump, probe, probe::Synapse::Probe)?;
+
+    core.run(organelle.run(handle))?;
+
+    Ok(())
+});
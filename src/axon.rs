@@ -1,187 +1,671 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::intrinsics;
+use std::rc::Rc;
+use std::time::Duration;
 
+use futures::future;
 use futures::prelude::*;
-use futures::unsync::oneshot;
+use futures::unsync::{mpsc, oneshot};
 use uuid::Uuid;
 
 use super::{Error, ErrorKind, Result};
-use probe::{self, ConstraintData, SomaData};
-use soma::{Impulse, Soma, Synapse};
+use probe::{
+    self, ConnectionMeta, ConstraintData, ConstraintStatus, SomaData,
+    SomaMetrics,
+};
+use soma::{Constraints, Impulse, Soma, Synapse};
 
 /// constraints that can be put on axons for validation purposes
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Constraint<S: Synapse> {
     /// only accept one synapse
     One(S),
+    /// accept at most one synapse
+    Optional(S),
     /// accept any number of synapses
     Variadic(S),
+    /// accept between min and max synapses, inclusive
+    ///
+    /// One, Optional, and Variadic are the special cases 1..=1, 0..=1, and
+    /// 0.. respectively. having more than max synapses is rejected as soon as
+    /// they are connected, and having fewer than min is rejected at Start.
+    Range {
+        /// the synapse this constraint applies to
+        synapse: S,
+        /// the fewest synapses accepted
+        min: usize,
+        /// the most synapses accepted
+        max: usize,
+    },
+    /// only accept one synapse of any variant that satisfies the predicate
+    ///
+    /// this lets library somas accept synapses from an application without
+    /// naming its variants. constraints on a specific synapse take priority
+    /// over predicates that also accept it.
+    OneWhere(fn(S) -> bool),
+    /// accept any number of synapses that satisfy the predicate
+    VariadicWhere(fn(S) -> bool),
 }
 
+impl<S: Synapse> Constraint<S> {
+    /// the synapse this constraint applies to, if it names one
+    ///
+    /// this is None for constraints given by a predicate.
+    pub fn synapse(&self) -> Option<S> {
+        match self {
+            &Constraint::One(synapse)
+            | &Constraint::Optional(synapse)
+            | &Constraint::Variadic(synapse)
+            | &Constraint::Range { synapse, .. } => Some(synapse),
+            &Constraint::OneWhere(_) | &Constraint::VariadicWhere(_) => None,
+        }
+    }
+
+    /// check if this constraint applies to a synapse
+    pub fn accepts(&self, synapse: S) -> bool {
+        match self.predicate() {
+            Some(f) => f(synapse),
+            None => self.synapse() == Some(synapse),
+        }
+    }
+
+    fn predicate(&self) -> Option<fn(S) -> bool> {
+        match self {
+            &Constraint::OneWhere(f) | &Constraint::VariadicWhere(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    /// the fewest and most synapses accepted, if there is a most
+    pub fn bounds(&self) -> (usize, Option<usize>) {
+        match self {
+            &Constraint::One(_) | &Constraint::OneWhere(_) => (1, Some(1)),
+            &Constraint::Optional(_) => (0, Some(1)),
+            &Constraint::Variadic(_) | &Constraint::VariadicWhere(_) => {
+                (0, None)
+            },
+            &Constraint::Range { min, max, .. } => (min, Some(max)),
+        }
+    }
+
+    /// convert the synapse of this constraint into another type
+    ///
+    /// this is None for constraints given by a predicate, since the
+    /// predicate only understands the original type.
+    pub fn convert<R: Synapse + From<S>>(self) -> Option<Constraint<R>> {
+        self.map(|synapse| synapse.into())
+    }
+
+    /// map the synapse of this constraint into another type with `f`
+    ///
+    /// like convert, this is None for constraints given by a predicate.
+    pub fn map<R, F>(self, f: F) -> Option<Constraint<R>>
+    where
+        R: Synapse,
+        F: Fn(S) -> R,
+    {
+        match self {
+            Constraint::One(synapse) => Some(Constraint::One(f(synapse))),
+            Constraint::Optional(synapse) => {
+                Some(Constraint::Optional(f(synapse)))
+            },
+            Constraint::Variadic(synapse) => {
+                Some(Constraint::Variadic(f(synapse)))
+            },
+            Constraint::Range { synapse, min, max } => {
+                Some(Constraint::Range {
+                    synapse: f(synapse),
+                    min: min,
+                    max: max,
+                })
+            },
+            Constraint::OneWhere(_) | Constraint::VariadicWhere(_) => None,
+        }
+    }
+
+    /// check that some number of synapses can satisfy the constraint
+    fn check(&self) -> Result<()> {
+        match self {
+            &Constraint::Range { min, max, .. } if min > max => bail!(
+                "role {} requires {}..={} connections, which can never be \
+                 satisfied",
+                self.label(),
+                min,
+                max
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// check if two constraints apply to the same synapses
+    fn same_target(&self, other: &Self) -> bool {
+        match (self.predicate(), other.predicate()) {
+            (Some(f), Some(g)) => f == g,
+            (None, None) => self.synapse() == other.synapse(),
+            _ => false,
+        }
+    }
+
+    fn label(&self) -> String {
+        match self.synapse() {
+            Some(synapse) => format!("{:?}", synapse),
+            None => "<predicate>".to_string(),
+        }
+    }
+}
+
+/// the somas connected under a single constraint, along with their synapses
 #[derive(Debug)]
-enum Requirement {
-    Unmet,
-    MetOne(Uuid),
-    MetVariadic(Vec<Uuid>),
+struct Peers<S: Synapse> {
+    constraint: Constraint<S>,
+    somas: Vec<(Uuid, S)>,
+}
+
+impl<S: Synapse> Peers<S> {
+    fn new(constraint: Constraint<S>) -> Self {
+        Self {
+            constraint: constraint,
+            somas: vec![],
+        }
+    }
+
+    fn add(&mut self, kind: &str, uuid: Uuid, synapse: S) -> Result<()> {
+        match self.constraint.bounds() {
+            (_, Some(1)) if !self.somas.is_empty() => {
+                bail!(ErrorKind::InvalidSynapse(format!(
+                    "expected only one {} for {:?}",
+                    kind, synapse
+                )))
+            },
+            (_, Some(max)) if self.somas.len() >= max => {
+                bail!(ErrorKind::InvalidSynapse(format!(
+                    "expected at most {} {}s for {:?}",
+                    max, kind, synapse
+                )))
+            },
+            _ => (),
+        }
+
+        self.somas.push((uuid, synapse));
+
+        Ok(())
+    }
+
+    fn remove(&mut self, uuid: Uuid, synapse: S) -> bool {
+        let len = self.somas.len();
+        self.somas.retain(|&peer| peer != (uuid, synapse));
+
+        self.somas.len() != len
+    }
+
+    fn peers(&self, synapse: S) -> Vec<Uuid> {
+        self.somas
+            .iter()
+            .filter(|&&(_, s)| s == synapse)
+            .map(|&(uuid, _)| uuid)
+            .collect()
+    }
+
+    fn status(&self, kind: &str) -> ConstraintStatus {
+        ConstraintStatus {
+            kind: kind.to_string(),
+            variant: self.constraint.label(),
+            connected: self.somas.len(),
+            required: self.constraint.bounds().0,
+        }
+    }
+
+    /// describe the constraint if it doesn't have enough connections
+    fn violation(&self, kind: &str) -> Option<String> {
+        let connected = self.somas.len();
+
+        match self.constraint {
+            Constraint::One(_) | Constraint::OneWhere(_) if connected == 0 => {
+                Some(format!(
+                    "missing required {}: {}",
+                    kind,
+                    self.constraint.label()
+                ))
+            },
+            Constraint::Range { min, max, .. } if connected < min => {
+                Some(format!(
+                    "role {} requires {}..={} connections, got {}",
+                    self.constraint.label(),
+                    min,
+                    max,
+                    connected
+                ))
+            },
+            _ => None,
+        }
+    }
+
+    /// describe the connections made under the constraint
+    ///
+    /// `meta` finds the metadata for the connection to a peer.
+    fn data<F>(&self, meta: F) -> Vec<ConstraintData>
+    where
+        F: Fn(Uuid, S) -> Option<ConnectionMeta>,
+    {
+        let variant = self.constraint.label();
+        let somas: Vec<Uuid> =
+            self.somas.iter().map(|&(uuid, _)| uuid).collect();
+        let first_meta = self.somas
+            .first()
+            .and_then(|&(uuid, synapse)| meta(uuid, synapse));
+        let all_meta = |synapse: Option<S>| -> BTreeMap<Uuid, ConnectionMeta> {
+            self.somas
+                .iter()
+                .filter(|&&(_, s)| synapse.map_or(true, |synapse| s == synapse))
+                .filter_map(|&(uuid, s)| meta(uuid, s).map(|m| (uuid, m)))
+                .collect()
+        };
+
+        match self.constraint {
+            // reported as pending instead
+            Constraint::One(_) if somas.is_empty() => vec![],
+            Constraint::One(_) => vec![ConstraintData::One {
+                variant: variant,
+                soma: somas[0],
+                satisfied: true,
+                meta: first_meta,
+            }],
+            Constraint::Optional(_) => vec![ConstraintData::Optional {
+                variant: variant,
+                soma: somas.first().cloned(),
+                satisfied: true,
+                meta: first_meta,
+            }],
+            Constraint::Variadic(_) => vec![ConstraintData::Variadic {
+                variant: variant,
+                somas: somas,
+                satisfied: true,
+                meta: all_meta(None),
+            }],
+            Constraint::Range { min, max, .. } => vec![ConstraintData::Range {
+                variant: variant,
+                satisfied: somas.len() >= min,
+                somas: somas,
+                min: min,
+                max: max,
+                meta: all_meta(None),
+            }],
+
+            // reported under each synapse that was actually connected
+            Constraint::OneWhere(_) => self.somas
+                .iter()
+                .map(|&(uuid, synapse)| ConstraintData::One {
+                    variant: format!("{:?}", synapse),
+                    soma: uuid,
+                    satisfied: true,
+                    meta: meta(uuid, synapse),
+                })
+                .collect(),
+            Constraint::VariadicWhere(_) => {
+                let mut synapses: Vec<S> = vec![];
+
+                for &(_, synapse) in &self.somas {
+                    if !synapses.contains(&synapse) {
+                        synapses.push(synapse);
+                    }
+                }
+
+                synapses
+                    .into_iter()
+                    .map(|synapse| ConstraintData::Variadic {
+                        variant: format!("{:?}", synapse),
+                        somas: self.peers(synapse),
+                        satisfied: true,
+                        meta: all_meta(Some(synapse)),
+                    })
+                    .collect()
+            },
+        }
+    }
 }
 
 /// wrap a soma with a set of requirements that will be validated upon startup
+///
+/// the axon checks connections against its constraints and answers probes,
+/// but every other impulse is passed straight through to the soma, so the
+/// soma only needs to handle its own signals.
 pub struct Axon<T: Soma + 'static> {
     soma: T,
 
     uuid: Option<Uuid>,
+    main_tx: Option<mpsc::Sender<Impulse<T::Synapse>>>,
 
-    dendrites: HashMap<T::Synapse, (Constraint<T::Synapse>, Requirement)>,
-    terminals: HashMap<T::Synapse, (Constraint<T::Synapse>, Requirement)>,
+    dendrites: Vec<Peers<T::Synapse>>,
+    terminals: Vec<Peers<T::Synapse>>,
 }
 
 impl<T: Soma + 'static> Axon<T> {
     /// wrap a soma with constraints specified by dendrite and terminal
     /// constraints
+    ///
+    /// # Panics
+    ///
+    /// panics if a Range constraint has a min greater than its max, since it
+    /// could never be satisfied.
     pub fn new(
         soma: T,
         dendrites: Vec<Constraint<T::Synapse>>,
         terminals: Vec<Constraint<T::Synapse>>,
     ) -> Self {
+        for constraint in dendrites.iter().chain(terminals.iter()) {
+            if let Err(e) = constraint.check() {
+                panic!("invalid constraint: {}", e)
+            }
+        }
+
         Self {
             soma: soma,
 
             uuid: None,
+            main_tx: None,
 
-            dendrites: dendrites
-                .iter()
-                .map(|d| match d {
-                    &Constraint::One(r) => {
-                        (r, (Constraint::One(r), Requirement::Unmet))
-                    },
-                    &Constraint::Variadic(r) => (
-                        r,
-                        (
-                            Constraint::Variadic(r),
-                            Requirement::MetVariadic(vec![]),
-                        ),
-                    ),
-                })
-                .collect(),
-            terminals: terminals
-                .iter()
-                .map(|d| match d {
-                    &Constraint::One(r) => {
-                        (r, (Constraint::One(r), Requirement::Unmet))
-                    },
-                    &Constraint::Variadic(r) => (
-                        r,
-                        (
-                            Constraint::Variadic(r),
-                            Requirement::MetVariadic(vec![]),
-                        ),
-                    ),
-                })
-                .collect(),
+            dendrites: dendrites.into_iter().map(Peers::new).collect(),
+            terminals: terminals.into_iter().map(Peers::new).collect(),
         }
     }
 
-    fn add_dendrite(&mut self, uuid: Uuid, synapse: T::Synapse) -> Result<()> {
-        if let Some(&mut (ref mut constraint, ref mut req)) =
-            self.dendrites.get_mut(&synapse)
-        {
-            match constraint {
-                &mut Constraint::One(_) => match req {
-                    &mut Requirement::Unmet => *req = Requirement::MetOne(uuid),
-                    &mut Requirement::MetOne(_) => {
-                        bail!(ErrorKind::InvalidSynapse(format!(
-                            "expected only one dendrite for {:?}",
-                            synapse
-                        )))
-                    },
-                    _ => unreachable!(),
-                },
-                &mut Constraint::Variadic(_) => match req {
-                    &mut Requirement::MetVariadic(ref mut dendrites) => {
-                        dendrites.push(uuid);
-                    },
-                    _ => unreachable!(),
-                },
+    /// accept dendrites for a synapse, replacing any constraint it had
+    ///
+    /// this must be called before the axon starts, and before it is added to
+    /// an organelle if the organelle should check connections against it
+    /// too. dendrites that are already connected are validated again under
+    /// the new constraint, and if there are too many of them the constraint
+    /// is not changed and an error is returned instead. a Range constraint
+    /// with a min greater than its max is rejected the same way.
+    pub fn add_dendrite_constraint(
+        &mut self,
+        constraint: Constraint<T::Synapse>,
+    ) -> Result<()> {
+        self.check_unstarted()?;
+
+        Self::add_constraint(&mut self.dendrites, "dendrite", constraint)
+    }
+
+    /// accept terminals for a synapse, replacing any constraint it had
+    ///
+    /// see add_dendrite_constraint.
+    pub fn add_terminal_constraint(
+        &mut self,
+        constraint: Constraint<T::Synapse>,
+    ) -> Result<()> {
+        self.check_unstarted()?;
+
+        Self::add_constraint(&mut self.terminals, "terminal", constraint)
+    }
+
+    fn check_unstarted(&self) -> Result<()> {
+        if self.uuid.is_some() {
+            bail!("constraints cannot be added after the axon has started")
+        }
+
+        Ok(())
+    }
+
+    fn add_constraint(
+        peers: &mut Vec<Peers<T::Synapse>>,
+        kind: &str,
+        constraint: Constraint<T::Synapse>,
+    ) -> Result<()> {
+        constraint.check()?;
+
+        let mut constraints: Vec<_> =
+            peers.iter().map(|p| p.constraint).collect();
+
+        match constraints.iter().position(|c| c.same_target(&constraint)) {
+            Some(index) => constraints[index] = constraint,
+            None => constraints.push(constraint),
+        }
+
+        // a new constraint on a synapse takes over peers that were connected
+        // under a predicate, so every connection is validated again
+        let mut updated: Vec<_> =
+            constraints.into_iter().map(Peers::new).collect();
+
+        for p in peers.iter() {
+            for &(uuid, synapse) in &p.somas {
+                // keep the constraints the connections were made under
+                Self::add_peer(&mut updated, kind, uuid, synapse)?;
             }
-        } else {
-            bail!(ErrorKind::InvalidSynapse(format!(
-                "no constraints found for {:?}",
-                synapse
-            )))
         }
 
+        *peers = updated;
+
         Ok(())
     }
 
+    /// wrap an error with the name and uuid of this soma and some context
+    pub fn error_with_context<E: Into<Error>>(
+        &self,
+        context: &str,
+        cause: E,
+    ) -> Error {
+        Self::context_error(self.uuid, context, cause)
+    }
+
+    fn context_error<E: Into<Error>>(
+        uuid: Option<Uuid>,
+        context: &str,
+        cause: E,
+    ) -> Error {
+        let uuid = match uuid {
+            Some(uuid) => uuid.to_string(),
+            None => "unstarted".to_string(),
+        };
+
+        Error::with_chain(
+            cause.into(),
+            ErrorKind::Msg(format!(
+                "{} ({}) - {}",
+                unsafe { intrinsics::type_name::<T>() },
+                uuid,
+                context
+            )),
+        )
+    }
+
+    /// fail the organelle with an error wrapped with this soma's context
+    ///
+    /// this can only be used after the axon has started.
+    pub fn fail<E: Into<Error>>(
+        &self,
+        context: &str,
+        cause: E,
+    ) -> Box<Future<Item = (), Error = Error>> {
+        match (self.uuid, self.main_tx.as_ref()) {
+            (Some(uuid), Some(main_tx)) => {
+                Self::fail_with(uuid, main_tx, context, cause)
+            },
+            _ => Box::new(future::err(self.error_with_context(context, cause))),
+        }
+    }
+
+    /// fail the organelle from the soma wrapped by an axon
+    ///
+    /// the wrapped soma can't reach its axon, so it passes the uuid and the
+    /// sender it was given with Impulse::Start instead, as in
+    /// `Axon::<Self>::fail_with(uuid, &main_tx, context, cause)`. the error
+    /// is wrapped the same way as with fail.
+    pub fn fail_with<E: Into<Error>>(
+        uuid: Uuid,
+        main_tx: &mpsc::Sender<Impulse<T::Synapse>>,
+        context: &str,
+        cause: E,
+    ) -> Box<Future<Item = (), Error = Error>> {
+        let e = Self::context_error(Some(uuid), context, cause);
+
+        Box::new(
+            main_tx
+                .clone()
+                .send(Impulse::Error(e))
+                .map(|_| ())
+                .map_err(|_| Error::from("unable to send error impulse")),
+        )
+    }
+
+    /// the connection status of each dendrite and terminal constraint
+    ///
+    /// this reflects every connection received so far, so it can be used to
+    /// see what an axon is still waiting for before it starts.
+    pub fn constraint_status(&self) -> Vec<ConstraintStatus> {
+        self.dendrites
+            .iter()
+            .map(|p| p.status("dendrite"))
+            .chain(self.terminals.iter().map(|p| p.status("terminal")))
+            .collect()
+    }
+
+    /// the constraints that are still waiting for connections
+    pub fn pending_constraints(&self) -> Vec<ConstraintStatus> {
+        self.constraint_status()
+            .into_iter()
+            .filter(|status| !status.is_satisfied())
+            .collect()
+    }
+
+    /// the somas connected to a dendrite synapse, in the order they connected
+    ///
+    /// this is empty for synapses without a dendrite constraint, and can be
+    /// used to route signals across variadic peers (e.g. round-robin).
+    pub fn dendrite_peers(&self, synapse: T::Synapse) -> Vec<Uuid> {
+        self.dendrites
+            .iter()
+            .flat_map(|p| p.peers(synapse))
+            .collect()
+    }
+
+    /// the somas connected to a terminal synapse, in the order they connected
+    ///
+    /// this is empty for synapses without a terminal constraint.
+    pub fn terminal_peers(&self, synapse: T::Synapse) -> Vec<Uuid> {
+        self.terminals
+            .iter()
+            .flat_map(|p| p.peers(synapse))
+            .collect()
+    }
+
+    fn add_dendrite(&mut self, uuid: Uuid, synapse: T::Synapse) -> Result<()> {
+        Self::add_peer(&mut self.dendrites, "dendrite", uuid, synapse)
+    }
+
     fn add_terminal(&mut self, uuid: Uuid, synapse: T::Synapse) -> Result<()> {
-        if let Some(&mut (ref mut constraint, ref mut req)) =
-            self.terminals.get_mut(&synapse)
-        {
-            match constraint {
-                &mut Constraint::One(_) => match req {
-                    &mut Requirement::Unmet => *req = Requirement::MetOne(uuid),
-                    &mut Requirement::MetOne(_) => {
-                        bail!(ErrorKind::InvalidSynapse(format!(
-                            "expected only one terminal for {:?}",
-                            synapse
-                        )))
-                    },
-                    _ => unreachable!(),
-                },
-                &mut Constraint::Variadic(_) => match req {
-                    &mut Requirement::MetVariadic(ref mut terminals) => {
-                        terminals.push(uuid);
-                    },
-                    _ => unreachable!(),
-                },
-            }
-        } else {
+        Self::add_peer(&mut self.terminals, "terminal", uuid, synapse)
+    }
+
+    fn add_peer(
+        peers: &mut Vec<Peers<T::Synapse>>,
+        kind: &str,
+        uuid: Uuid,
+        synapse: T::Synapse,
+    ) -> Result<()> {
+        // constraints on the synapse itself come before predicates
+        let index = peers
+            .iter()
+            .position(|p| p.constraint.synapse() == Some(synapse))
+            .or_else(|| {
+                peers.iter().position(|p| p.constraint.accepts(synapse))
+            });
+
+        match index {
+            Some(index) => peers[index].add(kind, uuid, synapse),
+            None => bail!(Self::unexpected_peer(kind, uuid, synapse, peers)),
+        }
+    }
+
+    fn remove_dendrite(
+        &mut self,
+        uuid: Uuid,
+        synapse: T::Synapse,
+    ) -> Result<()> {
+        Self::remove_peer(&mut self.dendrites, "dendrite", uuid, synapse)
+    }
+
+    fn remove_terminal(
+        &mut self,
+        uuid: Uuid,
+        synapse: T::Synapse,
+    ) -> Result<()> {
+        Self::remove_peer(&mut self.terminals, "terminal", uuid, synapse)
+    }
+
+    fn remove_peer(
+        peers: &mut Vec<Peers<T::Synapse>>,
+        kind: &str,
+        uuid: Uuid,
+        synapse: T::Synapse,
+    ) -> Result<()> {
+        if !peers.iter_mut().any(|p| p.remove(uuid, synapse)) {
             bail!(ErrorKind::InvalidSynapse(format!(
-                "no constraints found for {:?}",
-                synapse
+                "no {} for {:?} from soma {}",
+                kind, synapse, uuid
             )))
         }
 
         Ok(())
     }
 
+    fn unexpected_peer(
+        kind: &str,
+        peer: Uuid,
+        synapse: T::Synapse,
+        expected: &[Peers<T::Synapse>],
+    ) -> ErrorKind {
+        let expected: Vec<String> =
+            expected.iter().map(|p| p.constraint.label()).collect();
+
+        ErrorKind::InvalidSynapse(format!(
+            "peer {} connected as a {} with {:?}, but only [{}] are accepted",
+            peer,
+            kind,
+            synapse,
+            expected.join(", ")
+        ))
+    }
+
     fn start(&mut self, uuid: Uuid) -> Result<()> {
         self.uuid = Some(uuid);
 
-        for (synapse, &(ref constraint, ref req)) in &self.dendrites {
-            match constraint {
-                &Constraint::One(_) => match req {
-                    &Requirement::MetOne(_) => (),
-                    &Requirement::Unmet => bail!(ErrorKind::MissingSynapse(
-                        format!("expected dendrite synapse for {:?}", *synapse)
-                    )),
-                    _ => unreachable!(),
-                },
-                &Constraint::Variadic(_) => match req {
-                    &Requirement::MetVariadic(_) => (),
-                    _ => unreachable!(),
-                },
-            }
-        }
+        let violations: Vec<String> = self.dendrites
+            .iter()
+            .filter_map(|p| p.violation("dendrite"))
+            .chain(
+                self.terminals
+                    .iter()
+                    .filter_map(|p| p.violation("terminal")),
+            )
+            .collect();
 
-        for (synapse, &(ref constraint, ref req)) in &self.terminals {
-            match constraint {
-                &Constraint::One(_) => match req {
-                    &Requirement::MetOne(_) => (),
-                    &Requirement::Unmet => bail!(ErrorKind::MissingSynapse(
-                        format!("expected terminal synapse for {:?}", *synapse)
-                    )),
-                    _ => unreachable!(),
-                },
-                &Constraint::Variadic(_) => match req {
-                    &Requirement::MetVariadic(_) => (),
-                    _ => unreachable!(),
-                },
-            }
+        if !violations.is_empty() {
+            bail!(ErrorKind::MissingSynapse(violations.join("; ")))
         }
 
         Ok(())
     }
 
+    /// adapt this axon to an organelle with a different type of synapse
+    ///
+    /// `to` maps the axon's synapses into the organelle's and `from` maps
+    /// them back, so somas can be reused across crates whose synapse enums
+    /// can't be converted with a blanket From impl. the terminals and
+    /// dendrites still have to convert both ways with From.
+    pub fn map_synapse<S, F, G>(self, to: F, from: G) -> MapSynapse<Self, S>
+    where
+        S: Synapse,
+        F: Fn(T::Synapse) -> S + 'static,
+        G: Fn(S) -> T::Synapse + 'static,
+    {
+        MapSynapse {
+            soma: self,
+            to: Rc::new(to),
+            from: Rc::new(from),
+        }
+    }
+
     #[async]
     fn perform_probe(
         self,
@@ -202,56 +686,78 @@ impl<T: Soma + 'static> Soma for Axon<T> {
     type Synapse = T::Synapse;
     type Error = Error;
 
+    fn name(&self) -> Cow<str> {
+        self.soma.name()
+    }
+
+    fn category(&self) -> Option<Cow<str>> {
+        self.soma.category()
+    }
+
+    fn metrics(&self) -> SomaMetrics {
+        self.soma.metrics()
+    }
+
+    fn is_done(&self) -> bool {
+        self.soma.is_done()
+    }
+
+    fn handles_pause(&self) -> bool {
+        self.soma.handles_pause()
+    }
+
+    /// axons keep track of their peers, so they always see removals, but
+    /// they only pass them on to somas that handle removal
+    fn handles_removal(&self) -> bool {
+        true
+    }
+
+    fn update_deadline(&self) -> Option<Duration> {
+        self.soma.update_deadline()
+    }
+
+    fn run_buffer(&self) -> usize {
+        self.soma.run_buffer()
+    }
+
+    #[async(boxed)]
+    fn on_stop(self) -> Result<()> {
+        await!(self.soma.on_stop()).map_err(|e| e.into())
+    }
+
+    fn constraints(&self) -> Option<Constraints<Self::Synapse>> {
+        Some((
+            self.dendrites.iter().map(|p| p.constraint).collect(),
+            self.terminals.iter().map(|p| p.constraint).collect(),
+        ))
+    }
+
     #[async(boxed)]
-    fn probe(self, _settings: probe::Settings) -> Result<(Self, SomaData)> {
+    fn probe(self, settings: probe::Settings) -> Result<(Self, SomaData)> {
+        let uuid = self.uuid.unwrap_or_else(Uuid::nil);
+
+        // the soma holding the terminal is the dendrite of the connection
         let terminals = self.terminals
             .iter()
-            .map(|(synapse, &(ref constraint, ref requirement))| {
-                match constraint {
-                    &Constraint::One(_) => ConstraintData::One {
-                        variant: format!("{:?}", *synapse),
-                        soma: match requirement {
-                            &Requirement::MetOne(ref uuid) => *uuid,
-                            _ => panic!("axon failed to validate"),
-                        },
-                    },
-                    &Constraint::Variadic(_) => ConstraintData::Variadic {
-                        variant: format!("{:?}", *synapse),
-                        somas: match requirement {
-                            &Requirement::MetVariadic(ref somas) => {
-                                somas.clone()
-                            },
-                            _ => unreachable!(),
-                        },
-                    },
-                }
+            .flat_map(|p| {
+                p.data(|peer, synapse| {
+                    settings.connection_meta(uuid, peer, synapse)
+                })
             })
             .collect();
         let dendrites = self.dendrites
             .iter()
-            .map(|(synapse, &(ref constraint, ref requirement))| {
-                match constraint {
-                    &Constraint::One(_) => ConstraintData::One {
-                        variant: format!("{:?}", *synapse),
-                        soma: match requirement {
-                            &Requirement::MetOne(ref uuid) => *uuid,
-                            _ => panic!("axon failed to validate"),
-                        },
-                    },
-                    &Constraint::Variadic(_) => ConstraintData::Variadic {
-                        variant: format!("{:?}", *synapse),
-                        somas: match requirement {
-                            &Requirement::MetVariadic(ref somas) => {
-                                somas.clone()
-                            },
-                            _ => unreachable!(),
-                        },
-                    },
-                }
+            .flat_map(|p| {
+                p.data(|peer, synapse| {
+                    settings.connection_meta(peer, uuid, synapse)
+                })
             })
             .collect();
 
-        let uuid = self.uuid.unwrap();
+        let pending = self.pending_constraints();
+        let name = self.name().into_owned();
+        let category = self.category().map(|c| c.into_owned());
+        let metrics = self.metrics();
 
         Ok((
             self,
@@ -259,7 +765,10 @@ impl<T: Soma + 'static> Soma for Axon<T> {
                 terminals: terminals,
                 dendrites: dendrites,
                 uuid: uuid,
-                name: unsafe { intrinsics::type_name::<Self>().to_string() },
+                name: name,
+                pending: pending,
+                category: category,
+                metrics: metrics,
             },
         ))
     }
@@ -283,11 +792,33 @@ impl<T: Soma + 'static> Soma for Axon<T> {
 
                 Ok(self)
             },
-            Impulse::Start(uuid, _, _) => {
+            Impulse::RemoveDendrite(uuid, synapse) => {
+                self.remove_dendrite(uuid, synapse)?;
+
+                if self.soma.handles_removal() {
+                    self.soma =
+                        await!(self.soma.update(imp)).map_err(|e| e.into())?;
+                }
+
+                Ok(self)
+            },
+            Impulse::RemoveTerminal(uuid, synapse) => {
+                self.remove_terminal(uuid, synapse)?;
+
+                if self.soma.handles_removal() {
+                    self.soma =
+                        await!(self.soma.update(imp)).map_err(|e| e.into())?;
+                }
+
+                Ok(self)
+            },
+            Impulse::Start(uuid, main_tx, handle) => {
                 self.start(uuid)?;
+                self.main_tx = Some(main_tx.clone());
 
-                self.soma =
-                    await!(self.soma.update(imp)).map_err(|e| e.into())?;
+                self.soma = await!(
+                    self.soma.update(Impulse::Start(uuid, main_tx, handle))
+                ).map_err(|e| e.into())?;
 
                 Ok(self)
             },
@@ -296,10 +827,161 @@ impl<T: Soma + 'static> Soma for Axon<T> {
                 await!(self.perform_probe(settings, tx))
             },
 
-            Impulse::Stop | Impulse::Error(_) => {
-                bail!("unexpected impulse in axon")
+            // everything else is none of the axon's business, so the soma
+            // decides what to do with it
+            imp => {
+                self.soma =
+                    await!(self.soma.update(imp)).map_err(|e| e.into())?;
+
+                Ok(self)
+            },
+        }
+    }
+}
+
+type MapRole<S, R> = Rc<Fn(S) -> R>;
+
+impl<T: Soma + 'static, S: Synapse + 'static> MapSynapse<T, S> {
+    /// look up connection metadata with the wrapped soma's synapses
+    fn map_settings(&self, settings: probe::Settings) -> probe::Settings {
+        let lookup = settings.connection_lookup::<S>();
+
+        match lookup {
+            Some(lookup) => {
+                let to = Rc::clone(&self.to);
+                let mapped: probe::ConnectionLookup<T::Synapse> =
+                    Rc::new(move |dendrite, terminal, synapse| {
+                        (*lookup)(dendrite, terminal, (*to)(synapse))
+                    });
+
+                settings.connections(mapped)
             },
-            //_ => await!(self.soma.update(imp))?,
+            None => settings,
         }
     }
 }
+
+/// soma that speaks a different type of synapse than the one it wraps
+///
+/// see Axon::map_synapse.
+pub struct MapSynapse<T: Soma, S: Synapse> {
+    soma: T,
+    to: MapRole<T::Synapse, S>,
+    from: MapRole<S, T::Synapse>,
+}
+
+impl<T, S> Soma for MapSynapse<T, S>
+where
+    T: Soma + 'static,
+    S: Synapse + 'static,
+    S::Dendrite: From<<T::Synapse as Synapse>::Dendrite>
+        + Into<<T::Synapse as Synapse>::Dendrite>,
+    S::Terminal: From<<T::Synapse as Synapse>::Terminal>
+        + Into<<T::Synapse as Synapse>::Terminal>,
+{
+    type Synapse = S;
+    type Error = Error;
+
+    fn name(&self) -> Cow<str> {
+        self.soma.name()
+    }
+
+    fn category(&self) -> Option<Cow<str>> {
+        self.soma.category()
+    }
+
+    fn metrics(&self) -> SomaMetrics {
+        self.soma.metrics()
+    }
+
+    fn is_done(&self) -> bool {
+        self.soma.is_done()
+    }
+
+    fn handles_pause(&self) -> bool {
+        self.soma.handles_pause()
+    }
+
+    fn handles_removal(&self) -> bool {
+        self.soma.handles_removal()
+    }
+
+    fn update_deadline(&self) -> Option<Duration> {
+        self.soma.update_deadline()
+    }
+
+    fn run_buffer(&self) -> usize {
+        self.soma.run_buffer()
+    }
+
+    #[async(boxed)]
+    fn on_stop(self) -> Result<()> {
+        await!(self.soma.on_stop()).map_err(|e| e.into())
+    }
+
+    fn constraints(&self) -> Option<Constraints<Self::Synapse>> {
+        let (dendrites, terminals) = self.soma.constraints()?;
+
+        // constraints given by a predicate can't be mapped, so the soma is
+        // left to check its connections as they arrive
+        Some((
+            dendrites
+                .into_iter()
+                .map(|c| c.map(&*self.to))
+                .collect::<Option<_>>()?,
+            terminals
+                .into_iter()
+                .map(|c| c.map(&*self.to))
+                .collect::<Option<_>>()?,
+        ))
+    }
+
+    #[async(boxed)]
+    fn probe(self, settings: probe::Settings) -> Result<(Self, SomaData)> {
+        let settings = self.map_settings(settings);
+        let (soma, data) =
+            await!(self.soma.probe(settings)).map_err(|e| e.into())?;
+
+        Ok((
+            Self {
+                soma: soma,
+                to: self.to,
+                from: self.from,
+            },
+            data,
+        ))
+    }
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<S>) -> Result<Self> {
+        let imp = match imp {
+            Impulse::Start(uuid, main_tx, handle) => {
+                let (tx, rx) = mpsc::channel(self.soma.run_buffer());
+                let to = Rc::clone(&self.to);
+
+                // this only fails once the organelle has stopped, so there is
+                // nowhere left to report it
+                handle.spawn(
+                    main_tx
+                        .send_all(
+                            rx.map(move |imp: Impulse<T::Synapse>| {
+                                imp.map_synapse(&*to)
+                            }).map_err(|_| unreachable!()),
+                        )
+                        .map(|_| ())
+                        .map_err(|_| ()),
+                );
+
+                Impulse::Start(uuid, tx, handle)
+            },
+            Impulse::Probe(settings, tx) => {
+                Impulse::Probe(self.map_settings(settings), tx)
+            },
+            imp => imp.map_synapse(&*self.from),
+        };
+
+        self.soma = await!(self.soma.update(imp)).map_err(|e| e.into())?;
+
+        Ok(self)
+    }
+}
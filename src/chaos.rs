@@ -0,0 +1,144 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use futures::future;
+use futures::prelude::*;
+use tokio_core::reactor;
+
+use super::Error;
+
+const DEFAULT_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// seeded random number generator used to decide when to inject faults
+///
+/// the state is shared between clones so that every soma in an organelle
+/// draws from the same deterministic sequence.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: Rc<Cell<u64>>,
+}
+
+impl Rng {
+    /// create a generator from the given seed
+    pub fn seeded(seed: u64) -> Self {
+        let rng = Self {
+            state: Rc::new(Cell::new(DEFAULT_SEED)),
+        };
+
+        rng.reseed(seed);
+
+        rng
+    }
+
+    /// reset the sequence using the given seed
+    pub fn reseed(&self, seed: u64) {
+        // xorshift gets stuck on zero
+        self.state.set(if seed == 0 { DEFAULT_SEED } else { seed });
+    }
+
+    /// get the next number in the range [0, 1)
+    pub fn next_f64(&self) -> f64 {
+        let mut x = self.state.get();
+
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+
+        self.state.set(x);
+
+        (x.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64
+            / (1u64 << 53) as f64
+    }
+}
+
+/// artificial latency applied to the impulses of a single soma
+///
+/// this is shared between the organelle and the relay task of the soma so
+/// that it can be adjusted while the organelle is running.
+#[derive(Debug, Clone)]
+pub struct Latency {
+    duration: Rc<Cell<Option<Duration>>>,
+}
+
+impl Latency {
+    /// create a latency that does not delay anything
+    pub fn new() -> Self {
+        Self {
+            duration: Rc::new(Cell::new(None)),
+        }
+    }
+
+    /// delay each impulse by the given duration
+    pub fn set(&self, duration: Duration) {
+        self.duration.set(Some(duration));
+    }
+
+    /// wrap a stream so that each item is delayed by the current latency
+    pub fn delay<S>(
+        self,
+        stream: S,
+        handle: reactor::Handle,
+    ) -> Box<Stream<Item = S::Item, Error = S::Error>>
+    where
+        S: Stream + 'static,
+        S::Item: 'static,
+        S::Error: 'static,
+    {
+        Box::new(stream.and_then(
+            move |item| -> Box<Future<Item = S::Item, Error = S::Error>> {
+                match self.duration.get() {
+                    Some(duration) => {
+                        match reactor::Timeout::new(duration, &handle) {
+                            Ok(timeout) => {
+                                Box::new(timeout.then(move |_| Ok(item)))
+                            },
+                            Err(e) => {
+                                eprintln!("unable to inject latency: {}", e);
+
+                                Box::new(future::ok(item))
+                            },
+                        }
+                    },
+                    None => Box::new(future::ok(item)),
+                }
+            },
+        ))
+    }
+}
+
+/// synthetic failures applied to the updates of a single soma
+#[derive(Debug, Clone)]
+pub struct Faults {
+    probability: Rc<Cell<f64>>,
+    rng: Rng,
+}
+
+impl Faults {
+    /// create faults that never trigger
+    pub fn new(rng: Rng) -> Self {
+        Self {
+            probability: Rc::new(Cell::new(0.0)),
+            rng: rng,
+        }
+    }
+
+    /// fail each update with the given probability
+    pub fn set(&self, probability: f64) {
+        self.probability.set(probability.max(0.0).min(1.0));
+    }
+
+    /// decide whether the next update fails
+    ///
+    /// run_soma calls this in place of the soma's update, so a synthetic
+    /// error goes through the soma's restart policy like any other failure.
+    pub fn check(&self) -> Result<(), Error> {
+        let probability = self.probability.get();
+
+        if probability > 0.0 && self.rng.next_f64() < probability {
+            Err(Error::from("synthetic error injected by chaos testing"))
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use futures::prelude::*;
+use futures::stream;
+use futures::unsync::mpsc;
+use tokio_core::reactor;
+use uuid::Uuid;
+
+use super::Error;
+use axon::Constraint;
+use soma::{Impulse, Soma, Synapse};
+
+/// the most synapses generated for a single variadic constraint
+pub const MAX_VARIADIC: usize = 4;
+
+/// a single step of an impulse sequence, before its synapses are formed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Step<S: Synapse> {
+    /// connect a new peer to one of the soma's dendrites
+    AddDendrite(S),
+    /// connect a new peer to one of the soma's terminals
+    AddTerminal(S),
+    /// start the soma
+    Start,
+}
+
+/// turn arbitrary bytes into a valid sequence of steps for a soma
+///
+/// every One constraint is connected exactly once, every Optional constraint
+/// at most once, every Variadic constraint up to MAX_VARIADIC times, and
+/// every Range constraint within its bounds, in an order chosen by the bytes,
+/// followed by a single Start. constraints given by a predicate are never
+/// connected, since there's no telling which synapses they accept. the same
+/// bytes always give the same sequence, so this can be driven by quickcheck,
+/// proptest, or a fuzzer's input.
+pub fn steps<S: Synapse>(
+    dendrites: &[Constraint<S>],
+    terminals: &[Constraint<S>],
+    entropy: &[u8],
+) -> Vec<Step<S>> {
+    let mut bytes = entropy.iter().cloned();
+
+    // pick a number below n, falling back to 0 once the bytes run out
+    let mut pick = move |n: usize| -> usize {
+        match bytes.next() {
+            Some(b) if n > 1 => b as usize % n,
+            _ => 0,
+        }
+    };
+
+    let mut steps = vec![];
+
+    for constraint in dendrites {
+        match constraint {
+            &Constraint::One(synapse) => steps.push(Step::AddDendrite(synapse)),
+            &Constraint::Optional(synapse) => {
+                for _ in 0..pick(2) {
+                    steps.push(Step::AddDendrite(synapse));
+                }
+            },
+            &Constraint::Variadic(synapse) => {
+                for _ in 0..pick(MAX_VARIADIC + 1) {
+                    steps.push(Step::AddDendrite(synapse));
+                }
+            },
+            &Constraint::Range { synapse, min, max } => {
+                for _ in 0..min + pick(max - min + 1) {
+                    steps.push(Step::AddDendrite(synapse));
+                }
+            },
+            // there's no telling which synapses the predicate accepts
+            &Constraint::OneWhere(_) | &Constraint::VariadicWhere(_) => (),
+        }
+    }
+
+    for constraint in terminals {
+        match constraint {
+            &Constraint::One(synapse) => steps.push(Step::AddTerminal(synapse)),
+            &Constraint::Optional(synapse) => {
+                for _ in 0..pick(2) {
+                    steps.push(Step::AddTerminal(synapse));
+                }
+            },
+            &Constraint::Variadic(synapse) => {
+                for _ in 0..pick(MAX_VARIADIC + 1) {
+                    steps.push(Step::AddTerminal(synapse));
+                }
+            },
+            &Constraint::Range { synapse, min, max } => {
+                for _ in 0..min + pick(max - min + 1) {
+                    steps.push(Step::AddTerminal(synapse));
+                }
+            },
+            // there's no telling which synapses the predicate accepts
+            &Constraint::OneWhere(_) | &Constraint::VariadicWhere(_) => (),
+        }
+    }
+
+    for i in (1..steps.len()).rev() {
+        let j = pick(i + 1);
+
+        steps.swap(i, j);
+    }
+
+    steps.push(Step::Start);
+
+    steps
+}
+
+/// the far ends of the synapses formed for an impulse sequence
+///
+/// keep these alive while the soma runs so that its channels stay open.
+#[derive(Debug)]
+pub struct Peers<S: Synapse> {
+    /// terminals paired with the dendrites given to the soma
+    pub terminals: Vec<(Uuid, S::Terminal)>,
+    /// dendrites paired with the terminals given to the soma
+    pub dendrites: Vec<(Uuid, S::Dendrite)>,
+}
+
+/// form the synapses for a sequence of steps and create its impulses
+pub fn impulses<S: Synapse>(
+    steps: &[Step<S>],
+    main_tx: mpsc::Sender<Impulse<S>>,
+    handle: reactor::Handle,
+) -> (Vec<Impulse<S>>, Peers<S>) {
+    let mut peers = Peers {
+        terminals: vec![],
+        dendrites: vec![],
+    };
+
+    let impulses = steps
+        .iter()
+        .map(|step| match step {
+            &Step::AddDendrite(synapse) => {
+                let (tx, rx) = synapse.synapse();
+                let peer = Uuid::new_v4();
+
+                peers.terminals.push((peer, tx));
+
+                Impulse::AddDendrite(peer, synapse, rx)
+            },
+            &Step::AddTerminal(synapse) => {
+                let (tx, rx) = synapse.synapse();
+                let peer = Uuid::new_v4();
+
+                peers.dendrites.push((peer, rx));
+
+                Impulse::AddTerminal(peer, synapse, tx)
+            },
+            &Step::Start => {
+                Impulse::Start(Uuid::new_v4(), main_tx.clone(), handle.clone())
+            },
+        })
+        .collect();
+
+    (impulses, peers)
+}
+
+/// feed each impulse to the soma in order
+///
+/// resolves to the soma after its last update, or to the first error.
+pub fn drive<T: Soma + 'static>(
+    soma: T,
+    impulses: Vec<Impulse<T::Synapse>>,
+) -> Box<Future<Item = T, Error = Error>> {
+    Box::new(stream::iter_ok(impulses).fold(soma, |soma, imp| {
+        soma.update(imp).map_err(|e| -> Error { e.into() })
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    enum TestSynapse {
+        Input,
+        Output,
+    }
+
+    impl Synapse for TestSynapse {
+        type Terminal = ();
+        type Dendrite = ();
+
+        fn synapse(self) -> ((), ()) {
+            ((), ())
+        }
+    }
+
+    #[test]
+    fn test_valid_steps() {
+        let dendrites = [Constraint::Variadic(TestSynapse::Input)];
+        let terminals = [Constraint::One(TestSynapse::Output)];
+
+        for seed in 0..256 {
+            let entropy: Vec<u8> = (0..16)
+                .map(|i| (seed as u8).wrapping_mul(31).wrapping_add(i))
+                .collect();
+
+            let steps = steps(&dendrites, &terminals, &entropy);
+
+            assert_eq!(steps.last(), Some(&Step::Start));
+            assert_eq!(
+                steps.iter().filter(|s| **s == Step::Start).count(),
+                1
+            );
+            assert_eq!(
+                steps
+                    .iter()
+                    .filter(|s| **s == Step::AddTerminal(TestSynapse::Output))
+                    .count(),
+                1
+            );
+            assert!(
+                steps
+                    .iter()
+                    .filter(|s| **s == Step::AddDendrite(TestSynapse::Input))
+                    .count() <= MAX_VARIADIC
+            );
+        }
+    }
+}
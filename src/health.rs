@@ -0,0 +1,255 @@
+use std::time::Duration;
+
+use futures::future::{self, Either};
+use futures::prelude::*;
+use futures::unsync::{mpsc, oneshot};
+use tokio_core::reactor;
+use uuid::Uuid;
+
+use super::{Error, Result};
+use axon::{Axon, Constraint};
+use soma::{self, Impulse};
+use watch::{self, Publisher, Watcher};
+
+/// health reported by a single soma
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub enum Status {
+    /// the soma is working as intended
+    Healthy,
+    /// the soma is not working as intended, for the given reason
+    Unhealthy(String),
+}
+
+/// the aggregate health of every soma connected to the health soma
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Report {
+    /// the number of somas that were checked
+    pub checked: usize,
+    /// the somas that are unhealthy along with the reason
+    pub unhealthy: Vec<(Uuid, String)>,
+}
+
+impl Report {
+    /// check if every soma is healthy
+    pub fn is_healthy(&self) -> bool {
+        self.unhealthy.is_empty()
+    }
+}
+
+/// the synapse for health checks
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Synapse {
+    /// a synapse used to check the health of a soma
+    Health,
+}
+
+#[derive(Debug)]
+enum Request {
+    Check(oneshot::Sender<Status>),
+}
+
+/// sender for a health check
+#[derive(Debug, Clone)]
+pub struct Terminal {
+    tx: mpsc::Sender<Request>,
+}
+
+impl Terminal {
+    /// ask the soma on the other end for its health
+    #[async]
+    pub fn check(self) -> Result<Status> {
+        let (tx, rx) = oneshot::channel();
+
+        await!(
+            self.tx
+                .send(Request::Check(tx))
+                .map(|_| ())
+                .map_err(|_| Error::from("unable to send health check"))
+        )?;
+
+        await!(rx.map_err(|_| Error::from("unable to receive health status")))
+    }
+}
+
+/// receiver for a health check
+#[derive(Debug)]
+pub struct Dendrite {
+    rx: mpsc::Receiver<Request>,
+}
+
+impl Dendrite {
+    /// answer health checks using the given function
+    ///
+    /// call this from the Start handler of the soma being checked.
+    pub fn serve<F>(self, handle: &reactor::Handle, status: F)
+    where
+        F: Fn() -> Status + 'static,
+    {
+        handle.spawn(self.rx.for_each(move |req| {
+            match req {
+                Request::Check(tx) => {
+                    if let Err(_) = tx.send(status()) {
+                        // checker does not care anymore
+                    }
+                },
+            }
+
+            Ok(())
+        }));
+    }
+}
+
+/// create a junction between a health soma and a checked soma
+pub fn synapse() -> (Terminal, Dendrite) {
+    let (tx, rx) = mpsc::channel(1);
+
+    (Terminal { tx: tx }, Dendrite { rx: rx })
+}
+
+impl soma::Synapse for Synapse {
+    type Terminal = Terminal;
+    type Dendrite = Dendrite;
+
+    fn synapse(self) -> (Terminal, Dendrite) {
+        match self {
+            Synapse::Health => synapse(),
+        }
+    }
+}
+
+/// soma that periodically checks the health of every soma it is connected to
+///
+/// connect the health soma to each soma that should be checked with
+/// Synapse::Health. each checked soma serves its health with
+/// Dendrite::serve. the aggregate report is published through the watcher
+/// returned by axon, so it can back a readiness endpoint. a soma that does
+/// not answer within the interval is reported unhealthy.
+pub struct Soma {
+    interval: Duration,
+    publisher: Option<Publisher<Report>>,
+    terminals: Vec<(Uuid, Terminal)>,
+}
+
+impl Soma {
+    /// create a health soma that polls at the given interval
+    pub fn axon(interval: Duration) -> (Axon<Self>, Watcher<Report>) {
+        let (publisher, watcher) = watch::channel(Report {
+            checked: 0,
+            unhealthy: vec![],
+        });
+
+        (
+            Axon::new(
+                Self {
+                    interval: interval,
+                    publisher: Some(publisher),
+                    terminals: vec![],
+                },
+                vec![],
+                vec![Constraint::Variadic(Synapse::Health)],
+            ),
+            watcher,
+        )
+    }
+}
+
+impl soma::Soma for Soma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddTerminal(uuid, Synapse::Health, tx) => {
+                self.terminals.push((uuid, tx));
+
+                Ok(self)
+            },
+
+            Impulse::Start(_, main_tx, handle) => {
+                let publisher = match self.publisher {
+                    Some(publisher) => publisher,
+                    None => bail!("health soma was already started"),
+                };
+
+                handle.spawn(
+                    HealthTask::run(
+                        self.interval,
+                        self.terminals,
+                        publisher,
+                        handle.clone(),
+                    ).or_else(move |e| {
+                        main_tx
+                            .send(Impulse::Error(e))
+                            .map(|_| ())
+                            .map_err(|_| ())
+                    }),
+                );
+
+                Ok(Self {
+                    interval: self.interval,
+                    publisher: None,
+                    terminals: vec![],
+                })
+            },
+
+            imp => Err(imp.unexpected(&self)),
+        }
+    }
+}
+
+struct HealthTask;
+
+impl HealthTask {
+    #[async]
+    fn run(
+        interval: Duration,
+        terminals: Vec<(Uuid, Terminal)>,
+        publisher: Publisher<Report>,
+        handle: reactor::Handle,
+    ) -> Result<()> {
+        #[async]
+        for _ in reactor::Interval::new(interval, &handle)? {
+            let mut checks = vec![];
+
+            for &(uuid, ref terminal) in &terminals {
+                let timeout = reactor::Timeout::new(interval, &handle)?;
+
+                checks.push(terminal.clone().check().select2(timeout).then(
+                    move |result| {
+                        Ok::<_, Error>((
+                            uuid,
+                            match result {
+                                Ok(Either::A((status, _))) => status,
+                                Ok(Either::B(_)) => Status::Unhealthy(
+                                    "health check timed out".into(),
+                                ),
+                                Err(Either::A((e, _))) => {
+                                    Status::Unhealthy(e.to_string())
+                                },
+                                Err(Either::B((e, _))) => {
+                                    Status::Unhealthy(e.to_string())
+                                },
+                            },
+                        ))
+                    },
+                ));
+            }
+
+            let statuses = await!(future::join_all(checks))?;
+
+            publisher.publish(Report {
+                checked: statuses.len(),
+                unhealthy: statuses
+                    .into_iter()
+                    .filter_map(|(uuid, status)| match status {
+                        Status::Healthy => None,
+                        Status::Unhealthy(reason) => Some((uuid, reason)),
+                    })
+                    .collect(),
+            });
+        }
+
+        Ok(())
+    }
+}
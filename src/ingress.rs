@@ -0,0 +1,346 @@
+use std::net::SocketAddr;
+use std::rc::Rc;
+
+use futures::future;
+use futures::prelude::*;
+use hyper;
+use hyper::server::{Http, Service};
+use serde::de::DeserializeOwned;
+use serde_json;
+use tokio_core::reactor;
+
+use super::{Error, Result};
+use axon::{Axon, Constraint};
+use soma::{self, Impulse, Synapse};
+
+/// the outcome of handing a signal to the ingress soma's terminal
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Delivery {
+    /// the signal was sent downstream
+    Accepted,
+    /// the downstream channel is full, try again later
+    Full,
+    /// the downstream channel is closed
+    Closed,
+}
+
+/// ingress settings
+#[derive(Debug, Clone)]
+pub struct Settings {
+    addr: SocketAddr,
+}
+
+impl Settings {
+    /// set the address that the ingress server binds to
+    pub fn addr(self, addr: SocketAddr) -> Self {
+        Self { addr: addr }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            addr: ([127, 0, 0, 1], 8081).into(),
+        }
+    }
+}
+
+type Deserialize<P> = Rc<Fn(&[u8]) -> Result<P>>;
+type Deliver<S, P> = Rc<Fn(&<S as Synapse>::Terminal, P) -> Delivery>;
+
+/// soma that accepts signals POSTed as JSON over HTTP
+///
+/// each request body is deserialized into a signal and handed to `deliver`
+/// along with the soma's terminal. `deliver` should attempt a non-blocking
+/// send and report whether the signal was accepted. malformed bodies are
+/// answered with 400 Bad Request and a full downstream channel with 503
+/// Service Unavailable.
+pub struct Soma<S: Synapse, P> {
+    settings: Settings,
+    deserialize: Deserialize<P>,
+    deliver: Deliver<S, P>,
+    terminal: Option<S::Terminal>,
+}
+
+impl<S, P> Soma<S, P>
+where
+    S: Synapse + 'static,
+    S::Terminal: 'static,
+    P: 'static,
+{
+    /// create an ingress soma that outputs through the given synapse
+    pub fn axon<F>(settings: Settings, synapse: S, deliver: F) -> Axon<Self>
+    where
+        P: DeserializeOwned,
+        F: Fn(&S::Terminal, P) -> Delivery + 'static,
+    {
+        Self::axon_with(
+            settings,
+            synapse,
+            |body: &[u8]| Ok(serde_json::from_slice(body)?),
+            deliver,
+        )
+    }
+
+    /// create an ingress soma with a custom signal deserializer
+    pub fn axon_with<D, F>(
+        settings: Settings,
+        synapse: S,
+        deserialize: D,
+        deliver: F,
+    ) -> Axon<Self>
+    where
+        D: Fn(&[u8]) -> Result<P> + 'static,
+        F: Fn(&S::Terminal, P) -> Delivery + 'static,
+    {
+        Axon::new(
+            Self {
+                settings: settings,
+                deserialize: Rc::new(deserialize),
+                deliver: Rc::new(deliver),
+                terminal: None,
+            },
+            vec![],
+            vec![Constraint::One(synapse)],
+        )
+    }
+}
+
+impl<S, P> soma::Soma for Soma<S, P>
+where
+    S: Synapse + 'static,
+    S::Terminal: 'static,
+    P: 'static,
+{
+    type Synapse = S;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddTerminal(_, _, tx) => {
+                self.terminal = Some(tx);
+
+                Ok(self)
+            },
+
+            Impulse::Start(_, main_tx, handle) => {
+                let terminal = match self.terminal {
+                    Some(terminal) => Rc::new(terminal),
+                    None => bail!("ingress has no terminal"),
+                };
+
+                handle.spawn(
+                    IngressTask::run::<S, P>(
+                        self.settings.addr,
+                        IngressService {
+                            terminal: terminal,
+                            deserialize: Rc::clone(&self.deserialize),
+                            deliver: Rc::clone(&self.deliver),
+                        },
+                        handle.clone(),
+                    ).or_else(move |e| {
+                        main_tx
+                            .send(Impulse::Error(e))
+                            .map(|_| ())
+                            .map_err(|_| ())
+                    }),
+                );
+
+                Ok(Self {
+                    settings: self.settings,
+                    deserialize: self.deserialize,
+                    deliver: self.deliver,
+                    terminal: None,
+                })
+            },
+
+            imp => Err(imp.unexpected(&self)),
+        }
+    }
+}
+
+struct IngressTask;
+
+impl IngressTask {
+    #[async]
+    fn run<S, P>(
+        addr: SocketAddr,
+        service: IngressService<S, P>,
+        handle: reactor::Handle,
+    ) -> Result<()>
+    where
+        S: Synapse + 'static,
+        S::Terminal: 'static,
+        P: 'static,
+    {
+        let stream_handle = handle.clone();
+
+        await!(
+            Http::new()
+                .serve_addr_handle(&addr, &handle, move || Ok(service.clone()))?
+                .for_each(move |connection| {
+                    stream_handle.spawn(connection.map(|_| ()).or_else(
+                        move |e| {
+                            eprintln!(
+                                "error while serving HTTP request - {:?}",
+                                e
+                            );
+
+                            Ok(())
+                        },
+                    ));
+
+                    Ok(())
+                })
+        )?;
+
+        Ok(())
+    }
+}
+
+struct IngressService<S: Synapse, P> {
+    terminal: Rc<S::Terminal>,
+    deserialize: Deserialize<P>,
+    deliver: Deliver<S, P>,
+}
+
+impl<S: Synapse, P> Clone for IngressService<S, P> {
+    fn clone(&self) -> Self {
+        Self {
+            terminal: Rc::clone(&self.terminal),
+            deserialize: Rc::clone(&self.deserialize),
+            deliver: Rc::clone(&self.deliver),
+        }
+    }
+}
+
+impl<S, P> Service for IngressService<S, P>
+where
+    S: Synapse + 'static,
+    S::Terminal: 'static,
+    P: 'static,
+{
+    type Request = hyper::Request;
+    type Response = hyper::Response;
+    type Error = hyper::Error;
+    type Future = Box<Future<Item = Self::Response, Error = Self::Error>>;
+
+    fn call(&self, req: hyper::Request) -> Self::Future {
+        if req.method() != &hyper::Method::Post {
+            let mut rsp = hyper::Response::new();
+            rsp.set_status(hyper::StatusCode::MethodNotAllowed);
+            rsp.set_body("signals must be POSTed");
+
+            return Box::new(future::ok(rsp));
+        }
+
+        let service = self.clone();
+
+        Box::new(req.body().concat2().map(move |body| {
+            let mut rsp = hyper::Response::new();
+
+            match (service.deserialize)(&body) {
+                Ok(signal) => {
+                    match (service.deliver)(&service.terminal, signal) {
+                        Delivery::Accepted => {
+                            rsp.set_status(hyper::StatusCode::Accepted)
+                        },
+                        Delivery::Full => {
+                            rsp.set_status(
+                                hyper::StatusCode::ServiceUnavailable,
+                            );
+                            rsp.set_body("downstream channel is full");
+                        },
+                        Delivery::Closed => {
+                            rsp.set_status(
+                                hyper::StatusCode::ServiceUnavailable,
+                            );
+                            rsp.set_body("downstream channel is closed");
+                        },
+                    }
+                },
+                Err(e) => {
+                    rsp.set_status(hyper::StatusCode::BadRequest);
+                    rsp.set_body(format!("malformed signal - {}", e));
+                },
+            }
+
+            rsp
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    type Values = Rc<RefCell<Vec<u32>>>;
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    enum TestSynapse {
+        Value,
+    }
+
+    impl Synapse for TestSynapse {
+        type Terminal = Values;
+        type Dendrite = Values;
+
+        fn synapse(self) -> (Values, Values) {
+            let values = Rc::new(RefCell::new(vec![]));
+
+            (Rc::clone(&values), values)
+        }
+    }
+
+    #[test]
+    fn test_responses() {
+        let mut core = reactor::Core::new().unwrap();
+
+        let (terminal, values) = TestSynapse::Value.synapse();
+        let service: IngressService<TestSynapse, u32> = IngressService {
+            terminal: Rc::new(terminal),
+            deserialize: Rc::new(|body: &[u8]| -> Result<u32> {
+                Ok(serde_json::from_slice(body)?)
+            }),
+            // the downstream channel only has room for a single signal
+            deliver: Rc::new(|values: &Values, value| {
+                if values.borrow().is_empty() {
+                    values.borrow_mut().push(value);
+
+                    Delivery::Accepted
+                } else {
+                    Delivery::Full
+                }
+            }),
+        };
+
+        let mut send = |method, body: &str| {
+            let mut req = hyper::Request::new(method, "/".parse().unwrap());
+            req.set_body(body.to_string());
+
+            core.run(service.call(req)).unwrap().status()
+        };
+
+        assert_eq!(
+            send(hyper::Method::Get, "1"),
+            hyper::StatusCode::MethodNotAllowed
+        );
+        assert_eq!(
+            send(hyper::Method::Post, "not json"),
+            hyper::StatusCode::BadRequest
+        );
+        assert_eq!(
+            send(hyper::Method::Post, "1"),
+            hyper::StatusCode::Accepted
+        );
+        assert_eq!(
+            send(hyper::Method::Post, "2"),
+            hyper::StatusCode::ServiceUnavailable
+        );
+
+        assert_eq!(*values.borrow(), vec![1]);
+    }
+}
@@ -16,26 +16,92 @@ extern crate tokio;
 extern crate tokio_core;
 extern crate uuid;
 
-#[cfg(feature = "visualizer")]
+#[cfg(feature = "topology")]
+extern crate serde_yaml;
+#[cfg(feature = "topology")]
+extern crate yaml_rust;
+
+#[cfg(any(feature = "visualizer", feature = "ingress"))]
 extern crate hyper;
 #[cfg(feature = "visualizer")]
 extern crate open;
 
 mod axon;
+#[cfg(feature = "chaos")]
+mod chaos;
+mod mailbox;
+/// update latency histograms for somas
+#[cfg(feature = "metrics")]
+pub mod metrics;
 mod organelle;
+mod pause;
 mod soma;
 
 /// visualization soma used to visualize the results of a probe
 #[cfg(feature = "visualizer")]
 pub mod visualizer;
 
+/// helpers for fuzzing somas with valid impulse sequences
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+
+/// soma that aggregates the health of other somas
+pub mod health;
+
+/// soma that accepts signals from external systems over HTTP
+#[cfg(feature = "ingress")]
+pub mod ingress;
+
+/// soma that transforms signals between a dendrite and a terminal
+pub mod map;
+
 /// probe soma used to inspect the internal structure of an organelle
+///
+/// this is always available and does not pull in the visualizer or hyper.
 pub mod probe;
 
-pub use axon::{Axon, Constraint};
-pub use organelle::Organelle;
-pub use probe::{ConstraintData, SomaData};
-pub use soma::{Impulse, Soma, Synapse};
+/// soma that replays a recorded log of signals
+pub mod replay;
+
+/// soma that buffers signals to disk when they exceed a memory threshold
+pub mod spill;
+
+/// soma that bridges an external stream into the organelle
+pub mod source;
+
+/// soma that bridges signals over stdin and stdout
+pub mod stdio;
+
+/// declarative organelle definitions loaded from YAML
+#[cfg(feature = "topology")]
+pub mod topology;
+
+/// soma that distributes tasks to workers with acknowledgments
+pub mod workqueue;
+
+/// channel used by somas to publish their state to observers
+pub mod watch;
+
+/// delivery protocols that can be layered on top of synapses
+pub mod synapse;
+
+pub use axon::{Axon, Constraint, MapSynapse};
+pub use mailbox::OverflowPolicy;
+pub use organelle::{
+    Connection, Organelle, OrganelleBuilder, OrganelleConfig, ResultSender,
+    RestartPolicy, SomaHandle, SomaSettings, TopologyEvent,
+};
+pub use probe::{
+    ConstraintData, ConstraintStatus, EdgeData, FlatSomaData, GraphJson,
+    GraphNode, SomaData, SomaMetrics,
+};
+pub use soma::{
+    ask, from_stream, map, recording, AskDendrite, AskTerminal, Constraints,
+    Impulse, ImpulseKind, ImpulseRecord, RecordHandle, RecordingSoma, Soma,
+    Synapse,
+};
+
+use uuid::Uuid;
 
 /// organelle error
 error_chain! {
@@ -46,10 +112,13 @@ error_chain! {
 
         Canceled(futures::Canceled) #[doc = "glue for futures::Canceled"];
         SerdeJson(serde_json::Error) #[doc = "glue for serde_json::Error"];
+        SerdeYaml(serde_yaml::Error)
+            #[cfg(feature = "topology")]
+            #[doc = "glue for serde_yaml::Error"];
 
 
         Hyper(hyper::Error)
-            #[cfg(feature = "visualizer")]
+            #[cfg(any(feature = "visualizer", feature = "ingress"))]
             #[doc = "glue for hyper::Error"];
 
         AddrParse(std::net::AddrParseError)
@@ -69,6 +138,30 @@ error_chain! {
             display("invalid synapse given to somas - {}", msg)
         }
 
+        /// a soma's mailbox was full and its overflow policy is Error
+        MailboxFull(soma: Uuid) {
+            description("soma mailbox is full"),
+            display("mailbox for soma {} is full", soma)
+        }
+
+        /// adding a soma would exceed the organelle's max_somas
+        TooManySomas(max: usize) {
+            description("too many somas in organelle"),
+            display("organelle is limited to {} somas", max)
+        }
+
+        /// a topology document could not be built into an organelle
+        InvalidTopology(msg: String) {
+            description("invalid topology"),
+            display("invalid topology - {}", msg)
+        }
+
+        /// somas did not finish handling Start within the start timeout
+        StartTimeout(somas: Vec<Uuid>) {
+            description("somas did not start in time"),
+            display("somas did not start in time - {:?}", somas)
+        }
+
         /// axon is missing a synapse
         MissingSynapse(msg: String) {
             description("missing synapse"),
@@ -77,7 +170,7 @@ error_chain! {
     }
 }
 
-#[cfg(feature = "visualizer")]
+#[cfg(any(feature = "visualizer", feature = "ingress"))]
 impl From<Error> for hyper::Error {
     fn from(e: Error) -> Self {
         hyper::Error::Io(std::io::Error::new(
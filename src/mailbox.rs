@@ -0,0 +1,475 @@
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+
+use futures::future;
+use futures::prelude::*;
+use futures::unsync::mpsc;
+use uuid::Uuid;
+
+use super::{Error, ErrorKind};
+use soma::{Impulse, Synapse};
+
+/// what to do when a soma's mailbox is full
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// wait until the soma has room for the impulse
+    ///
+    /// this is the default behavior. keep in mind that blocking can cascade
+    /// into deadlocks if two somas are waiting on each other.
+    Block,
+    /// drop the impulse that was about to be sent
+    ///
+    /// impulses that can't be dropped (see Droppable) are queued anyway.
+    /// each drop is counted and reported to the organelle's topology
+    /// subscribers with TopologyEvent::ImpulseDropped.
+    DropNewest,
+    /// drop the oldest impulse waiting in the mailbox to make room
+    ///
+    /// only droppable impulses are dropped, so the oldest one that can be
+    /// dropped makes room instead. if there isn't one, the new impulse is
+    /// queued anyway or dropped itself.
+    DropOldest,
+    /// fail the send with a MailboxFull error
+    Error,
+}
+
+/// items that a mailbox may drop when it overflows
+pub trait Droppable {
+    /// whether the drop policies may drop this item
+    fn droppable(&self) -> bool;
+}
+
+impl<R: Synapse> Droppable for Impulse<R> {
+    fn droppable(&self) -> bool {
+        match self {
+            // the organelle answers a dropped probe on the soma's behalf
+            &Impulse::Probe(_, _) => true,
+            // the rest wire up, start, stop or pause the soma, and losing
+            // any of them leaves it or its peers waiting forever
+            _ => false,
+        }
+    }
+}
+
+impl Default for OverflowPolicy {
+    fn default() -> Self {
+        OverflowPolicy::Block
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    soma: Uuid,
+    capacity: usize,
+    queued: Cell<usize>,
+    droppable: Cell<usize>,
+    skip: Cell<usize>,
+    dropped: Cell<usize>,
+}
+
+impl State {
+    fn record_drop(&self) {
+        self.dropped.set(self.dropped.get() + 1);
+    }
+}
+
+#[derive(Debug)]
+enum Sender<I> {
+    Bounded(mpsc::Sender<I>),
+    Unbounded(mpsc::UnboundedSender<I>),
+}
+
+impl<I> Clone for Sender<I> {
+    fn clone(&self) -> Self {
+        match self {
+            &Sender::Bounded(ref tx) => Sender::Bounded(tx.clone()),
+            &Sender::Unbounded(ref tx) => Sender::Unbounded(tx.clone()),
+        }
+    }
+}
+
+/// the sending half of a soma's inbound channel
+pub struct Mailbox<I> {
+    policy: OverflowPolicy,
+    tx: Sender<I>,
+    state: Rc<State>,
+    on_drop: Rc<Fn(I)>,
+}
+
+impl<I> Clone for Mailbox<I> {
+    fn clone(&self) -> Self {
+        Self {
+            policy: self.policy,
+            tx: self.tx.clone(),
+            state: Rc::clone(&self.state),
+            on_drop: Rc::clone(&self.on_drop),
+        }
+    }
+}
+
+impl<I> fmt::Debug for Mailbox<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Mailbox")
+            .field("policy", &self.policy)
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+impl<I: Droppable + 'static> Mailbox<I> {
+    /// send an item to the soma, applying the overflow policy if it is full
+    pub fn send(self, item: I) -> Box<Future<Item = Self, Error = Error>> {
+        let Mailbox {
+            policy,
+            tx,
+            state,
+            on_drop,
+        } = self;
+
+        match tx {
+            Sender::Bounded(tx) => Box::new(
+                tx.send(item)
+                    .map(move |tx| {
+                        // the item is queued in the same poll that resolves
+                        // the send, so the soma can't receive it uncounted
+                        state.queued.set(state.queued.get() + 1);
+
+                        Mailbox {
+                            policy: policy,
+                            tx: Sender::Bounded(tx),
+                            state: state,
+                            on_drop: on_drop,
+                        }
+                    })
+                    .map_err(|_| Error::from("soma mailbox is closed")),
+            ),
+            Sender::Unbounded(tx) => {
+                let droppable = item.droppable();
+
+                if state.queued.get() >= state.capacity {
+                    match policy {
+                        OverflowPolicy::Error => {
+                            return Box::new(future::err(
+                                ErrorKind::MailboxFull(state.soma).into(),
+                            ))
+                        },
+                        OverflowPolicy::DropOldest
+                            if state.droppable.get() > 0 =>
+                        {
+                            // the receiver skips the oldest droppable item
+                            state.skip.set(state.skip.get() + 1);
+                            state.queued.set(state.queued.get() - 1);
+                            state.droppable.set(state.droppable.get() - 1);
+
+                            state.record_drop();
+                        },
+                        OverflowPolicy::DropNewest
+                        | OverflowPolicy::DropOldest if droppable =>
+                        {
+                            state.record_drop();
+                            on_drop(item);
+
+                            return Box::new(future::ok(Mailbox {
+                                policy: policy,
+                                tx: Sender::Unbounded(tx),
+                                state: state,
+                                on_drop: on_drop,
+                            }));
+                        },
+                        // nothing can be dropped, so the mailbox goes over
+                        // its capacity instead
+                        OverflowPolicy::DropNewest
+                        | OverflowPolicy::DropOldest => (),
+                        OverflowPolicy::Block => unreachable!(),
+                    }
+                }
+
+                if let Err(_) = tx.unbounded_send(item) {
+                    return Box::new(future::err(Error::from(
+                        "soma mailbox is closed",
+                    )));
+                }
+
+                state.queued.set(state.queued.get() + 1);
+
+                if droppable {
+                    state.droppable.set(state.droppable.get() + 1);
+                }
+
+                Box::new(future::ok(Mailbox {
+                    policy: policy,
+                    tx: Sender::Unbounded(tx),
+                    state: state,
+                    on_drop: on_drop,
+                }))
+            },
+        }
+    }
+
+    /// the number of impulses dropped due to overflow
+    ///
+    /// this is also reported for each soma in the organelle's probe data.
+    pub fn dropped(&self) -> usize {
+        self.state.dropped.get()
+    }
+
+    /// the number of impulses waiting to be received by the soma
+    pub fn queued(&self) -> usize {
+        self.state.queued.get()
+    }
+}
+
+/// create a mailbox for the given soma along with its receiving end
+///
+/// every item dropped by the overflow policy is handed to on_drop.
+pub fn channel<I, F>(
+    soma: Uuid,
+    capacity: usize,
+    policy: OverflowPolicy,
+    on_drop: F,
+) -> (Mailbox<I>, Box<Stream<Item = I, Error = ()>>)
+where
+    I: Droppable + 'static,
+    F: Fn(I) + 'static,
+{
+    let on_drop: Rc<Fn(I)> = Rc::new(on_drop);
+    let state = Rc::new(State {
+        soma: soma,
+        capacity: capacity,
+        queued: Cell::new(0),
+        droppable: Cell::new(0),
+        skip: Cell::new(0),
+        dropped: Cell::new(0),
+    });
+
+    match policy {
+        OverflowPolicy::Block => {
+            let (tx, rx) = mpsc::channel(capacity);
+            let rx_state = Rc::clone(&state);
+
+            (
+                Mailbox {
+                    policy: policy,
+                    tx: Sender::Bounded(tx),
+                    state: state,
+                    on_drop: on_drop,
+                },
+                Box::new(rx.map(move |item| {
+                    rx_state.queued.set(rx_state.queued.get() - 1);
+                    item
+                })),
+            )
+        },
+        _ => {
+            let (tx, rx) = mpsc::unbounded();
+            let rx_state = Rc::clone(&state);
+            let rx_on_drop = Rc::clone(&on_drop);
+
+            (
+                Mailbox {
+                    policy: policy,
+                    tx: Sender::Unbounded(tx),
+                    state: state,
+                    on_drop: on_drop,
+                },
+                Box::new(rx.filter_map(move |item| {
+                    let droppable = item.droppable();
+                    let skip = rx_state.skip.get();
+
+                    if droppable && skip > 0 {
+                        rx_state.skip.set(skip - 1);
+                        rx_on_drop(item);
+
+                        None
+                    } else {
+                        rx_state.queued.set(rx_state.queued.get() - 1);
+
+                        if droppable {
+                            let left = rx_state.droppable.get() - 1;
+                            rx_state.droppable.set(left);
+                        }
+
+                        Some(item)
+                    }
+                })),
+            )
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    use tokio_core::reactor;
+
+    impl Droppable for u32 {
+        fn droppable(&self) -> bool {
+            true
+        }
+    }
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    enum Item {
+        Data(u32),
+        Control(u32),
+    }
+
+    impl Droppable for Item {
+        fn droppable(&self) -> bool {
+            match *self {
+                Item::Data(_) => true,
+                Item::Control(_) => false,
+            }
+        }
+    }
+
+    /// a channel that keeps the items it drops
+    fn channel_with_drops<I: Droppable + 'static>(
+        capacity: usize,
+        policy: OverflowPolicy,
+    ) -> (Mailbox<I>, Box<Stream<Item = I, Error = ()>>, Rc<RefCell<Vec<I>>>)
+    {
+        let drops = Rc::new(RefCell::new(vec![]));
+        let on_drop = Rc::clone(&drops);
+
+        let (tx, rx) = channel(Uuid::new_v4(), capacity, policy, move |item| {
+            on_drop.borrow_mut().push(item)
+        });
+
+        (tx, rx, drops)
+    }
+
+    fn saturate(policy: OverflowPolicy) -> (Vec<u32>, usize, Vec<u32>) {
+        let mut core = reactor::Core::new().unwrap();
+
+        let (tx, rx, drops) = channel_with_drops::<u32>(2, policy);
+
+        let tx = core.run(tx.send(1)).unwrap();
+        let tx = core.run(tx.send(2)).unwrap();
+
+        let result = core.run(tx.clone().send(3));
+        let dropped = tx.dropped();
+
+        match policy {
+            OverflowPolicy::Error => match result {
+                Err(Error(ErrorKind::MailboxFull(_), _)) => (),
+                _ => panic!("expected the mailbox to be full"),
+            },
+            _ => assert!(result.is_ok()),
+        }
+
+        drop(tx);
+
+        let received = core.run(rx.collect()).unwrap();
+        let drops = drops.borrow().clone();
+
+        (received, dropped, drops)
+    }
+
+    #[test]
+    fn test_block() {
+        let mut core = reactor::Core::new().unwrap();
+
+        let (tx, _rx, _) = channel_with_drops::<u32>(2, OverflowPolicy::Block);
+
+        let tx = core.run(tx.send(1)).unwrap();
+        let tx = core.run(tx.send(2)).unwrap();
+
+        let mut send = tx.send(3);
+
+        let ready = core.run(future::lazy(move || {
+            Ok::<bool, ()>(match send.poll() {
+                Ok(Async::NotReady) => false,
+                _ => true,
+            })
+        })).unwrap();
+
+        assert!(!ready, "send should block on a full mailbox");
+    }
+
+    #[test]
+    fn test_queued() {
+        let mut core = reactor::Core::new().unwrap();
+
+        for &policy in &[OverflowPolicy::Block, OverflowPolicy::DropNewest] {
+            let (tx, rx, _) = channel_with_drops::<u32>(2, policy);
+
+            let tx = core.run(tx.send(1)).unwrap();
+            let tx = core.run(tx.send(2)).unwrap();
+
+            assert_eq!(tx.queued(), 2);
+
+            let (_, rx) = core.run(rx.into_future()).map_err(|_| ()).unwrap();
+
+            assert_eq!(tx.queued(), 1);
+
+            drop(tx);
+            drop(rx);
+        }
+    }
+
+    #[test]
+    fn test_drop_newest() {
+        assert_eq!(
+            saturate(OverflowPolicy::DropNewest),
+            (vec![1, 2], 1, vec![3])
+        );
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        assert_eq!(
+            saturate(OverflowPolicy::DropOldest),
+            (vec![2, 3], 1, vec![1])
+        );
+    }
+
+    #[test]
+    fn test_error() {
+        assert_eq!(saturate(OverflowPolicy::Error), (vec![1, 2], 0, vec![]));
+    }
+
+    #[test]
+    fn test_control_is_never_dropped() {
+        let mut core = reactor::Core::new().unwrap();
+
+        let (tx, rx, drops) =
+            channel_with_drops::<Item>(2, OverflowPolicy::DropNewest);
+
+        let tx = core.run(tx.send(Item::Data(1))).unwrap();
+        let tx = core.run(tx.send(Item::Data(2))).unwrap();
+        let tx = core.run(tx.send(Item::Control(3))).unwrap();
+        let tx = core.run(tx.send(Item::Data(4))).unwrap();
+
+        assert_eq!(tx.dropped(), 1);
+        assert_eq!(*drops.borrow(), vec![Item::Data(4)]);
+        drop(tx);
+
+        assert_eq!(
+            core.run(rx.collect()).unwrap(),
+            vec![Item::Data(1), Item::Data(2), Item::Control(3)]
+        );
+
+        let (tx, rx, drops) =
+            channel_with_drops::<Item>(2, OverflowPolicy::DropOldest);
+
+        let tx = core.run(tx.send(Item::Control(1))).unwrap();
+        let tx = core.run(tx.send(Item::Data(2))).unwrap();
+        let tx = core.run(tx.send(Item::Data(3))).unwrap();
+        let tx = core.run(tx.send(Item::Control(4))).unwrap();
+
+        assert_eq!(tx.dropped(), 2);
+        drop(tx);
+
+        // the oldest droppable items made room, not the oldest items
+        assert_eq!(
+            core.run(rx.collect()).unwrap(),
+            vec![Item::Control(1), Item::Control(4)]
+        );
+        assert_eq!(*drops.borrow(), vec![Item::Data(2), Item::Data(3)]);
+    }
+}
@@ -0,0 +1,124 @@
+use std::rc::Rc;
+
+use futures::prelude::*;
+
+use super::{Error, Result};
+use axon::{Axon, Constraint};
+use soma::{self, Impulse, Synapse};
+
+type Receive<S, P> = Rc<
+    Fn(<S as Synapse>::Dendrite) -> Box<Stream<Item = P, Error = Error>>,
+>;
+type Transform<P, Q> = Rc<Fn(P) -> Q>;
+type Emit<S, Q> = Rc<
+    Fn(&<S as Synapse>::Terminal, Q) -> Box<Future<Item = (), Error = Error>>,
+>;
+
+/// soma that transforms each signal before passing it on
+///
+/// signals are read from the input dendrite with `receive`, transformed with
+/// `f`, and handed to `emit` along with the output terminal, in the order
+/// they were received. this replaces adapter somas that only differ in the
+/// closure they apply.
+pub struct Soma<S: Synapse, P, Q> {
+    receive: Receive<S, P>,
+    transform: Transform<P, Q>,
+    emit: Emit<S, Q>,
+    input: Option<S::Dendrite>,
+    output: Option<S::Terminal>,
+}
+
+impl<S, P, Q> Soma<S, P, Q>
+where
+    S: Synapse + 'static,
+    S::Dendrite: 'static,
+    S::Terminal: 'static,
+    P: 'static,
+    Q: 'static,
+{
+    /// create a map soma between the input and output synapses
+    pub fn axon<R, F, E>(
+        input: S,
+        output: S,
+        receive: R,
+        f: F,
+        emit: E,
+    ) -> Axon<Self>
+    where
+        R: Fn(S::Dendrite) -> Box<Stream<Item = P, Error = Error>> + 'static,
+        F: Fn(P) -> Q + 'static,
+        E: Fn(&S::Terminal, Q) -> Box<Future<Item = (), Error = Error>>
+            + 'static,
+    {
+        Axon::new(
+            Self {
+                receive: Rc::new(receive),
+                transform: Rc::new(f),
+                emit: Rc::new(emit),
+                input: None,
+                output: None,
+            },
+            vec![Constraint::One(input)],
+            vec![Constraint::One(output)],
+        )
+    }
+}
+
+impl<S, P, Q> soma::Soma for Soma<S, P, Q>
+where
+    S: Synapse + 'static,
+    S::Dendrite: 'static,
+    S::Terminal: 'static,
+    P: 'static,
+    Q: 'static,
+{
+    type Synapse = S;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddDendrite(_, _, rx) => {
+                self.input = Some(rx);
+
+                Ok(self)
+            },
+            Impulse::AddTerminal(_, _, tx) => {
+                self.output = Some(tx);
+
+                Ok(self)
+            },
+
+            Impulse::Start(_, main_tx, handle) => {
+                let input = match self.input.take() {
+                    Some(input) => input,
+                    None => bail!("map has no input"),
+                };
+                let output = match self.output.take() {
+                    Some(output) => output,
+                    None => bail!("map has no output"),
+                };
+
+                let transform = Rc::clone(&self.transform);
+                let emit = Rc::clone(&self.emit);
+
+                handle.spawn(
+                    (self.receive)(input)
+                        .for_each(move |signal| {
+                            emit(&output, transform(signal))
+                        })
+                        .or_else(move |e| {
+                            main_tx
+                                .send(Impulse::Error(e))
+                                .map(|_| ())
+                                .map_err(|_| ())
+                        }),
+                );
+
+                Ok(self)
+            },
+
+            imp => Err(imp.unexpected(&self)),
+        }
+    }
+}
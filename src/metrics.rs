@@ -0,0 +1,138 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+// each power of two is split into 2^SUB_BITS linear sub-buckets, which keeps
+// the relative error of a recorded value under 12.5%
+const SUB_BITS: u32 = 3;
+const SUB_BUCKETS: usize = 1 << SUB_BITS;
+const BUCKETS: usize = (64 - SUB_BITS as usize + 1) * SUB_BUCKETS;
+
+fn bucket(micros: u64) -> usize {
+    if micros < SUB_BUCKETS as u64 {
+        micros as usize
+    } else {
+        let exp = 63 - micros.leading_zeros();
+        let sub = (micros >> (exp - SUB_BITS)) as usize & (SUB_BUCKETS - 1);
+
+        (exp - SUB_BITS + 1) as usize * SUB_BUCKETS + sub
+    }
+}
+
+fn lower_bound(bucket: usize) -> u64 {
+    if bucket < SUB_BUCKETS {
+        bucket as u64
+    } else {
+        let exp = (bucket / SUB_BUCKETS) as u32 + SUB_BITS - 1;
+        let sub = (bucket % SUB_BUCKETS) as u64;
+
+        (SUB_BUCKETS as u64 + sub) << (exp - SUB_BITS)
+    }
+}
+
+/// update latency percentiles for a soma
+#[derive(Debug, Copy, Clone, Serialize, PartialEq, Eq)]
+pub struct Percentiles {
+    /// the number of updates recorded
+    pub count: u64,
+    /// median update latency
+    pub p50: Duration,
+    /// 95th percentile update latency
+    pub p95: Duration,
+    /// 99th percentile update latency
+    pub p99: Duration,
+}
+
+/// compact log-bucketed histogram of update latencies
+///
+/// clones share the same counts.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    counts: Rc<RefCell<Vec<u64>>>,
+}
+
+impl Histogram {
+    /// create an empty histogram
+    pub fn new() -> Self {
+        Self {
+            counts: Rc::new(RefCell::new(vec![0; BUCKETS])),
+        }
+    }
+
+    /// record a single latency
+    pub fn record(&self, latency: Duration) {
+        let micros = latency.as_secs() * 1_000_000
+            + latency.subsec_nanos() as u64 / 1_000;
+
+        self.counts.borrow_mut()[bucket(micros)] += 1;
+    }
+
+    /// get the value at the given quantile (0.0 - 1.0)
+    pub fn quantile(&self, quantile: f64) -> Duration {
+        let counts = self.counts.borrow();
+        let total: u64 = counts.iter().sum();
+
+        if total == 0 {
+            return Duration::from_secs(0);
+        }
+
+        let target = ((total as f64 * quantile).ceil() as u64).max(1);
+        let mut seen = 0;
+
+        for (i, count) in counts.iter().enumerate() {
+            seen += *count;
+
+            if seen >= target {
+                return from_micros(lower_bound(i));
+            }
+        }
+
+        unreachable!()
+    }
+
+    /// get the p50, p95, and p99 latencies
+    pub fn percentiles(&self) -> Percentiles {
+        Percentiles {
+            count: self.counts.borrow().iter().sum(),
+            p50: self.quantile(0.50),
+            p95: self.quantile(0.95),
+            p99: self.quantile(0.99),
+        }
+    }
+}
+
+fn from_micros(micros: u64) -> Duration {
+    Duration::new(micros / 1_000_000, (micros % 1_000_000) as u32 * 1_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets() {
+        for micros in (0..10_000).chain(vec![1 << 40, u64::max_value()]) {
+            let lower = lower_bound(bucket(micros));
+
+            assert!(lower <= micros);
+            assert!(micros - lower <= micros / SUB_BUCKETS as u64);
+        }
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let histogram = Histogram::new();
+
+        for micros in 1..101 {
+            histogram.record(Duration::from_millis(micros));
+        }
+
+        let percentiles = histogram.percentiles();
+
+        assert_eq!(percentiles.count, 100);
+        assert!(percentiles.p50 <= Duration::from_millis(50));
+        assert!(percentiles.p50 >= Duration::from_millis(44));
+        assert!(percentiles.p99 <= Duration::from_millis(99));
+        assert!(percentiles.p99 >= Duration::from_millis(87));
+    }
+}
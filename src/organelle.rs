@@ -1,18 +1,699 @@
 use std;
-use std::collections::HashMap;
-use std::intrinsics;
+use std::any::Any;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::marker::PhantomData;
 use std::mem;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+use std::time::Instant;
 
-use futures::future;
+use futures::future::{self, Either};
 use futures::prelude::*;
 use futures::stream;
 use futures::unsync::{mpsc, oneshot};
 use tokio_core::reactor;
 use uuid::Uuid;
 
-use super::{Error, Result};
-use probe::{self, SomaData};
-use soma::{Impulse, Soma, Synapse};
+use super::{Error, ErrorKind, Result, ResultExt};
+#[cfg(feature = "chaos")]
+use chaos;
+use mailbox::{self, Mailbox, OverflowPolicy};
+use pause::Pausable;
+#[cfg(feature = "metrics")]
+use metrics::{Histogram, Percentiles};
+use axon::Constraint;
+use probe::{self, ConnectionMeta, EdgeData, SomaData};
+use soma::{
+    Constraints, Impulse, ImpulseKind, ImpulseRecord, Soma, Synapse,
+};
+use watch::{self, Publisher, Watcher};
+
+/// settings for a soma added to an organelle
+#[derive(Debug, Copy, Clone)]
+pub struct SomaSettings {
+    overflow: OverflowPolicy,
+    history: Option<usize>,
+    start_priority: i32,
+}
+
+impl SomaSettings {
+    /// set the policy for when the soma's mailbox is full
+    ///
+    /// only probes can be dropped, and a dropped probe is answered with
+    /// SomaData::Unavailable on the soma's behalf. impulses that wire up,
+    /// start, stop or pause the soma are queued past the mailbox's capacity
+    /// instead. every drop is counted, reported in probes and by
+    /// Organelle::dropped_impulses, and sent to topology subscribers as
+    /// TopologyEvent::ImpulseDropped.
+    pub fn overflow(self, policy: OverflowPolicy) -> Self {
+        Self {
+            overflow: policy,
+            ..self
+        }
+    }
+
+    /// keep a record of the last `len` impulses received by the soma
+    ///
+    /// this is a debugging aid, see Organelle::soma_history.
+    pub fn history(self, len: usize) -> Self {
+        Self {
+            history: Some(len),
+            ..self
+        }
+    }
+
+    /// set the order in which the soma is started relative to others
+    ///
+    /// somas with a higher priority are sent Start first, and every soma
+    /// with that priority must finish handling Start before somas with a
+    /// lower priority are sent Start. somas with the same priority are
+    /// started concurrently. if a soma never finishes handling Start, the
+    /// somas after it are never started, and if it fails, the organelle
+    /// fails as usual. the default priority is 0.
+    pub fn start_priority(self, priority: i32) -> Self {
+        Self {
+            start_priority: priority,
+            ..self
+        }
+    }
+}
+
+impl Default for SomaSettings {
+    fn default() -> Self {
+        Self {
+            overflow: OverflowPolicy::Block,
+            history: None,
+            start_priority: 0,
+        }
+    }
+}
+
+/// what to do with a soma when one of its updates fails
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// fail the organelle, like any other soma
+    Never,
+    /// always replace the soma with a fresh one
+    Always,
+    /// replace the soma at most the given number of times, then fail
+    MaxRetries(u32),
+}
+
+impl RestartPolicy {
+    fn allows(&self, retries: u32) -> bool {
+        match self {
+            &RestartPolicy::Never => false,
+            &RestartPolicy::Always => true,
+            &RestartPolicy::MaxRetries(max) => retries < max,
+        }
+    }
+}
+
+/// settings for an organelle as a whole
+#[derive(Debug, Copy, Clone)]
+pub struct OrganelleConfig {
+    max_somas: Option<usize>,
+    main_buffer: usize,
+    relay_buffer: usize,
+    soma_buffer: usize,
+    reject_cycles: bool,
+    start_timeout: Option<Duration>,
+}
+
+impl OrganelleConfig {
+    /// limit the number of somas in the organelle, including the nucleus
+    ///
+    /// by default, there is no limit.
+    ///
+    /// # Panics
+    ///
+    /// panics if `max` is 0, since there would be no room for the nucleus.
+    pub fn max_somas(self, max: usize) -> Self {
+        assert!(
+            max >= 1,
+            "max_somas must be at least 1 to leave room for the nucleus"
+        );
+
+        Self {
+            max_somas: Some(max),
+            ..self
+        }
+    }
+
+    /// set the buffer sizes of the organelle's internal channels
+    ///
+    /// `main` buffers the impulses sent to the organelle itself, `relay`
+    /// buffers each soma's mailbox, and `soma` buffers the channel between
+    /// each mailbox and its soma. the defaults are 100, 10, and 1.
+    pub fn buffer_sizes(self, main: usize, relay: usize, soma: usize) -> Self {
+        Self {
+            main_buffer: main,
+            relay_buffer: relay,
+            soma_buffer: soma,
+            ..self
+        }
+    }
+
+    /// make connect fail if it would close a loop of One synapses
+    ///
+    /// somas that each wait on a single upstream can deadlock when wired in
+    /// a loop, so this is useful for catching wiring mistakes. it is off by
+    /// default because feedback is sometimes intended. only connections
+    /// where either soma has a One constraint on the synapse are considered.
+    pub fn reject_cycles(self, flag: bool) -> Self {
+        Self {
+            reject_cycles: flag,
+            ..self
+        }
+    }
+
+    /// fail with StartTimeout if any soma takes longer than this to start
+    ///
+    /// the window covers every soma present when the organelle starts, so
+    /// somas that hang in Start (e.g. awaiting an external resource) are
+    /// reported by uuid instead of blocking forever. by default, there is no
+    /// timeout.
+    pub fn start_timeout(self, timeout: Duration) -> Self {
+        Self {
+            start_timeout: Some(timeout),
+            ..self
+        }
+    }
+}
+
+impl Default for OrganelleConfig {
+    fn default() -> Self {
+        Self {
+            max_somas: None,
+            main_buffer: 100,
+            relay_buffer: 10,
+            soma_buffer: 1,
+            reject_cycles: false,
+            start_timeout: None,
+        }
+    }
+}
+
+/// a change to the somas or connections of an organelle
+///
+/// see Organelle::on_topology_change.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TopologyEvent<S: Synapse> {
+    /// two somas were connected with Organelle::connect
+    Connected {
+        /// the soma that received the terminal
+        dendrite: Uuid,
+        /// the soma that received the dendrite
+        terminal: Uuid,
+        /// the synapse the somas are connected with
+        synapse: S,
+    },
+    /// a connection was torn down with Organelle::disconnect
+    Disconnected {
+        /// the soma that held the terminal
+        dendrite: Uuid,
+        /// the soma that held the dendrite
+        terminal: Uuid,
+        /// the synapse the somas were connected with
+        synapse: S,
+    },
+    /// a soma was added to the organelle
+    SomaAdded(Uuid),
+    /// a soma was removed from the organelle
+    SomaRemoved(Uuid),
+    /// an update is taking longer than the soma's Soma::update_deadline
+    ///
+    /// the update is left to finish. this is sent at most once per update.
+    SlowUpdate {
+        /// the soma running the update
+        soma: Uuid,
+        /// how long the update had been running
+        elapsed: Duration,
+    },
+    /// a soma failed an update and was replaced according to its
+    /// RestartPolicy
+    ///
+    /// if the replacement can't be wired up, the organelle fails instead.
+    SomaRestarted {
+        /// the soma that was restarted
+        soma: Uuid,
+        /// how many times the soma has been restarted, starting at 1
+        attempt: u32,
+    },
+    /// the soma's mailbox was full, so its OverflowPolicy dropped an impulse
+    ImpulseDropped {
+        /// the soma the impulse was meant for
+        soma: Uuid,
+        /// the variant of the impulse that was dropped
+        kind: ImpulseKind,
+    },
+}
+
+/// the uuid of a soma along with the type of the soma
+///
+/// returned by Organelle::add_typed_soma so that handles to different kinds
+/// of somas can't be mixed up.
+#[derive(Debug)]
+pub struct SomaHandle<U> {
+    uuid: Uuid,
+    soma: PhantomData<fn() -> U>,
+}
+
+impl<U> SomaHandle<U> {
+    fn new(uuid: Uuid) -> Self {
+        Self {
+            uuid: uuid,
+            soma: PhantomData,
+        }
+    }
+
+    /// the uuid of the soma
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+}
+
+impl<U> Copy for SomaHandle<U> {}
+
+impl<U> Clone for SomaHandle<U> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<U> PartialEq for SomaHandle<U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.uuid == other.uuid
+    }
+}
+
+impl<U> Eq for SomaHandle<U> {}
+
+impl<U> From<SomaHandle<U>> for Uuid {
+    fn from(handle: SomaHandle<U>) -> Self {
+        handle.uuid
+    }
+}
+
+/// builds an organelle one option at a time
+///
+/// created with Organelle::builder. the nucleus is the only required option.
+pub struct OrganelleBuilder<T: Soma> {
+    handle: reactor::Handle,
+    config: OrganelleConfig,
+    name: Option<String>,
+    nucleus: Option<T>,
+}
+
+impl<T: Soma + 'static> OrganelleBuilder<T> {
+    /// the main soma of the organelle
+    pub fn nucleus(self, nucleus: T) -> Self {
+        Self {
+            nucleus: Some(nucleus),
+            ..self
+        }
+    }
+
+    /// the name reported for the organelle in probes
+    ///
+    /// by default, the name of the nucleus is reported.
+    pub fn name(self, name: String) -> Self {
+        Self {
+            name: Some(name),
+            ..self
+        }
+    }
+
+    /// settings for the organelle as a whole
+    pub fn config(self, config: OrganelleConfig) -> Self {
+        Self {
+            config: config,
+            ..self
+        }
+    }
+
+    /// see OrganelleConfig::reject_cycles
+    pub fn reject_cycles(self, flag: bool) -> Self {
+        Self {
+            config: self.config.reject_cycles(flag),
+            ..self
+        }
+    }
+
+    /// see OrganelleConfig::start_timeout
+    pub fn start_timeout(self, timeout: Duration) -> Self {
+        Self {
+            config: self.config.start_timeout(timeout),
+            ..self
+        }
+    }
+
+    /// create the organelle, failing if no nucleus was given
+    pub fn build(self) -> Result<Organelle<T>> {
+        let nucleus = match self.nucleus {
+            Some(nucleus) => nucleus,
+            None => bail!("organelle has no nucleus"),
+        };
+
+        let mut organelle =
+            Organelle::with_config(nucleus, self.handle, self.config);
+        organelle.name = self.name;
+
+        Ok(organelle)
+    }
+}
+
+/// a connection made between two somas
+///
+/// returned by Organelle::connect so that the edge can be referred to later.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Connection<S: Synapse> {
+    /// unique id of the connection
+    pub uuid: Uuid,
+    /// the soma that received the terminal
+    pub dendrite: Uuid,
+    /// the soma that received the dendrite
+    pub terminal: Uuid,
+    /// the synapse the somas are connected with
+    pub synapse: S,
+}
+
+/// sender for the value produced by an organelle
+///
+/// see Organelle::result_sender.
+#[derive(Debug)]
+pub struct ResultSender<R> {
+    tx: oneshot::Sender<R>,
+}
+
+impl<R> ResultSender<R> {
+    /// deposit the organelle's result
+    ///
+    /// the organelle still needs to be stopped before the result is
+    /// delivered.
+    pub fn send(self, value: R) -> Result<()> {
+        self.tx
+            .send(value)
+            .map_err(|_| Error::from("organelle is not waiting for a result"))
+    }
+}
+
+type History = Rc<RefCell<VecDeque<ImpulseRecord>>>;
+
+type Condition<T> = Box<Fn(&Organelle<T>) -> bool>;
+
+type Route<T> = Mailbox<Impulse<<T as Soma>::Synapse>>;
+type TopologySender<T> =
+    mpsc::UnboundedSender<TopologyEvent<<T as Soma>::Synapse>>;
+type TopologySubscribers<T> = Rc<RefCell<Vec<TopologySender<T>>>>;
+
+fn emit_topology_change<T: Soma>(
+    subscribers: &TopologySubscribers<T>,
+    event: TopologyEvent<T::Synapse>,
+) {
+    subscribers
+        .borrow_mut()
+        .retain(|tx| tx.unbounded_send(event).is_ok());
+}
+
+/// ask a soma for its probe data, giving up after the probe's timeout
+fn probe_soma<T: Soma + 'static>(
+    uuid: Uuid,
+    sender: Route<T>,
+    settings: probe::Settings,
+    handle: &reactor::Handle,
+) -> Box<Future<Item = (Uuid, SomaData), Error = Error>> {
+    let timeout = settings.probe_timeout();
+    let (tx, rx) = oneshot::channel();
+
+    let answer = sender
+        .send(Impulse::Probe(settings, tx))
+        .map_err(|e| e.chain_err(|| "unable to send probe impulse"))
+        .and_then(move |_| rx.map_err(|e| e.into()));
+
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return Box::new(answer.map(move |data| (uuid, data))),
+    };
+
+    let timer = match reactor::Timeout::new(timeout, handle) {
+        Ok(timer) => timer,
+        Err(e) => return Box::new(future::err(e.into())),
+    };
+
+    Box::new(answer.select2(timer).then(move |result| match result {
+        Ok(Either::A((data, _))) => Ok((uuid, data)),
+        Ok(Either::B(_)) => Ok((
+            uuid,
+            SomaData::Unavailable {
+                uuid: uuid,
+                reason: format!("no answer within {:?}", timeout),
+            },
+        )),
+        Err(Either::A((e, _))) => Err(e),
+        Err(Either::B((e, _))) => Err(e.into()),
+    }))
+}
+
+/// per-soma bookkeeping shared between the organelle and run_soma
+#[derive(Debug, Clone)]
+struct SomaContext {
+    start_priority: i32,
+    handle: reactor::Handle,
+    // published by run_soma once the soma has finished handling Start
+    started: Rc<Publisher<bool>>,
+    started_watcher: Watcher<bool>,
+    // published once run_soma has exited, however it ended
+    stopped: Rc<Publisher<bool>>,
+    stopped_watcher: Watcher<bool>,
+    // counters reported in probes, see SomaMetrics
+    impulses: Rc<Cell<u64>>,
+    last_update: Rc<Cell<Option<Instant>>>,
+
+    #[cfg(feature = "metrics")]
+    latency: Histogram,
+    // synthetic failures checked before each update, see inject_error
+    #[cfg(feature = "chaos")]
+    faults: Option<chaos::Faults>,
+}
+
+impl SomaContext {
+    fn new(settings: &SomaSettings, handle: reactor::Handle) -> Self {
+        let (started, started_watcher) = watch::channel(false);
+        let (stopped, stopped_watcher) = watch::channel(false);
+
+        Self {
+            start_priority: settings.start_priority,
+            handle: handle,
+            started: Rc::new(started),
+            started_watcher: started_watcher,
+            stopped: Rc::new(stopped),
+            stopped_watcher: stopped_watcher,
+            impulses: Rc::new(Cell::new(0)),
+            last_update: Rc::new(Cell::new(None)),
+
+            #[cfg(feature = "metrics")]
+            latency: Histogram::new(),
+            #[cfg(feature = "chaos")]
+            faults: None,
+        }
+    }
+}
+
+type Started<S> = (Uuid, mpsc::Sender<Impulse<S>>, reactor::Handle);
+
+// a soma waiting to be sent Start or StopDraining, along with the watcher
+// for when it has handled it, see Organelle::start_all and drain_somas
+type StartEntry<S> = (Uuid, Mailbox<Impulse<S>>, Watcher<bool>);
+
+// the host's ends of the synapses made with Organelle::connect_host, keyed
+// by the soma and synapse they reach
+type HostTerminals<S> = HashMap<(Uuid, S), <S as Synapse>::Terminal>;
+
+/// the organelle that the task running a soma reports to
+///
+/// the task holds this through a shared cell, so Organelle::absorb can hand
+/// the soma over to the absorbing organelle after it has been spawned.
+struct Home<S: Synapse> {
+    uuid: Uuid,
+    // only set for the nucleus, which stops its organelle once it is done
+    stop_tx: Option<mpsc::Sender<Impulse<S>>>,
+    finished: Rc<RefCell<Vec<Uuid>>>,
+    subscribers: Rc<RefCell<Vec<mpsc::UnboundedSender<TopologyEvent<S>>>>>,
+    // weak, so that the task doesn't keep its own mailbox open
+    mailboxes: Weak<RefCell<HashMap<Uuid, Mailbox<Impulse<S>>>>>,
+    connections: Rc<RefCell<HashMap<Uuid, Connection<S>>>>,
+    host: Uuid,
+    host_terminals: Rc<RefCell<HostTerminals<S>>>,
+}
+
+impl<S: Synapse> Clone for Home<S> {
+    fn clone(&self) -> Self {
+        Self {
+            uuid: self.uuid,
+            stop_tx: self.stop_tx.clone(),
+            finished: Rc::clone(&self.finished),
+            subscribers: Rc::clone(&self.subscribers),
+            mailboxes: Weak::clone(&self.mailboxes),
+            connections: Rc::clone(&self.connections),
+            host: self.host,
+            host_terminals: Rc::clone(&self.host_terminals),
+        }
+    }
+}
+
+/// everything run_soma needs to replace a failed soma, along with its Home
+struct Restart<U> {
+    factory: Rc<Fn() -> U>,
+    policy: RestartPolicy,
+}
+
+impl<U> Clone for Restart<U> {
+    fn clone(&self) -> Self {
+        Self {
+            factory: Rc::clone(&self.factory),
+            policy: self.policy,
+        }
+    }
+}
+
+impl<U> Restart<U>
+where
+    U: Soma + 'static,
+{
+    /// create a fresh soma and rebuild each of the old soma's connections
+    ///
+    /// see rewire. the fresh soma is started if the old one was.
+    #[async]
+    fn restart<S>(
+        self,
+        home: Home<S>,
+        started: Option<Started<U::Synapse>>,
+    ) -> Result<U>
+    where
+        S: Synapse + 'static,
+        U::Synapse: From<S>,
+        <U::Synapse as Synapse>::Dendrite: From<S::Dendrite>,
+        <U::Synapse as Synapse>::Terminal: From<S::Terminal>,
+    {
+        let mailboxes = match home.mailboxes.upgrade() {
+            Some(mailboxes) => mailboxes,
+            None => bail!("the organelle is gone"),
+        };
+
+        let mut soma = (self.factory)();
+
+        let (peers, mut own) = rewire(&home.connections.borrow(), home.uuid);
+        own.extend(rewire_host(
+            &mut home.host_terminals.borrow_mut(),
+            home.host,
+            home.uuid,
+        ));
+
+        await!(replace_peers(mailboxes, peers))?;
+
+        for imp in own {
+            soma = await!(soma.update(Impulse::convert_from(imp)))
+                .map_err(|e| e.into())?;
+        }
+
+        if let Some((uuid, tx, handle)) = started {
+            soma = await!(soma.update(Impulse::Start(uuid, tx, handle)))
+                .map_err(|e| e.into())?;
+        }
+
+        Ok(soma)
+    }
+}
+
+type Replacement<S> = (Uuid, Impulse<S>, Impulse<S>);
+
+/// form new synapses for every connection made to a soma
+///
+/// gives the impulses for each peer, a Remove for its end of the old synapse
+/// followed by an Add with its end of the new one, and the Add impulses that
+/// give the soma its own ends.
+fn rewire<S: Synapse>(
+    connections: &HashMap<Uuid, Connection<S>>,
+    uuid: Uuid,
+) -> (Vec<Replacement<S>>, Vec<Impulse<S>>) {
+    let mut peers = vec![];
+    let mut own = vec![];
+
+    for c in connections.values() {
+        let (tx, rx) = c.synapse.synapse();
+
+        if c.dendrite == uuid {
+            own.push(Impulse::AddTerminal(c.terminal, c.synapse, tx));
+
+            if c.terminal == uuid {
+                own.push(Impulse::AddDendrite(uuid, c.synapse, rx));
+            } else {
+                peers.push((
+                    c.terminal,
+                    Impulse::RemoveDendrite(uuid, c.synapse),
+                    Impulse::AddDendrite(uuid, c.synapse, rx),
+                ));
+            }
+        } else if c.terminal == uuid {
+            own.push(Impulse::AddDendrite(c.dendrite, c.synapse, rx));
+
+            peers.push((
+                c.dendrite,
+                Impulse::RemoveTerminal(uuid, c.synapse),
+                Impulse::AddTerminal(uuid, c.synapse, tx),
+            ));
+        }
+    }
+
+    (peers, own)
+}
+
+/// form new synapses for the host's connections to a soma
+///
+/// the host's terminals are replaced in place, and the AddDendrite impulses
+/// that give the soma its ends are returned.
+fn rewire_host<S: Synapse>(
+    host_terminals: &mut HostTerminals<S>,
+    host: Uuid,
+    uuid: Uuid,
+) -> Vec<Impulse<S>> {
+    host_terminals
+        .iter_mut()
+        .filter(|&(&(dest, _), _)| dest == uuid)
+        .map(|(&(_, synapse), terminal)| {
+            let (tx, rx) = synapse.synapse();
+            *terminal = tx;
+
+            Impulse::AddDendrite(host, synapse, rx)
+        })
+        .collect()
+}
+
+#[async]
+fn replace_peers<S>(
+    mailboxes: Rc<RefCell<HashMap<Uuid, Mailbox<Impulse<S>>>>>,
+    peers: Vec<Replacement<S>>,
+) -> Result<()>
+where
+    S: Synapse + 'static,
+    S::Dendrite: 'static,
+    S::Terminal: 'static,
+{
+    for (peer, remove, add) in peers {
+        let mailbox = mailboxes.borrow().get(&peer).cloned();
+
+        // the peer may have been removed since the connection was made
+        if let Some(mailbox) = mailbox {
+            await!(mailbox.send(remove).and_then(move |m| m.send(add)))?;
+        }
+    }
+
+    Ok(())
+}
 
 /// a soma designed to facilitate connections between other somas
 ///
@@ -25,343 +706,5356 @@ where
     T: Soma,
 {
     handle: reactor::Handle,
+    config: OrganelleConfig,
+    name: Option<String>,
+    nucleus_name: String,
 
     uuid: Option<Uuid>,
+    // the sender and handle given to the organelle with Start
+    started: Option<(mpsc::Sender<Impulse<T::Synapse>>, reactor::Handle)>,
 
     main: Uuid,
     main_tx: mpsc::Sender<Impulse<T::Synapse>>,
     main_rx: Option<mpsc::Receiver<Impulse<T::Synapse>>>,
 
-    somas: HashMap<Uuid, mpsc::Sender<Impulse<T::Synapse>>>,
-}
+    somas: HashMap<Uuid, Mailbox<Impulse<T::Synapse>>>,
+    history: HashMap<Uuid, History>,
+    watchers: HashMap<Uuid, Box<Any>>,
+    contexts: HashMap<Uuid, SomaContext>,
+    constraints: HashMap<Uuid, Constraints<T::Synapse>>,
+    deferred: Vec<(Uuid, Uuid, T::Synapse, Condition<T>)>,
+    routes: HashMap<Vec<Uuid>, Route<T>>,
+    connections: Rc<RefCell<HashMap<Uuid, Connection<T::Synapse>>>>,
+    // connections made inside nested organelles, only used to check
+    // connect_path against the constraints of nested somas
+    nested_connections: Vec<Connection<T::Synapse>>,
+    connection_meta: RefCell<HashMap<Uuid, ConnectionMeta>>,
+    // the peer that connect_host connections appear to come from
+    host: Uuid,
+    host_terminals: Rc<RefCell<HostTerminals<T::Synapse>>>,
+    topology_subscribers: TopologySubscribers<T>,
+    // shared with the tasks of somas that can be restarted
+    mailboxes: Rc<RefCell<HashMap<Uuid, Route<T>>>>,
+    // somas that reported Soma::is_done, removed on the next impulse
+    finished: Rc<RefCell<Vec<Uuid>>>,
+    // shared with the task of each soma, see Home
+    homes: HashMap<Uuid, Rc<RefCell<Home<T::Synapse>>>>,
+    result: Option<Box<Any>>,
+
+    #[cfg(feature = "chaos")]
+    latency: HashMap<Uuid, chaos::Latency>,
+    #[cfg(feature = "chaos")]
+    faults: HashMap<Uuid, chaos::Faults>,
+    #[cfg(feature = "chaos")]
+    rng: chaos::Rng,
+}
+
+impl<T: Soma + 'static> Organelle<T> {
+    /// create a new organelle
+    pub fn new(main: T, handle: reactor::Handle) -> Self {
+        Self::with_config(main, handle, OrganelleConfig::default())
+    }
+
+    /// start building an organelle
+    ///
+    /// see OrganelleBuilder.
+    pub fn builder(handle: reactor::Handle) -> OrganelleBuilder<T> {
+        OrganelleBuilder {
+            handle: handle,
+            config: OrganelleConfig::default(),
+            name: None,
+            nucleus: None,
+        }
+    }
+
+    /// create a new organelle with the given config
+    pub fn with_config(
+        main: T,
+        handle: reactor::Handle,
+        config: OrganelleConfig,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel(config.main_buffer);
+
+        let mut organelle = Self {
+            handle: handle,
+            config: config,
+            name: None,
+            nucleus_name: main.name().into_owned(),
+
+            uuid: None,
+            started: None,
+
+            main: Uuid::new_v4(),
+            main_tx: tx,
+            main_rx: Some(rx),
+
+            somas: HashMap::new(),
+            history: HashMap::new(),
+            watchers: HashMap::new(),
+            contexts: HashMap::new(),
+            constraints: HashMap::new(),
+            deferred: vec![],
+            routes: HashMap::new(),
+            connections: Rc::new(RefCell::new(HashMap::new())),
+            nested_connections: vec![],
+            connection_meta: RefCell::new(HashMap::new()),
+            host: Uuid::new_v4(),
+            host_terminals: Rc::new(RefCell::new(HashMap::new())),
+            topology_subscribers: Rc::new(RefCell::new(vec![])),
+            mailboxes: Rc::new(RefCell::new(HashMap::new())),
+            finished: Rc::new(RefCell::new(vec![])),
+            homes: HashMap::new(),
+            result: None,
+
+            #[cfg(feature = "chaos")]
+            latency: HashMap::new(),
+            #[cfg(feature = "chaos")]
+            faults: HashMap::new(),
+            #[cfg(feature = "chaos")]
+            rng: chaos::Rng::seeded(0),
+        };
+
+        // the nucleus is spawned under the uuid it was given up front so its
+        // task knows to stop the organelle once it is done
+        let nucleus = organelle.main;
+        organelle
+            .spawn_soma(nucleus, main, SomaSettings::default(), None)
+            .expect("unable to add the nucleus to the organelle");
+
+        organelle
+    }
+
+    /// get the main soma's uuid
+    pub fn nucleus(&self) -> Uuid {
+        self.main
+    }
+
+    /// the number of somas in the organelle, including the nucleus
+    pub fn soma_count(&self) -> usize {
+        self.somas.len()
+    }
+
+    /// whether the organelle has no somas other than its nucleus
+    ///
+    /// the nucleus can't be removed, so an organelle is never truly empty.
+    pub fn is_empty(&self) -> bool {
+        self.somas.len() <= 1
+    }
+
+    /// subscribe to changes to the somas and connections of the organelle
+    ///
+    /// every soma added or removed and every connection made or torn down
+    /// after this call is sent as an event, in the order the changes are
+    /// made. the nucleus is added before anyone can subscribe, so there is
+    /// never an event for it. subscribers are forgotten once their receiver
+    /// is dropped, and nothing is done for changes without subscribers.
+    pub fn on_topology_change(
+        &mut self,
+        tx: mpsc::UnboundedSender<TopologyEvent<T::Synapse>>,
+    ) {
+        self.topology_subscribers.borrow_mut().push(tx);
+    }
+
+    /// notify `changes` of every topology change, see probe::Settings
+    fn notify_changes(&mut self, changes: mpsc::UnboundedSender<()>) {
+        let (tx, rx) = mpsc::unbounded();
+
+        self.on_topology_change(tx);
+
+        self.handle.spawn(
+            rx.map(|_| ())
+                .forward(changes.sink_map_err(|_| ()))
+                .map(|_| ()),
+        );
+    }
+
+    fn emit_topology_change(&self, event: TopologyEvent<T::Synapse>) {
+        emit_topology_change::<T>(&self.topology_subscribers, event);
+    }
+
+    /// what the task of the given soma reports to in this organelle
+    fn home(&self, uuid: Uuid) -> Home<T::Synapse> {
+        Home {
+            uuid: uuid,
+            stop_tx: if uuid == self.main {
+                Some(self.main_tx.clone())
+            } else {
+                None
+            },
+            finished: Rc::clone(&self.finished),
+            subscribers: Rc::clone(&self.topology_subscribers),
+            mailboxes: Rc::downgrade(&self.mailboxes),
+            connections: Rc::clone(&self.connections),
+            host: self.host,
+            host_terminals: Rc::clone(&self.host_terminals),
+        }
+    }
+
+    /// send SlowUpdate if an update is still running after the deadline
+    ///
+    /// the returned sender is dropped once the update resolves, which cancels
+    /// the check.
+    fn watch_update(
+        ctx: &SomaContext,
+        uuid: Uuid,
+        deadline: Duration,
+        subscribers: TopologySubscribers<T>,
+    ) -> Result<oneshot::Sender<()>> {
+        let (done_tx, done_rx) = oneshot::channel();
+        let start = Instant::now();
+
+        ctx.handle.spawn(
+            reactor::Timeout::new(deadline, &ctx.handle)?
+                .select2(done_rx)
+                .then(move |result| {
+                    if let Ok(Either::A(_)) = result {
+                        emit_topology_change::<T>(
+                            &subscribers,
+                            TopologyEvent::SlowUpdate {
+                                soma: uuid,
+                                elapsed: start.elapsed(),
+                            },
+                        );
+                    }
+
+                    Ok(())
+                }),
+        );
+
+        Ok(done_tx)
+    }
+
+    /// delay every impulse sent to the given soma by the given duration
+    ///
+    /// this is intended for chaos testing only and should not be used in
+    /// production. it is only available with the `chaos` feature.
+    #[cfg(feature = "chaos")]
+    pub fn inject_latency(&self, soma: Uuid, duration: Duration) -> Result<()> {
+        if let Some(latency) = self.latency.get(&soma) {
+            latency.set(duration);
+        } else {
+            bail!("unable to find soma")
+        }
+
+        Ok(())
+    }
+
+    /// make each update of the given soma fail with the given probability
+    ///
+    /// a failing update is skipped and treated exactly like an error returned
+    /// from the soma's update, so somas added with a restart policy are
+    /// restarted and any others take down the organelle. like latency
+    /// injection, this is intended for chaos testing only and is only
+    /// available with the `chaos` feature.
+    #[cfg(feature = "chaos")]
+    pub fn inject_error(&self, soma: Uuid, probability: f64) -> Result<()> {
+        if let Some(faults) = self.faults.get(&soma) {
+            faults.set(probability);
+        } else {
+            bail!("unable to find soma")
+        }
+
+        Ok(())
+    }
+
+    /// seed the random number generator used for error injection
+    ///
+    /// the same seed will always produce the same sequence of failures.
+    #[cfg(feature = "chaos")]
+    pub fn seed_chaos(&self, seed: u64) {
+        self.rng.reseed(seed);
+    }
+
+    /// create the mailbox for a soma and the relay that feeds it
+    ///
+    /// the relay ends when the returned oneshot sender is dropped, so the
+    /// caller should hold it for as long as the soma is running.
+    fn create_soma_channel<R>(
+        &mut self,
+        uuid: Uuid,
+        overflow: OverflowPolicy,
+    ) -> (mpsc::Receiver<Impulse<R>>, oneshot::Sender<()>)
+    where
+        R: Synapse + From<T::Synapse> + Into<T::Synapse> + 'static,
+        R::Dendrite: From<<T::Synapse as Synapse>::Dendrite>
+            + Into<<T::Synapse as Synapse>::Dendrite>
+            + 'static,
+        R::Terminal: From<<T::Synapse as Synapse>::Terminal>
+            + Into<<T::Synapse as Synapse>::Terminal>
+            + 'static,
+    {
+        // the soma's task reports here too, see Home
+        let home = Rc::new(RefCell::new(self.home(uuid)));
+        self.homes.insert(uuid, Rc::clone(&home));
+
+        let (tx, rx) = mailbox::channel(
+            uuid,
+            self.config.relay_buffer,
+            overflow,
+            move |imp: Impulse<T::Synapse>| {
+                let home = home.borrow();
+
+                emit_topology_change::<T>(
+                    &home.subscribers,
+                    TopologyEvent::ImpulseDropped {
+                        soma: home.uuid,
+                        kind: imp.kind(),
+                    },
+                );
+
+                // nobody would answer it otherwise
+                if let Impulse::Probe(_, tx) = imp {
+                    let _ = tx.send(SomaData::Unavailable {
+                        uuid: home.uuid,
+                        reason: "the probe was dropped because the soma's \
+                                 mailbox was full"
+                            .into(),
+                    });
+                }
+            },
+        );
+
+        let soma_buffer = self.config.soma_buffer;
+        let (soma_tx, soma_rx) = mpsc::channel::<Impulse<R>>(soma_buffer);
+        let (closed_tx, closed_rx) = oneshot::channel();
+        let main_tx = self.main_tx.clone();
+
+        #[cfg(feature = "chaos")]
+        let rx = {
+            let latency = chaos::Latency::new();
+            self.latency.insert(uuid, latency.clone());
+
+            latency.delay(rx, self.handle.clone())
+        };
+
+        let relay = soma_tx
+            .send_all(rx.map(move |imp| match imp {
+                Impulse::Start(uuid, sender, handle) => {
+                    let (tx, rx) = mpsc::channel::<Impulse<R>>(soma_buffer);
+
+                    // this only fails once the organelle has stopped, so
+                    // there is nowhere left to report it
+                    handle.spawn(
+                        sender
+                            .send_all(rx.map(move |imp| {
+                                Impulse::<T::Synapse>::convert_from(imp)
+                            }).map_err(|_| unreachable!()))
+                            .map(|_| ())
+                            .map_err(|_| ()),
+                    );
+
+                    Impulse::Start(uuid, tx, handle)
+                },
+                _ => Impulse::<R>::convert_from(imp),
+            }).map_err(|_| unreachable!()))
+            .map(|_| ())
+            .or_else(move |_| {
+                // a soma that failed reports its own error before its
+                // receiver is dropped, so that error is seen first
+                main_tx
+                    .send(Impulse::Error(Error::from(format!(
+                        "relay for soma {} stopped, the soma is no \
+                         longer receiving impulses",
+                        uuid
+                    ))))
+                    .map(|_| ())
+                    .map_err(|_| ())
+            });
+
+        // the soma is gone once closed_rx resolves, so it is polled first to
+        // keep the relay from reporting a soma that finished on purpose
+        self.handle.spawn(
+            closed_rx
+                .then(|_| Ok(()))
+                .select(relay)
+                .map(|_| ())
+                .map_err(|_| ()),
+        );
+
+        self.mailboxes.borrow_mut().insert(uuid, tx.clone());
+        self.somas.insert(uuid, tx);
+
+        (soma_rx, closed_tx)
+    }
+
+    #[async]
+    fn run_soma<U, S>(
+        mut soma: U,
+        soma_rx: S,
+        ctx: SomaContext,
+        home: Rc<RefCell<Home<T::Synapse>>>,
+        restart: Option<Restart<U>>,
+    ) -> std::result::Result<bool, Error>
+    where
+        U: Soma + 'static,
+        U::Synapse: From<T::Synapse>,
+        <U::Synapse as Synapse>::Dendrite: From<<T::Synapse as Synapse>::Dendrite>,
+        <U::Synapse as Synapse>::Terminal: From<<T::Synapse as Synapse>::Terminal>,
+        S: Stream<Item = Impulse<U::Synapse>, Error = Error> + 'static,
+    {
+        let mut started = None;
+        let mut retries = 0;
+
+        let handles_removal = soma.handles_removal();
+        let soma_rx = soma_rx.filter(move |imp| match imp.kind() {
+            ImpulseKind::RemoveDendrite | ImpulseKind::RemoveTerminal => {
+                handles_removal
+            },
+            _ => true,
+        });
+
+        let soma_rx = Pausable::new(soma_rx, !soma.handles_pause());
+
+        #[async]
+        for imp in soma_rx {
+            let kind = imp.kind();
+
+            // everything queued ahead of it has already been handled
+            if kind == ImpulseKind::StopDraining {
+                await!(soma.on_stop()).map_err(|e| -> Error { e.into() })?;
+
+                return Ok(true);
+            }
+
+            if let Impulse::Start(uuid, ref tx, ref handle) = imp {
+                started = Some((uuid, tx.clone(), handle.clone()));
+            }
+
+            #[cfg(feature = "metrics")]
+            let start = Instant::now();
+
+            let watchdog = match soma.update_deadline() {
+                Some(deadline) => {
+                    let home = home.borrow().clone();
+
+                    Some(Self::watch_update(
+                        &ctx,
+                        home.uuid,
+                        deadline,
+                        home.subscribers,
+                    )?)
+                },
+                None => None,
+            };
+
+            // an injected fault takes the place of the update
+            #[cfg(feature = "chaos")]
+            let fault = match ctx.faults {
+                Some(ref faults) => faults.check(),
+                None => Ok(()),
+            };
+            #[cfg(not(feature = "chaos"))]
+            let fault = Ok(());
+
+            let updated = match fault {
+                Ok(()) => {
+                    await!(soma.update(imp)).map_err(|e| -> Error { e.into() })
+                },
+                Err(e) => Err(e),
+            };
+
+            soma = match updated {
+                Ok(soma) => soma,
+                Err(e) => {
+                    let restart = match restart {
+                        Some(ref restart) if restart.policy.allows(retries) => {
+                            restart.clone()
+                        },
+                        _ => return Err(e),
+                    };
+
+                    retries += 1;
+
+                    let home = home.borrow().clone();
+                    let uuid = home.uuid;
+                    let subscribers = Rc::clone(&home.subscribers);
+
+                    let soma = await!(restart.restart(home, started.clone()))
+                        .chain_err(|| {
+                            format!(
+                                "unable to restart soma {} after it failed \
+                                 with {}",
+                                uuid, e
+                            )
+                        })?;
+
+                    emit_topology_change::<T>(
+                        &subscribers,
+                        TopologyEvent::SomaRestarted {
+                            soma: uuid,
+                            attempt: retries,
+                        },
+                    );
+
+                    soma
+                },
+            };
+
+            mem::drop(watchdog);
+
+            ctx.impulses.set(ctx.impulses.get() + 1);
+            ctx.last_update.set(Some(Instant::now()));
+
+            #[cfg(feature = "metrics")]
+            ctx.latency.record(start.elapsed());
+
+            if kind == ImpulseKind::Start {
+                ctx.started.publish(true);
+            }
+
+            if soma.is_done() {
+                await!(soma.on_stop()).map_err(|e| -> Error { e.into() })?;
+
+                return Ok(true);
+            }
+        }
+
+        await!(soma.on_stop()).map_err(|e| -> Error { e.into() })?;
+
+        Ok(false)
+    }
+
+    /// add a soma to the organelle
+    ///
+    /// if the organelle has already started, the soma is sent Start as soon
+    /// as it is added. any connections made to it afterwards arrive after
+    /// Start, so it must be able to accept them at that point.
+    ///
+    /// # Panics
+    ///
+    /// panics if the soma would exceed OrganelleConfig::max_somas. use
+    /// try_add_soma to handle the error directly.
+    pub fn add_soma<U: Soma + 'static>(&mut self, soma: U) -> Uuid
+    where
+        U::Synapse: From<T::Synapse> + Into<T::Synapse>,
+        <U::Synapse as Synapse>::Dendrite: From<<T::Synapse as Synapse>::Dendrite>
+            + Into<<T::Synapse as Synapse>::Dendrite>,
+        <U::Synapse as Synapse>::Terminal: From<<T::Synapse as Synapse>::Terminal>
+            + Into<<T::Synapse as Synapse>::Terminal>,
+    {
+        self.add_soma_with_settings(soma, SomaSettings::default())
+    }
+
+    /// add a soma to the organelle and get a handle that remembers its type
+    ///
+    /// the handle can be used anywhere a uuid is expected by connect, but
+    /// functions that take a SomaHandle<U> only accept handles to a U.
+    pub fn add_typed_soma<U: Soma + 'static>(
+        &mut self,
+        soma: U,
+    ) -> SomaHandle<U>
+    where
+        U::Synapse: From<T::Synapse> + Into<T::Synapse>,
+        <U::Synapse as Synapse>::Dendrite: From<<T::Synapse as Synapse>::Dendrite>
+            + Into<<T::Synapse as Synapse>::Dendrite>,
+        <U::Synapse as Synapse>::Terminal: From<<T::Synapse as Synapse>::Terminal>
+            + Into<<T::Synapse as Synapse>::Terminal>,
+    {
+        SomaHandle::new(self.add_soma(soma))
+    }
+
+    /// add a soma to the organelle with the given settings
+    ///
+    /// # Panics
+    ///
+    /// panics if the soma would exceed OrganelleConfig::max_somas. use
+    /// try_add_soma_with_settings to handle the error directly.
+    pub fn add_soma_with_settings<U: Soma + 'static>(
+        &mut self,
+        soma: U,
+        settings: SomaSettings,
+    ) -> Uuid
+    where
+        U::Synapse: From<T::Synapse> + Into<T::Synapse>,
+        <U::Synapse as Synapse>::Dendrite: From<<T::Synapse as Synapse>::Dendrite>
+            + Into<<T::Synapse as Synapse>::Dendrite>,
+        <U::Synapse as Synapse>::Terminal: From<<T::Synapse as Synapse>::Terminal>
+            + Into<<T::Synapse as Synapse>::Terminal>,
+    {
+        match self.try_add_soma_with_settings(soma, settings) {
+            Ok(uuid) => uuid,
+            Err(e) => panic!(
+                "unable to add soma (use try_add_soma_with_settings to \
+                 handle this): {}",
+                e
+            ),
+        }
+    }
+
+    /// add a soma to the organelle, failing if it would exceed max_somas
+    pub fn try_add_soma<U: Soma + 'static>(&mut self, soma: U) -> Result<Uuid>
+    where
+        U::Synapse: From<T::Synapse> + Into<T::Synapse>,
+        <U::Synapse as Synapse>::Dendrite: From<<T::Synapse as Synapse>::Dendrite>
+            + Into<<T::Synapse as Synapse>::Dendrite>,
+        <U::Synapse as Synapse>::Terminal: From<<T::Synapse as Synapse>::Terminal>
+            + Into<<T::Synapse as Synapse>::Terminal>,
+    {
+        self.try_add_soma_with_settings(soma, SomaSettings::default())
+    }
+
+    /// add a soma with the given settings, failing if it would exceed
+    /// max_somas
+    pub fn try_add_soma_with_settings<U: Soma + 'static>(
+        &mut self,
+        soma: U,
+        settings: SomaSettings,
+    ) -> Result<Uuid>
+    where
+        U::Synapse: From<T::Synapse> + Into<T::Synapse>,
+        <U::Synapse as Synapse>::Dendrite: From<<T::Synapse as Synapse>::Dendrite>
+            + Into<<T::Synapse as Synapse>::Dendrite>,
+        <U::Synapse as Synapse>::Terminal: From<<T::Synapse as Synapse>::Terminal>
+            + Into<<T::Synapse as Synapse>::Terminal>,
+    {
+        let uuid = self.spawn_soma(Uuid::new_v4(), soma, settings, None)?;
+        self.start_late(uuid);
+
+        Ok(uuid)
+    }
+
+    /// add a soma that is replaced with a fresh one when an update fails
+    ///
+    /// the factory creates the first soma and each of its replacements. a
+    /// replacement is sent AddDendrite and AddTerminal for every connection
+    /// the soma had, using newly formed synapses, and Start if the failed
+    /// soma had been started. each peer is sent RemoveDendrite or
+    /// RemoveTerminal for the old synapse before it is sent the new one. the
+    /// impulse that caused the failure is not retried.
+    ///
+    /// # Panics
+    ///
+    /// panics if the soma would exceed OrganelleConfig::max_somas. use
+    /// try_add_soma_with_policy to handle the error directly.
+    pub fn add_soma_with_policy<U, F>(
+        &mut self,
+        factory: F,
+        policy: RestartPolicy,
+    ) -> Uuid
+    where
+        U: Soma + 'static,
+        F: Fn() -> U + 'static,
+        U::Synapse: From<T::Synapse> + Into<T::Synapse>,
+        <U::Synapse as Synapse>::Dendrite: From<<T::Synapse as Synapse>::Dendrite>
+            + Into<<T::Synapse as Synapse>::Dendrite>,
+        <U::Synapse as Synapse>::Terminal: From<<T::Synapse as Synapse>::Terminal>
+            + Into<<T::Synapse as Synapse>::Terminal>,
+    {
+        match self.try_add_soma_with_policy(factory, policy) {
+            Ok(uuid) => uuid,
+            Err(e) => panic!(
+                "unable to add soma (use try_add_soma_with_policy to handle \
+                 this): {}",
+                e
+            ),
+        }
+    }
+
+    /// add a soma with a restart policy, failing if it would exceed
+    /// max_somas
+    pub fn try_add_soma_with_policy<U, F>(
+        &mut self,
+        factory: F,
+        policy: RestartPolicy,
+    ) -> Result<Uuid>
+    where
+        U: Soma + 'static,
+        F: Fn() -> U + 'static,
+        U::Synapse: From<T::Synapse> + Into<T::Synapse>,
+        <U::Synapse as Synapse>::Dendrite: From<<T::Synapse as Synapse>::Dendrite>
+            + Into<<T::Synapse as Synapse>::Dendrite>,
+        <U::Synapse as Synapse>::Terminal: From<<T::Synapse as Synapse>::Terminal>
+            + Into<<T::Synapse as Synapse>::Terminal>,
+    {
+        let restart = Restart {
+            factory: Rc::new(factory),
+            policy: policy,
+        };
+
+        let soma = (restart.factory)();
+
+        let uuid = self.spawn_soma(
+            Uuid::new_v4(),
+            soma,
+            SomaSettings::default(),
+            Some(restart),
+        )?;
+        self.start_late(uuid);
+
+        Ok(uuid)
+    }
+
+    fn spawn_soma<U: Soma + 'static>(
+        &mut self,
+        uuid: Uuid,
+        soma: U,
+        settings: SomaSettings,
+        restart: Option<Restart<U>>,
+    ) -> Result<Uuid>
+    where
+        U::Synapse: From<T::Synapse> + Into<T::Synapse>,
+        <U::Synapse as Synapse>::Dendrite: From<<T::Synapse as Synapse>::Dendrite>
+            + Into<<T::Synapse as Synapse>::Dendrite>,
+        <U::Synapse as Synapse>::Terminal: From<<T::Synapse as Synapse>::Terminal>
+            + Into<<T::Synapse as Synapse>::Terminal>,
+    {
+        self.check_capacity(1)?;
+
+        let (soma_rx, closed) =
+            self.create_soma_channel::<U::Synapse>(uuid, settings.overflow);
+
+        if let Some((dendrites, terminals)) = soma.constraints() {
+            let dendrites: Option<Vec<_>> =
+                dendrites.into_iter().map(|c| c.convert()).collect();
+            let terminals: Option<Vec<_>> =
+                terminals.into_iter().map(|c| c.convert()).collect();
+
+            // constraints given by a predicate can't be converted, so the
+            // soma is left to check its connections as they arrive
+            if let (Some(dendrites), Some(terminals)) = (dendrites, terminals) {
+                self.constraints.insert(uuid, (dendrites, terminals));
+            }
+        }
+
+        let history = settings.history.map(|len| {
+            let history = Rc::new(RefCell::new(VecDeque::with_capacity(len)));
+            self.history.insert(uuid, Rc::clone(&history));
+
+            (len, history)
+        });
+
+        let soma_rx = soma_rx
+            .inspect(move |imp| {
+                if let Some((len, ref history)) = history {
+                    let mut history = history.borrow_mut();
+
+                    if history.len() >= len {
+                        history.pop_front();
+                    }
+
+                    if len > 0 {
+                        history.push_back(imp.record());
+                    }
+                }
+            })
+            .map_err(|_| -> Error { unreachable!() });
+
+        let ctx = SomaContext::new(&settings, self.handle.clone());
+
+        #[cfg(feature = "chaos")]
+        let ctx = {
+            let faults = chaos::Faults::new(self.rng.clone());
+            self.faults.insert(uuid, faults.clone());
+
+            SomaContext {
+                faults: Some(faults),
+                ..ctx
+            }
+        };
+
+        self.contexts.insert(uuid, ctx.clone());
+
+        let home = Rc::clone(&self.homes[&uuid]);
+
+        let main_tx = self.main_tx.clone();
+        let name = soma.name().into_owned();
+        let done_home = Rc::clone(&home);
+        let failed_home = Rc::clone(&home);
+        let stopped = Rc::clone(&ctx.stopped);
+
+        self.handle.spawn(
+            Self::run_soma(soma, soma_rx, ctx, home, restart)
+                .then(move |result| {
+                    stopped.publish(true);
+
+                    result
+                })
+                .and_then(move |done| {
+                    if !done {
+                        return Either::A(future::ok(()));
+                    }
+
+                    let home = done_home.borrow().clone();
+
+                    home.finished.borrow_mut().push(home.uuid);
+
+                    if let Some(stop_tx) = home.stop_tx {
+                        // other somas are reaped on the next update, but
+                        // nothing is left to send one once the nucleus is
+                        // done, so stop the organelle right away
+                        Either::B(
+                            stop_tx
+                                .send(Impulse::Stop)
+                                .map(|_| ())
+                                .map_err(|_| {
+                                    Error::from("unable to stop the organelle")
+                                }),
+                        )
+                    } else {
+                        Either::A(future::ok(()))
+                    }
+                })
+                .or_else(move |e| {
+                    let uuid = failed_home.borrow().uuid;
+                    let e = e.chain_err(|| {
+                        format!("soma {} ({}) failed", name, uuid)
+                    });
+
+                    main_tx
+                        .send(Impulse::Error(e))
+                        .map(|_| ())
+                        .map_err(|_| ())
+                })
+                .then(move |result| {
+                    // the soma is gone, so end its relay
+                    mem::drop(closed);
+
+                    result
+                }),
+        );
+
+        self.emit_topology_change(TopologyEvent::SomaAdded(uuid));
+
+        Ok(uuid)
+    }
+
+    /// send Start to a soma added after the organelle started
+    fn start_late(&self, uuid: Uuid) {
+        if let Some((ref tx, ref handle)) = self.started {
+            let main_tx = tx.clone();
+
+            handle.spawn(
+                self.somas[&uuid]
+                    .clone()
+                    .send(Impulse::Start(uuid, tx.clone(), handle.clone()))
+                    .map(|_| ())
+                    .or_else(move |e| {
+                        let msg = format!("unable to start soma {}", uuid);
+                        let e = Error::with_chain(e, ErrorKind::Msg(msg));
+
+                        main_tx
+                            .send(Impulse::Error(e))
+                            .map(|_| ())
+                            .map_err(|_| ())
+                    }),
+            );
+        }
+    }
+
+    /// swap the soma behind a uuid for another one
+    ///
+    /// the old soma's mailbox is dropped, so it stops once it has handled
+    /// the impulses already queued for it (see remove_soma). the new soma
+    /// gets a fresh mailbox under the same uuid and is sent AddDendrite and
+    /// AddTerminal for every connection made to the old one with connect,
+    /// using newly formed synapses, followed by Start if the organelle has
+    /// started. each peer is sent RemoveDendrite or RemoveTerminal for the
+    /// old synapse before it is sent the new one.
+    ///
+    /// any state in the old soma is lost unless it is transferred to the new
+    /// one explicitly. the new soma is added with the default settings.
+    pub fn replace_soma<U: Soma + 'static>(
+        &mut self,
+        uuid: Uuid,
+        soma: U,
+    ) -> Result<()>
+    where
+        U::Synapse: From<T::Synapse> + Into<T::Synapse>,
+        <U::Synapse as Synapse>::Dendrite: From<<T::Synapse as Synapse>::Dendrite>
+            + Into<<T::Synapse as Synapse>::Dendrite>,
+        <U::Synapse as Synapse>::Terminal: From<<T::Synapse as Synapse>::Terminal>
+            + Into<<T::Synapse as Synapse>::Terminal>,
+    {
+        if self.somas.remove(&uuid).is_none() {
+            bail!("unable to find soma")
+        }
+
+        self.emit_topology_change(TopologyEvent::SomaRemoved(uuid));
+
+        self.mailboxes.borrow_mut().remove(&uuid);
+        self.history.remove(&uuid);
+        self.watchers.remove(&uuid);
+        self.contexts.remove(&uuid);
+        self.homes.remove(&uuid);
+        self.constraints.remove(&uuid);
+
+        #[cfg(feature = "chaos")]
+        {
+            self.latency.remove(&uuid);
+            self.faults.remove(&uuid);
+        }
+
+        self.spawn_soma(uuid, soma, SomaSettings::default(), None)?;
+
+        let (peers, mut own) = rewire(&self.connections.borrow(), uuid);
+        own.extend(rewire_host(
+            &mut self.host_terminals.borrow_mut(),
+            self.host,
+            uuid,
+        ));
+
+        if let Some((ref tx, ref handle)) = self.started {
+            own.push(Impulse::Start(uuid, tx.clone(), handle.clone()));
+        }
+
+        let mailbox = self.somas[&uuid].clone();
+        let main_tx = self.main_tx.clone();
+
+        self.handle.spawn(
+            replace_peers(Rc::clone(&self.mailboxes), peers)
+                .and_then(move |_| {
+                    stream::iter_ok(own).fold(mailbox, |mailbox, imp| {
+                        mailbox.send(imp)
+                    })
+                })
+                .map(|_| ())
+                .or_else(move |e| {
+                    main_tx
+                        .send(Impulse::Error(e))
+                        .map(|_| ())
+                        .map_err(|_| ())
+                }),
+        );
+
+        Ok(())
+    }
+
+    /// remove a soma from the organelle
+    ///
+    /// the organelle drops its handle to the soma's mailbox. once any other
+    /// handles are gone, the soma's relay ends and the soma stops after its
+    /// current update. impulses already queued in the mailbox are still
+    /// delivered before it stops, but nothing new can be sent to it through
+    /// the organelle. connections to the soma are forgotten, but the synapses
+    /// themselves are left to close as their ends are dropped.
+    ///
+    /// the nucleus cannot be removed.
+    pub fn remove_soma(&mut self, uuid: Uuid) -> Result<()> {
+        if uuid == self.nucleus() {
+            bail!("unable to remove the nucleus")
+        }
+
+        if self.somas.remove(&uuid).is_none() {
+            bail!("unable to find soma")
+        }
+
+        self.emit_topology_change(TopologyEvent::SomaRemoved(uuid));
+
+        // anything nested in the soma goes with it
+        let mut removed: Vec<_> = self.routes
+            .keys()
+            .filter(|path| path[0] == uuid)
+            .map(|path| path[path.len() - 1])
+            .collect();
+        removed.push(uuid);
+
+        for uuid in &removed {
+            self.mailboxes.borrow_mut().remove(uuid);
+            self.constraints.remove(uuid);
+        }
+
+        self.history.remove(&uuid);
+        self.watchers.remove(&uuid);
+        self.contexts.remove(&uuid);
+        self.homes.remove(&uuid);
+        self.deferred.retain(|&(dendrite, terminal, _, _)| {
+            dendrite != uuid && terminal != uuid
+        });
+        self.routes.retain(|path, _| path[0] != uuid);
+        self.connections.borrow_mut().retain(|_, c| {
+            !removed.contains(&c.dendrite) && !removed.contains(&c.terminal)
+        });
+        self.nested_connections.retain(|c| {
+            !removed.contains(&c.dendrite) && !removed.contains(&c.terminal)
+        });
+        self.host_terminals
+            .borrow_mut()
+            .retain(|&(dest, _), _| dest != uuid);
+        {
+            let connections = self.connections.borrow();
+            self.connection_meta
+                .borrow_mut()
+                .retain(|c, _| connections.contains_key(c));
+        }
+
+        #[cfg(feature = "chaos")]
+        {
+            self.latency.remove(&uuid);
+            self.faults.remove(&uuid);
+        }
+
+        Ok(())
+    }
+
+    /// remove the somas that finished since the last impulse, see
+    /// Soma::is_done
+    ///
+    /// the nucleus cannot be removed, so the event loop is sent Stop instead
+    /// once the nucleus finishes.
+    fn reap_finished(&mut self) -> Result<()> {
+        let finished = mem::replace(&mut *self.finished.borrow_mut(), vec![]);
+
+        for uuid in finished {
+            if uuid == self.nucleus() {
+                if let Some((ref tx, ref handle)) = self.started {
+                    handle.spawn(
+                        tx.clone()
+                            .send(Impulse::Stop)
+                            .map(|_| ())
+                            .map_err(|_| ()),
+                    );
+                }
+            } else if self.somas.contains_key(&uuid) {
+                self.remove_soma(uuid)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_capacity(&self, additional: usize) -> Result<()> {
+        if let Some(max) = self.config.max_somas {
+            if self.somas.len() + additional > max {
+                bail!(ErrorKind::TooManySomas(max))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// move all of the somas in another organelle into this one
+    ///
+    /// the other organelle's nucleus becomes a regular soma in this organelle
+    /// and any connections made within the other organelle are preserved.
+    /// the returned map gives the new uuid for each absorbed soma. uuids are
+    /// only changed in the unlikely event of a collision, in which case
+    /// connections previously made to that soma will still refer to the old
+    /// uuid.
+    ///
+    /// both organelles must have been created with handles to the same
+    /// reactor, since the absorbed somas keep running on the tasks that were
+    /// spawned for them. those tasks report to this organelle from then on,
+    /// so a restart rewires the soma using this organelle's connections and
+    /// a soma that is done is reaped here. neither organelle may have been
+    /// started yet.
+    pub fn absorb<U>(
+        &mut self,
+        mut other: Organelle<U>,
+    ) -> Result<HashMap<Uuid, Uuid>>
+    where
+        U: Soma<Synapse = T::Synapse> + 'static,
+    {
+        if self.uuid.is_some() || other.uuid.is_some() {
+            bail!("unable to absorb an organelle after it has started")
+        }
+
+        self.check_capacity(other.somas.len())?;
+
+        let other_rx = if let Some(rx) = mem::replace(&mut other.main_rx, None)
+        {
+            rx
+        } else {
+            bail!("unable to absorb an organelle after it has started")
+        };
+
+        // errors from the absorbed somas now go to this organelle
+        self.handle.spawn(
+            self.main_tx
+                .clone()
+                .send_all(other_rx.map_err(|_| unreachable!()))
+                .map(|_| ())
+                .map_err(|_| ()),
+        );
+
+        let mut remap = HashMap::new();
+
+        for (uuid, mailbox) in other.somas.drain() {
+            let new_uuid = if self.somas.contains_key(&uuid) {
+                let new_uuid = Uuid::new_v4();
+
+                eprintln!(
+                    "uuid collision while absorbing soma {}, rehashed to {}",
+                    uuid, new_uuid
+                );
+
+                new_uuid
+            } else {
+                uuid
+            };
+
+            if let Some(history) = other.history.remove(&uuid) {
+                self.history.insert(new_uuid, history);
+            }
+            if let Some(ctx) = other.contexts.remove(&uuid) {
+                self.contexts.insert(new_uuid, ctx);
+            }
+            if let Some(watcher) = other.watchers.remove(&uuid) {
+                self.watchers.insert(new_uuid, watcher);
+            }
+            if let Some(home) = other.homes.remove(&uuid) {
+                // the task now reports to this organelle under the new uuid,
+                // and the other nucleus no longer stops anything when done
+                *home.borrow_mut() = self.home(new_uuid);
+                self.homes.insert(new_uuid, home);
+            }
+            if let Some(constraints) = other.constraints.remove(&uuid) {
+                self.constraints.insert(new_uuid, constraints);
+            }
+
+            #[cfg(feature = "chaos")]
+            {
+                if let Some(latency) = other.latency.remove(&uuid) {
+                    self.latency.insert(new_uuid, latency);
+                }
+                if let Some(faults) = other.faults.remove(&uuid) {
+                    self.faults.insert(new_uuid, faults);
+                }
+            }
+
+            other.mailboxes.borrow_mut().remove(&uuid);
+            self.mailboxes
+                .borrow_mut()
+                .insert(new_uuid, mailbox.clone());
+            self.somas.insert(new_uuid, mailbox);
+            remap.insert(uuid, new_uuid);
+
+            self.emit_topology_change(TopologyEvent::SomaAdded(new_uuid));
+        }
+
+        for (mut path, mailbox) in other.routes.drain() {
+            path[0] = remap[&path[0]];
+
+            // somas nested in the absorbed ones keep their uuids
+            let leaf = path[path.len() - 1];
+
+            if let Some(constraints) = other.constraints.remove(&leaf) {
+                self.constraints.insert(leaf, constraints);
+            }
+            self.mailboxes.borrow_mut().insert(leaf, mailbox.clone());
+            self.routes.insert(path, mailbox);
+        }
+
+        self.nested_connections.extend(other.nested_connections.drain(..));
+
+        for ((dest, synapse), terminal) in
+            other.host_terminals.borrow_mut().drain()
+        {
+            self.host_terminals
+                .borrow_mut()
+                .insert((remap[&dest], synapse), terminal);
+        }
+
+        // keep the connections around for topology snapshots and restarts
+        for (uuid, mut connection) in other.connections.borrow_mut().drain() {
+            if let Some(dendrite) = remap.get(&connection.dendrite) {
+                connection.dendrite = *dendrite;
+            }
+            if let Some(terminal) = remap.get(&connection.terminal) {
+                connection.terminal = *terminal;
+            }
+
+            self.connections.borrow_mut().insert(uuid, connection);
+        }
+
+        for (uuid, meta) in other.connection_meta.borrow_mut().drain() {
+            self.connection_meta.borrow_mut().insert(uuid, meta);
+        }
+
+        Ok(remap)
+    }
+
+    /// add a child organelle whose somas can be connected to by path
+    ///
+    /// this is the same as add_soma, except that the parent also keeps a
+    /// route to every soma inside the child (and inside its own children),
+    /// see Organelle::connect_path. the child must use the same synapse as
+    /// the parent, which spares the conversion bounds that add_soma needs.
+    ///
+    /// the returned uuid refers to the child as a whole. once started, a
+    /// probe reports it as a SomaData::Organelle with its own nested somas.
+    ///
+    /// ```
+    /// extern crate futures_await as futures;
+    /// extern crate organelle;
+    /// extern crate tokio_core;
+    ///
+    /// use futures::prelude::*;
+    /// use organelle::*;
+    /// use tokio_core::reactor;
+    ///
+    /// #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    /// enum Synapse {
+    ///     Nothing,
+    /// }
+    ///
+    /// impl organelle::Synapse for Synapse {
+    ///     type Terminal = ();
+    ///     type Dendrite = ();
+    ///
+    ///     fn synapse(self) -> ((), ()) {
+    ///         ((), ())
+    ///     }
+    /// }
+    ///
+    /// struct IdleSoma;
+    ///
+    /// impl Soma for IdleSoma {
+    ///     type Synapse = Synapse;
+    ///     type Error = Error;
+    ///
+    ///     fn update(
+    ///         self,
+    ///         _imp: Impulse<Synapse>,
+    ///     ) -> Box<Future<Item = Self, Error = Error>> {
+    ///         Box::new(futures::future::ok(self))
+    ///     }
+    /// }
+    ///
+    /// # fn main() {
+    /// let core = reactor::Core::new().unwrap();
+    ///
+    /// let mut child = Organelle::new(IdleSoma, core.handle());
+    /// let leaf = child.add_soma(IdleSoma);
+    ///
+    /// let mut parent = Organelle::new(IdleSoma, core.handle());
+    /// let nested = parent.add_organelle(child);
+    ///
+    /// // the leaf is reached through the child by its path
+    /// parent
+    ///     .connect_path(
+    ///         &[parent.nucleus()],
+    ///         &[nested, leaf],
+    ///         Synapse::Nothing,
+    ///     )
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn add_organelle<U>(&mut self, child: Organelle<U>) -> Uuid
+    where
+        U: Soma<Synapse = T::Synapse> + 'static,
+    {
+        let routes: Vec<_> = child
+            .somas
+            .iter()
+            .map(|(uuid, mailbox)| (vec![*uuid], mailbox.clone()))
+            .chain(
+                child
+                    .routes
+                    .iter()
+                    .map(|(path, mailbox)| (path.clone(), mailbox.clone())),
+            )
+            .collect();
+
+        // connect_path checks nested somas against their constraints and
+        // the connections already made to them
+        let constraints: Vec<_> = child
+            .constraints
+            .iter()
+            .map(|(uuid, constraints)| (*uuid, constraints.clone()))
+            .collect();
+        let connections: Vec<_> = child
+            .connections
+            .borrow()
+            .values()
+            .chain(child.nested_connections.iter())
+            .cloned()
+            .collect();
+
+        let uuid = self.add_soma(child);
+
+        for (path, mailbox) in routes {
+            self.mailboxes
+                .borrow_mut()
+                .insert(path[path.len() - 1], mailbox.clone());
+
+            let mut full_path = vec![uuid];
+            full_path.extend(path);
+
+            self.routes.insert(full_path, mailbox);
+        }
+
+        self.constraints.extend(constraints);
+        self.nested_connections.extend(connections);
+
+        uuid
+    }
+
+    /// create the sender for the value this organelle produces
+    ///
+    /// give the sender to the soma that computes the result. that soma
+    /// should send the value and then stop the organelle, at which point the
+    /// future returned by run_with_result resolves to the value. creating a
+    /// new sender replaces the previous one.
+    pub fn result_sender<R: 'static>(&mut self) -> ResultSender<R> {
+        let (tx, rx) = oneshot::channel::<R>();
+
+        self.result = Some(Box::new(rx));
+
+        ResultSender { tx: tx }
+    }
+
+    /// run the organelle until it stops and resolve to its result
+    ///
+    /// fails if the organelle fails or if it stops without a result being
+    /// sent through Organelle::result_sender.
+    pub fn run_with_result<R: 'static>(
+        mut self,
+        handle: reactor::Handle,
+    ) -> Box<Future<Item = R, Error = Error>> {
+        let rx = match self.result.take() {
+            Some(rx) => match rx.downcast::<oneshot::Receiver<R>>() {
+                Ok(rx) => *rx,
+                Err(_) => {
+                    return Box::new(future::err(Error::from(
+                        "organelle produces a different type of result",
+                    )))
+                },
+            },
+            None => {
+                return Box::new(future::err(Error::from(
+                    "organelle has no result sender",
+                )))
+            },
+        };
+
+        Box::new(self.run(handle).and_then(move |_| {
+            rx.map_err(|_| {
+                Error::from("organelle stopped without producing a result")
+            })
+        }))
+    }
+
+    /// spawn the organelle onto the reactor and get a future for its outcome
+    ///
+    /// the future resolves once the organelle stops, or fails with the same
+    /// error that stopped it. dropping the future does not stop the
+    /// organelle.
+    pub fn spawn_on(
+        self,
+        handle: reactor::Handle,
+    ) -> Box<Future<Item = (), Error = Error>> {
+        let (tx, rx) = oneshot::channel();
+
+        handle.spawn(self.run(handle.clone()).then(move |result| {
+            // nobody may be waiting on the outcome
+            let _ = tx.send(result);
+
+            Ok(())
+        }));
+
+        Box::new(rx.then(|result| match result {
+            Ok(result) => result,
+            Err(_) => Err(Error::from("organelle ended without an outcome")),
+        }))
+    }
+
+    /// register the watcher for a soma's published state
+    ///
+    /// the soma keeps the matching watch::Publisher and publishes its state
+    /// whenever it changes. see Organelle::await_soma_state.
+    pub fn watch_soma<S: 'static>(
+        &mut self,
+        soma: Uuid,
+        watcher: Watcher<S>,
+    ) -> Result<()> {
+        if !self.somas.contains_key(&soma) {
+            bail!("unable to find soma")
+        }
+
+        self.watchers.insert(soma, Box::new(watcher));
+
+        Ok(())
+    }
+
+    /// find the watcher registered for a soma with Organelle::watch_soma
+    fn state_watcher<S>(&self, soma: Uuid) -> Result<Watcher<S>>
+    where
+        S: Clone + 'static,
+    {
+        let watcher = match self.watchers.get(&soma) {
+            Some(watcher) => watcher.downcast_ref::<Watcher<S>>().cloned(),
+            None => bail!("no state is being watched for soma"),
+        };
+
+        match watcher {
+            Some(watcher) => Ok(watcher),
+            None => bail!("soma publishes a different type of state"),
+        }
+    }
+
+    /// resolve once the state published by the soma satisfies the predicate
+    ///
+    /// the predicate is always checked against the latest published state,
+    /// so a stale value is never reported once it has been replaced.
+    pub fn await_soma_state<S, F>(
+        &self,
+        soma: Uuid,
+        predicate: F,
+    ) -> Box<Future<Item = S, Error = Error>>
+    where
+        S: Clone + 'static,
+        F: Fn(&S) -> bool + 'static,
+    {
+        match self.state_watcher(soma) {
+            Ok(watcher) => Box::new(watcher.wait_for(predicate)),
+            Err(e) => Box::new(future::err(e)),
+        }
+    }
+
+    /// get the latest state published by a soma
+    ///
+    /// each soma is moved into its own task once it is added, so the
+    /// organelle can never lend out a reference to it. instead, the soma
+    /// keeps a watch::Publisher and the organelle reads a snapshot through
+    /// the watcher registered with Organelle::watch_soma. this is all built
+    /// on unsync channels, so it only works from the thread running the
+    /// reactor, and the snapshot may lag behind an update still in progress.
+    /// to change a soma's state, send it impulses instead.
+    pub fn soma_state<S: Clone + 'static>(&self, soma: Uuid) -> Result<S> {
+        Ok(self.state_watcher(soma)?.get())
+    }
+
+    /// get the latest state published by the nucleus, see soma_state
+    pub fn nucleus_state<S: Clone + 'static>(&self) -> Result<S> {
+        self.soma_state(self.nucleus())
+    }
+
+    /// get the last impulses received by the soma, oldest first
+    ///
+    /// history must be enabled with SomaSettings::history when the soma is
+    /// added.
+    pub fn soma_history(&self, soma: Uuid) -> Result<Vec<ImpulseRecord>> {
+        if let Some(history) = self.history.get(&soma) {
+            Ok(history.borrow().iter().cloned().collect())
+        } else if self.somas.contains_key(&soma) {
+            bail!("history is not enabled for soma")
+        } else {
+            bail!("unable to find soma")
+        }
+    }
+
+    /// get the update latency percentiles for a soma
+    ///
+    /// this is only available with the `metrics` feature.
+    #[cfg(feature = "metrics")]
+    pub fn soma_latency(&self, soma: Uuid) -> Result<Percentiles> {
+        if let Some(ctx) = self.contexts.get(&soma) {
+            Ok(ctx.latency.percentiles())
+        } else {
+            bail!("unable to find soma")
+        }
+    }
+
+    /// the number of impulses dropped because the soma's mailbox was full
+    pub fn dropped_impulses(&self, soma: Uuid) -> Result<usize> {
+        if let Some(mailbox) = self.somas.get(&soma) {
+            Ok(mailbox.dropped())
+        } else {
+            bail!("unable to find soma")
+        }
+    }
+
+    /// connect two somas together using the specified synapse
+    ///
+    /// either soma can be given as a uuid or as a SomaHandle.
+    pub fn connect<D, E>(
+        &self,
+        dendrite: D,
+        terminal: E,
+        synapse: T::Synapse,
+    ) -> Result<Connection<T::Synapse>>
+    where
+        D: Into<Uuid>,
+        E: Into<Uuid>,
+    {
+        self.connect_role(dendrite, terminal, synapse)
+    }
+
+    /// connect two somas together and attach a label or weight to it
+    ///
+    /// the metadata has no effect on the synapse itself. it is reported
+    /// alongside the connection when the organelle is probed, so it shows up
+    /// in the probe api and as an edge label in the visualizer.
+    pub fn connect_with<D, E>(
+        &self,
+        dendrite: D,
+        terminal: E,
+        synapse: T::Synapse,
+        meta: ConnectionMeta,
+    ) -> Result<Connection<T::Synapse>>
+    where
+        D: Into<Uuid>,
+        E: Into<Uuid>,
+    {
+        let connection = self.connect_role(dendrite, terminal, synapse)?;
+
+        self.connection_meta
+            .borrow_mut()
+            .insert(connection.uuid, meta);
+
+        Ok(connection)
+    }
+
+    /// connect two somas together using any role that converts to a synapse
+    ///
+    /// somas don't declare a separate role type, since a synapse already
+    /// forms both of its ends in Synapse::synapse. this accepts the synapse
+    /// of a soma added to this organelle, so a child's own synapse enum can
+    /// be used directly instead of building the organelle's by hand. the
+    /// dendrite soma always receives the terminal end and the terminal soma
+    /// the dendrite end, just like connect.
+    pub fn connect_role<D, E, R>(
+        &self,
+        dendrite: D,
+        terminal: E,
+        role: R,
+    ) -> Result<Connection<T::Synapse>>
+    where
+        D: Into<Uuid>,
+        E: Into<Uuid>,
+        R: Into<T::Synapse>,
+    {
+        let (dendrite, terminal) = (dendrite.into(), terminal.into());
+        let synapse = role.into();
+
+        self.check_connection(dendrite, terminal, synapse)?;
+
+        if self.config.reject_cycles && self.is_one(dendrite, terminal, synapse)
+        {
+            self.check_cycle(dendrite, terminal)?;
+        }
+
+        let (tx, rx) = synapse.synapse();
+
+        self.add_terminal((terminal, tx), dendrite, synapse)?;
+        self.add_dendrite((dendrite, rx), terminal, synapse)?;
+
+        let connection = Connection {
+            uuid: Uuid::new_v4(),
+            dendrite: dendrite,
+            terminal: terminal,
+            synapse: synapse,
+        };
+
+        self.connections
+            .borrow_mut()
+            .insert(connection.uuid, connection);
+
+        self.emit_topology_change(TopologyEvent::Connected {
+            dendrite: dendrite,
+            terminal: terminal,
+            synapse: synapse,
+        });
+
+        Ok(connection)
+    }
+
+    /// check a new connection against the constraints of both somas
+    fn check_connection(
+        &self,
+        dendrite: Uuid,
+        terminal: Uuid,
+        synapse: T::Synapse,
+    ) -> Result<()> {
+        self.check_variadic(dendrite, terminal, synapse)?;
+
+        let connections = self.connections.borrow();
+        let connections: Vec<_> = connections
+            .values()
+            .chain(self.nested_connections.iter())
+            .collect();
+
+        // the dendrite soma is handed the terminal end of the synapse
+        if let Some(&(_, ref terminals)) = self.constraints.get(&dendrite) {
+            let existing = connections
+                .iter()
+                .filter(|c| c.dendrite == dendrite && c.synapse == synapse)
+                .count();
+
+            Self::check_constraint(
+                "terminal",
+                dendrite,
+                terminals,
+                synapse,
+                existing,
+            )?;
+        }
+
+        if let Some(&(ref dendrites, _)) = self.constraints.get(&terminal) {
+            let host = self.host_terminals
+                .borrow()
+                .contains_key(&(terminal, synapse));
+
+            let existing = connections
+                .iter()
+                .filter(|c| c.terminal == terminal && c.synapse == synapse)
+                .count() + host as usize;
+
+            Self::check_constraint(
+                "dendrite",
+                terminal,
+                dendrites,
+                synapse,
+                existing,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// fail if a peer on a variadic end of a connection can't take the other
+    /// end of the synapse
+    ///
+    /// every peer connected to a Variadic or Range constraint has to accept
+    /// the same synapse on its opposite end. peers whose constraints aren't
+    /// known to the organelle are left to check their own connections.
+    fn check_variadic(
+        &self,
+        dendrite: Uuid,
+        terminal: Uuid,
+        synapse: T::Synapse,
+    ) -> Result<()> {
+        let accepts = |constraints: &[Constraint<T::Synapse>]| {
+            constraints.iter().any(|c| c.accepts(synapse))
+        };
+        let is_variadic = |constraints: &[Constraint<T::Synapse>]| {
+            constraints.iter().any(|c| {
+                c.accepts(synapse) && match c {
+                    &Constraint::Variadic(_)
+                    | &Constraint::VariadicWhere(_)
+                    | &Constraint::Range { .. } => true,
+                    _ => false,
+                }
+            })
+        };
+
+        let ends = (
+            self.constraints.get(&dendrite),
+            self.constraints.get(&terminal),
+        );
+
+        // the dendrite soma is handed the terminal end of the synapse
+        if let (Some(&(_, ref terminals)), Some(&(ref dendrites, _))) = ends {
+            if is_variadic(terminals) && !accepts(dendrites) {
+                bail!(ErrorKind::InvalidSynapse(format!(
+                    "peer {} has no dendrite for {:?}, but it is connected \
+                     to the variadic terminal of soma {}",
+                    terminal, synapse, dendrite
+                )))
+            }
+
+            if is_variadic(dendrites) && !accepts(terminals) {
+                bail!(ErrorKind::InvalidSynapse(format!(
+                    "peer {} has no terminal for {:?}, but it is connected \
+                     to the variadic dendrite of soma {}",
+                    dendrite, synapse, terminal
+                )))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// check_variadic for every connection, in case a soma was replaced
+    fn check_variadic_peers(&self) -> Result<()> {
+        for c in self.connections.borrow().values() {
+            self.check_variadic(c.dendrite, c.terminal, c.synapse)?;
+        }
+
+        Ok(())
+    }
+
+    /// whether either end of a connection has a One constraint on it
+    fn is_one(
+        &self,
+        dendrite: Uuid,
+        terminal: Uuid,
+        synapse: T::Synapse,
+    ) -> bool {
+        let is_one = |constraints: &[Constraint<T::Synapse>]| {
+            constraints.iter().any(|c| *c == Constraint::One(synapse))
+        };
+
+        self.constraints
+            .get(&dendrite)
+            .map_or(false, |&(_, ref terminals)| is_one(terminals))
+            || self.constraints
+                .get(&terminal)
+                .map_or(false, |&(ref dendrites, _)| is_one(dendrites))
+    }
+
+    /// fail if the terminal soma can already reach the dendrite soma through
+    /// One connections
+    fn check_cycle(&self, dendrite: Uuid, terminal: Uuid) -> Result<()> {
+        let mut edges: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        let connections = self.connections.borrow();
+
+        for c in connections.values().chain(self.nested_connections.iter()) {
+            if self.is_one(c.dendrite, c.terminal, c.synapse) {
+                edges
+                    .entry(c.dendrite)
+                    .or_insert_with(Vec::new)
+                    .push(c.terminal);
+            }
+        }
+
+        // breadth first, remembering how each soma was reached
+        let mut parents = HashMap::new();
+        let mut queue = VecDeque::new();
+
+        parents.insert(terminal, terminal);
+        queue.push_back(terminal);
+
+        while let Some(soma) = queue.pop_front() {
+            if soma == dendrite {
+                let mut path = vec![soma];
+
+                while path[path.len() - 1] != terminal {
+                    let parent = parents[&path[path.len() - 1]];
+                    path.push(parent);
+                }
+
+                let through: Vec<_> =
+                    path.iter().rev().map(|uuid| uuid.to_string()).collect();
+
+                bail!(
+                    "connecting {}->{} would create a cycle through {}",
+                    dendrite,
+                    terminal,
+                    through.join("->")
+                )
+            }
+
+            for next in edges.get(&soma).into_iter().flat_map(|e| e) {
+                if !parents.contains_key(next) {
+                    parents.insert(*next, soma);
+                    queue.push_back(*next);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_constraint(
+        role: &str,
+        soma: Uuid,
+        constraints: &[Constraint<T::Synapse>],
+        synapse: T::Synapse,
+        existing: usize,
+    ) -> Result<()> {
+        match constraints.iter().find(|c| c.accepts(synapse)) {
+            Some(c @ &Constraint::One(_)) | Some(c @ &Constraint::Optional(_))
+                if existing > 0 =>
+            {
+                bail!(ErrorKind::InvalidSynapse(format!(
+                    "{} {} already has a {:?} connection",
+                    role, soma, c
+                )))
+            },
+            Some(&Constraint::Range { max, .. }) if existing >= max => {
+                bail!(ErrorKind::InvalidSynapse(format!(
+                    "{} {} already has the {} {:?} connections it accepts",
+                    role, soma, max, synapse
+                )))
+            },
+            Some(_) => Ok(()),
+            None => bail!(ErrorKind::InvalidSynapse(format!(
+                "{} {} has no constraint for {:?}",
+                role, soma, synapse
+            ))),
+        }
+    }
+
+    /// tear down a connection made with Organelle::connect
+    ///
+    /// each soma is sent a RemoveDendrite or RemoveTerminal impulse so that it
+    /// can drop its end of the synapse. once both ends are dropped, any tasks
+    /// reading from or writing to the synapse complete on their own. somas
+    /// only see these impulses if they opt in with Soma::handles_removal,
+    /// otherwise they keep their end until they are dropped. if an impulse
+    /// can't be delivered, the organelle fails.
+    pub fn disconnect<D, E>(
+        &self,
+        dendrite: D,
+        terminal: E,
+        synapse: T::Synapse,
+    ) -> Result<()>
+    where
+        D: Into<Uuid>,
+        E: Into<Uuid>,
+    {
+        let (dendrite, terminal) = (dendrite.into(), terminal.into());
+
+        if self.mailbox(dendrite).is_none() {
+            bail!("unable to find dendrite")
+        }
+        if self.mailbox(terminal).is_none() {
+            bail!("unable to find terminal")
+        }
+
+        let uuid = self.connections
+            .borrow()
+            .values()
+            .find(|c| {
+                c.dendrite == dendrite && c.terminal == terminal
+                    && c.synapse == synapse
+            })
+            .map(|c| c.uuid);
+
+        if let Some(uuid) = uuid {
+            self.connections.borrow_mut().remove(&uuid);
+            self.connection_meta.borrow_mut().remove(&uuid);
+        } else {
+            bail!(
+                "no {:?} connection from {} to {}",
+                synapse,
+                terminal,
+                dendrite
+            )
+        }
+
+        self.remove_terminal(terminal, dendrite, synapse);
+        self.remove_dendrite(dendrite, terminal, synapse);
+
+        self.emit_topology_change(TopologyEvent::Disconnected {
+            dendrite: dendrite,
+            terminal: terminal,
+            synapse: synapse,
+        });
+
+        Ok(())
+    }
+
+    fn remove_dendrite(
+        &self,
+        dendrite: Uuid,
+        terminal: Uuid,
+        synapse: T::Synapse,
+    ) {
+        let mailbox = match self.mailbox(terminal) {
+            Some(mailbox) => mailbox,
+            None => return,
+        };
+        let main_tx = self.main_tx.clone();
+
+        self.handle.spawn(
+            mailbox
+                .send(Impulse::RemoveDendrite(dendrite, synapse))
+                .map(|_| ())
+                .or_else(move |_| {
+                    let e = Error::from("unable to remove dendrite");
+
+                    main_tx
+                        .send(Impulse::Error(e))
+                        .map(|_| ())
+                        .map_err(|_| ())
+                }),
+        );
+    }
+
+    fn remove_terminal(
+        &self,
+        terminal: Uuid,
+        dendrite: Uuid,
+        synapse: T::Synapse,
+    ) {
+        let mailbox = match self.mailbox(dendrite) {
+            Some(mailbox) => mailbox,
+            None => return,
+        };
+        let main_tx = self.main_tx.clone();
+
+        self.handle.spawn(
+            mailbox
+                .send(Impulse::RemoveTerminal(terminal, synapse))
+                .map(|_| ())
+                .or_else(move |_| {
+                    let e = Error::from("unable to remove terminal");
+
+                    main_tx
+                        .send(Impulse::Error(e))
+                        .map(|_| ())
+                        .map_err(|_| ())
+                }),
+        );
+    }
+
+    /// look up a connection made with Organelle::connect
+    pub fn connection(&self, uuid: Uuid) -> Option<Connection<T::Synapse>> {
+        self.connections.borrow().get(&uuid).cloned()
+    }
+
+    /// a snapshot of the somas in this organelle and how they are connected
+    ///
+    /// each soma is paired with the somas it sends to through connections
+    /// made with Organelle::connect, sorted by uuid so that the same wiring
+    /// always gives the same snapshot. an end of a connection made with
+    /// connect_path that lies inside a nested organelle is shown as the soma
+    /// in this organelle that contains it.
+    pub fn topology(&self) -> Vec<(Uuid, Vec<Uuid>)> {
+        let mut edges: HashMap<Uuid, Vec<Uuid>> =
+            self.somas.keys().map(|uuid| (*uuid, vec![])).collect();
+
+        for connection in self.connections.borrow().values() {
+            let ends = (
+                self.outermost(connection.dendrite),
+                self.outermost(connection.terminal),
+            );
+
+            if let (Some(dendrite), Some(terminal)) = ends {
+                if let Some(targets) = edges.get_mut(&dendrite) {
+                    targets.push(terminal);
+                }
+            }
+        }
+
+        let mut topology: Vec<_> = edges
+            .into_iter()
+            .map(|(uuid, mut targets)| {
+                targets.sort();
+                targets.dedup();
+
+                (uuid, targets)
+            })
+            .collect();
+
+        topology.sort();
+
+        topology
+    }
+
+    /// the soma in this organelle that is or contains the given soma
+    fn outermost(&self, uuid: Uuid) -> Option<Uuid> {
+        if self.somas.contains_key(&uuid) {
+            Some(uuid)
+        } else {
+            self.routes
+                .keys()
+                .find(|path| path[path.len() - 1] == uuid)
+                .map(|path| path[0])
+        }
+    }
+
+    /// the mailbox of a soma in this organelle or in a nested one
+    fn mailbox(&self, uuid: Uuid) -> Option<Route<T>> {
+        self.mailboxes.borrow().get(&uuid).cloned()
+    }
+
+    /// connect two somas that may live in nested organelles
+    ///
+    /// each soma is addressed by a path of uuids, starting with a soma in
+    /// this organelle and descending through organelles added with
+    /// add_organelle. a path with a single uuid is a soma in this organelle.
+    /// once the paths are resolved, this is the same as connect: constraints
+    /// are checked on both ends, the connection is recorded (so it shows up
+    /// in topology and probes and can be removed with disconnect) and
+    /// subscribers see TopologyEvent::Connected.
+    ///
+    /// the terminal and dendrite are handed straight to the nested somas, so
+    /// signals don't pass through the organelles in between. everything a
+    /// nested soma sends to its organelle (errors, Stop) does take one extra
+    /// hop per level of nesting though, since each organelle forwards its
+    /// main channel to its parent's. like connect, this must be done before
+    /// the nested organelles are started.
+    pub fn connect_path(
+        &self,
+        dendrite: &[Uuid],
+        terminal: &[Uuid],
+        synapse: T::Synapse,
+    ) -> Result<Connection<T::Synapse>> {
+        let dendrite = self.resolve(dendrite)?;
+        let terminal = self.resolve(terminal)?;
+
+        self.connect(dendrite, terminal, synapse)
+    }
+
+    /// find the uuid of the soma at the end of a path
+    fn resolve(&self, path: &[Uuid]) -> Result<Uuid> {
+        let found = match path.len() {
+            0 => bail!("soma path is empty"),
+            1 => self.somas.contains_key(&path[0]),
+            _ => self.routes.contains_key(path),
+        };
+
+        if found {
+            Ok(path[path.len() - 1])
+        } else {
+            bail!("unable to find soma at path {:?}", path)
+        }
+    }
+
+    /// connect two somas only if the condition holds
+    pub fn connect_if(
+        &self,
+        cond: bool,
+        dendrite: Uuid,
+        terminal: Uuid,
+        synapse: T::Synapse,
+    ) -> Result<Option<Connection<T::Synapse>>> {
+        if cond {
+            Ok(Some(self.connect(dendrite, terminal, synapse)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// connect two somas at Start if the condition holds at that time
+    ///
+    /// the condition is evaluated once, when the organelle receives Start
+    /// and before any of its somas are sent Start, so the connection is in
+    /// place by the time the somas start. both somas must still exist at
+    /// that point or Start fails.
+    pub fn connect_when<F>(
+        &mut self,
+        dendrite: Uuid,
+        terminal: Uuid,
+        synapse: T::Synapse,
+        cond: F,
+    ) where
+        F: Fn(&Organelle<T>) -> bool + 'static,
+    {
+        self.deferred.push((dendrite, terminal, synapse, Box::new(cond)));
+    }
+
+    fn connect_deferred(&mut self) -> Result<()> {
+        let deferred = mem::replace(&mut self.deferred, vec![]);
+
+        for (dendrite, terminal, synapse, cond) in deferred {
+            if cond(self) {
+                self.connect(dendrite, terminal, synapse)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// connect a hub soma to each of the leaves using the same synapse
+    ///
+    /// the hub receives a terminal for every leaf and each leaf receives a
+    /// dendrite. every leaf and the constraints on both ends are checked
+    /// before any of the connections are made, so a failure leaves the
+    /// organelle wired as it was.
+    pub fn connect_all(
+        &self,
+        hub: Uuid,
+        leaves: &[Uuid],
+        synapse: T::Synapse,
+    ) -> Result<()> {
+        if !self.somas.contains_key(&hub) {
+            bail!("unable to find hub {}", hub)
+        }
+
+        for (i, leaf) in leaves.iter().enumerate() {
+            if !self.somas.contains_key(leaf) {
+                bail!("unable to find leaf {}", leaf)
+            }
+            if leaves[..i].contains(leaf) {
+                bail!("leaf {} is given more than once", leaf)
+            }
+
+            self.check_connection(hub, *leaf, synapse)?;
+
+            if self.config.reject_cycles && self.is_one(hub, *leaf, synapse) {
+                self.check_cycle(hub, *leaf)?;
+            }
+        }
+
+        // check_connection only accounts for one new terminal on the hub
+        if leaves.len() > 1 {
+            if let Some(&(_, ref terminals)) = self.constraints.get(&hub) {
+                let existing = self.connections
+                    .borrow()
+                    .values()
+                    .filter(|c| c.dendrite == hub && c.synapse == synapse)
+                    .count();
+
+                Self::check_constraint(
+                    "terminal",
+                    hub,
+                    terminals,
+                    synapse,
+                    existing + leaves.len() - 1,
+                )?;
+            }
+        }
+
+        for leaf in leaves {
+            self.connect(hub, *leaf, synapse)?;
+        }
+
+        Ok(())
+    }
+
+    /// connect each soma to the next one in the list
+    ///
+    /// every link uses the same synapse. use connect_chain_with to give each
+    /// link its own synapse. all of the somas are checked before any of the
+    /// links are made.
+    pub fn connect_chain(
+        &self,
+        somas: &[Uuid],
+        synapse: T::Synapse,
+    ) -> Result<()> {
+        let synapses = vec![synapse; somas.len().saturating_sub(1)];
+
+        self.connect_chain_with(somas, &synapses)
+    }
+
+    /// connect each soma to the next one using the synapse for that link
+    ///
+    /// there must be exactly one synapse for each pair of consecutive somas.
+    pub fn connect_chain_with(
+        &self,
+        somas: &[Uuid],
+        synapses: &[T::Synapse],
+    ) -> Result<()> {
+        if synapses.len() != somas.len().saturating_sub(1) {
+            bail!(
+                "expected {} synapses for a chain of {} somas, got {}",
+                somas.len().saturating_sub(1),
+                somas.len(),
+                synapses.len()
+            )
+        }
+
+        for soma in somas {
+            if !self.somas.contains_key(soma) {
+                bail!("unable to find soma {} in chain", soma)
+            }
+        }
+
+        for (i, (link, synapse)) in
+            somas.windows(2).zip(synapses.iter()).enumerate()
+        {
+            self.connect(link[0], link[1], *synapse).chain_err(|| {
+                format!(
+                    "unable to connect link {} ({} -> {}) of chain",
+                    i, link[0], link[1]
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// forward each item of a stream to the entry soma as a signal
+    ///
+    /// the entry soma receives a dendrite for the given synapse, and each
+    /// item is handed to `forward` along with the matching terminal. call
+    /// this before the organelle is started so the dendrite arrives before
+    /// Start. if `stop` is set, the organelle stops when the stream ends and
+    /// fails when the stream or `forward` fails, otherwise the end of the
+    /// stream is ignored and any error is logged.
+    pub fn feed<S, F>(
+        &self,
+        entry: Uuid,
+        synapse: T::Synapse,
+        stream: S,
+        stop: bool,
+        forward: F,
+    ) -> Result<()>
+    where
+        S: Stream<Error = Error> + 'static,
+        F: Fn(&<T::Synapse as Synapse>::Terminal, S::Item)
+                -> Box<Future<Item = (), Error = Error>>
+            + 'static,
+    {
+        let (tx, rx) = synapse.synapse();
+
+        self.add_dendrite((Uuid::new_v4(), rx), entry, synapse)?;
+
+        let main_tx = self.main_tx.clone();
+
+        self.handle.spawn(
+            stream
+                .for_each(move |signal| forward(&tx, signal))
+                .then(move |result| {
+                    let imp = match result {
+                        Ok(()) => Impulse::Stop,
+                        Err(e) => Impulse::Error(e),
+                    };
+
+                    if stop {
+                        Box::new(
+                            main_tx.send(imp).map(|_| ()).map_err(|_| ()),
+                        )
+                            as Box<Future<Item = (), Error = ()>>
+                    } else {
+                        if let Impulse::Error(e) = imp {
+                            eprintln!("feed failed - {}", e);
+                        }
+
+                        Box::new(future::ok(()))
+                    }
+                }),
+        );
+
+        Ok(())
+    }
+
+    /// connect the host to a soma so it can be sent signals with send
+    ///
+    /// the soma receives a dendrite for the synapse from the host, which
+    /// counts against its constraints like any other connection, and the
+    /// organelle keeps the terminal for every send to that soma and synapse.
+    /// call this before the organelle is started, since somas only expect
+    /// dendrites before Start.
+    pub fn connect_host(&self, dest: Uuid, synapse: T::Synapse) -> Result<()> {
+        if self.started.is_some() {
+            bail!("unable to connect the host after the organelle has started")
+        }
+
+        if !self.somas.contains_key(&dest) {
+            bail!("unable to find soma {}", dest)
+        }
+
+        if self.host_terminals
+            .borrow()
+            .contains_key(&(dest, synapse))
+        {
+            bail!(ErrorKind::InvalidSynapse(format!(
+                "soma {} is already connected to the host with {:?}",
+                dest, synapse
+            )))
+        }
+
+        self.check_connection(self.host, dest, synapse)?;
+
+        let (tx, rx) = synapse.synapse();
+
+        self.add_dendrite((self.host, rx), dest, synapse)?;
+        self.host_terminals
+            .borrow_mut()
+            .insert((dest, synapse), tx);
+
+        Ok(())
+    }
+
+    /// send a single signal from outside the organelle to a soma
+    ///
+    /// signals only travel through synapses, so the soma must have been
+    /// connected to the host with connect_host first. `forward` sends the
+    /// signal through the host's terminal, and the returned future fails if
+    /// the soma isn't connected or `forward` fails. use feed to drive a
+    /// whole stream into a soma instead.
+    pub fn send<P, F>(
+        &self,
+        dest: Uuid,
+        synapse: T::Synapse,
+        signal: P,
+        forward: F,
+    ) -> Box<Future<Item = (), Error = Error>>
+    where
+        F: FnOnce(&<T::Synapse as Synapse>::Terminal, P)
+            -> Box<Future<Item = (), Error = Error>>,
+    {
+        let host_terminals = self.host_terminals.borrow();
+
+        if let Some(terminal) = host_terminals.get(&(dest, synapse)) {
+            return forward(terminal, signal);
+        }
+
+        let e = if self.somas.contains_key(&dest) {
+            Error::from(format!(
+                "soma {} is not connected to the host with {:?} - connect it \
+                 with Organelle::connect_host first",
+                dest, synapse
+            ))
+        } else {
+            Error::from(format!("unable to find soma {}", dest))
+        };
+
+        Box::new(future::err(e))
+    }
+
+    /// send a dendrite to the specified soma
+    pub fn add_dendrite(
+        &self,
+        dendrite: (Uuid, <T::Synapse as Synapse>::Dendrite),
+        terminal: Uuid,
+        synapse: T::Synapse,
+    ) -> Result<()> {
+        let terminal_sender = if let Some(sender) = self.mailbox(terminal) {
+            sender
+        } else {
+            bail!("unable to find terminal")
+        };
+
+        let main_tx = self.main_tx.clone();
+
+        self.handle.spawn(
+            terminal_sender
+                .send(Impulse::AddDendrite(dendrite.0, synapse, dendrite.1))
+                .map(|_| ())
+                .or_else(move |e| {
+                    let msg = format!("unable to add dendrite to {}", terminal);
+                    let e = Error::with_chain(e, ErrorKind::Msg(msg));
+
+                    main_tx.send(Impulse::Error(e)).map(|_| ()).map_err(|_| ())
+                }),
+        );
+
+        Ok(())
+    }
+
+    /// send a terminal to the specified soma
+    pub fn add_terminal(
+        &self,
+        terminal: (Uuid, <T::Synapse as Synapse>::Terminal),
+        dendrite: Uuid,
+        synapse: T::Synapse,
+    ) -> Result<()> {
+        let dendrite_sender = if let Some(sender) = self.mailbox(dendrite) {
+            sender
+        } else {
+            bail!("unable to find dendrite")
+        };
+
+        let main_tx = self.main_tx.clone();
+
+        self.handle.spawn(
+            dendrite_sender
+                .send(Impulse::AddTerminal(terminal.0, synapse, terminal.1))
+                .map(|_| ())
+                .or_else(move |e| {
+                    let msg = format!("unable to add terminal to {}", dendrite);
+                    let e = Error::with_chain(e, ErrorKind::Msg(msg));
+
+                    main_tx.send(Impulse::Error(e)).map(|_| ()).map_err(|_| ())
+                }),
+        );
+
+        Ok(())
+    }
+
+    /// hold back the impulses sent to a soma until it is resumed
+    ///
+    /// see Impulse::Pause. probes, Stop, StopDraining and Error still reach
+    /// the soma while it is paused.
+    pub fn pause(&self, uuid: Uuid) -> Result<()> {
+        self.send_control(uuid, Impulse::Pause)
+    }
+
+    /// handle the impulses held back since pause and continue as normal
+    pub fn resume(&self, uuid: Uuid) -> Result<()> {
+        self.send_control(uuid, Impulse::Resume)
+    }
+
+    fn send_control(
+        &self,
+        uuid: Uuid,
+        imp: Impulse<T::Synapse>,
+    ) -> Result<()> {
+        let sender = if let Some(sender) = self.somas.get(&uuid) {
+            sender.clone()
+        } else {
+            bail!("unable to find soma {}", uuid)
+        };
+
+        let kind = imp.kind();
+        let main_tx = self.main_tx.clone();
+
+        self.handle.spawn(sender.send(imp).map(|_| ()).or_else(move |e| {
+            let msg = format!("unable to send {:?} to soma {}", kind, uuid);
+            let e = Error::with_chain(e, ErrorKind::Msg(msg));
+
+            main_tx.send(Impulse::Error(e)).map(|_| ()).map_err(|_| ())
+        }));
+
+        Ok(())
+    }
+
+    fn start_all(
+        &self,
+        tx: mpsc::Sender<Impulse<T::Synapse>>,
+        handle: reactor::Handle,
+    ) -> Result<()> {
+        // highest priority first, see SomaSettings::start_priority
+        let mut groups = BTreeMap::new();
+
+        for (uuid, sender) in &self.somas {
+            let ctx = &self.contexts[uuid];
+
+            groups
+                .entry(Reverse(ctx.start_priority))
+                .or_insert_with(Vec::new)
+                .push((*uuid, sender.clone(), ctx.started_watcher.clone()));
+        }
+
+        // somas with the same priority are sent Start in order of their uuid
+        for group in groups.values_mut() {
+            group.sort_by_key(|&(uuid, _, _)| uuid);
+        }
+
+        if let Some(timeout) = self.config.start_timeout {
+            self.watch_start(timeout, &groups, tx.clone())?;
+        }
+
+        let error_tx = tx.clone();
+
+        self.handle.spawn(
+            stream::iter_ok::<_, Error>(groups.into_iter().map(|(_, g)| g))
+                .for_each(move |group| {
+                    let acks: Vec<_> = group
+                        .into_iter()
+                        .map(|(uuid, sender, started)| {
+                            sender
+                                .send(Impulse::Start(
+                                    uuid,
+                                    tx.clone(),
+                                    handle.clone(),
+                                ))
+                                .and_then(move |_| {
+                                    started.wait_for(|started| *started)
+                                })
+                                .map_err(move |e| {
+                                    Error::with_chain(
+                                        e,
+                                        ErrorKind::Msg(format!(
+                                            "unable to start soma {}",
+                                            uuid
+                                        )),
+                                    )
+                                })
+                        })
+                        .collect();
+
+                    future::join_all(acks).map(|_| ())
+                })
+                .or_else(move |e| {
+                    error_tx
+                        .send(Impulse::Error(e))
+                        .map(|_| ())
+                        .map_err(|_| ())
+                }),
+        );
+
+        Ok(())
+    }
+
+    /// report the somas that have not started once the timeout elapses
+    fn watch_start(
+        &self,
+        timeout: Duration,
+        groups: &BTreeMap<Reverse<i32>, Vec<StartEntry<T::Synapse>>>,
+        tx: mpsc::Sender<Impulse<T::Synapse>>,
+    ) -> Result<()> {
+        let watchers: Vec<_> = groups
+            .values()
+            .flat_map(|group| group.iter())
+            .map(|&(uuid, _, ref started)| (uuid, started.clone()))
+            .collect();
+
+        self.handle.spawn(
+            reactor::Timeout::new(timeout, &self.handle)?
+                .map_err(Error::from)
+                .and_then(move |_| {
+                    let stragglers: Vec<_> = watchers
+                        .into_iter()
+                        .filter(|&(_, ref started)| !started.get())
+                        .map(|(uuid, _)| uuid)
+                        .collect();
+
+                    if stragglers.is_empty() {
+                        Ok(())
+                    } else {
+                        Err(ErrorKind::StartTimeout(stragglers).into())
+                    }
+                })
+                .or_else(move |e| {
+                    tx.send(Impulse::Error(e)).map(|_| ()).map_err(|_| ())
+                }),
+        );
+
+        Ok(())
+    }
+
+    /// stop every soma with StopDraining, upstream somas first
+    ///
+    /// a soma is only sent StopDraining once every soma connected to its
+    /// dendrites has stopped and dropped its terminals, so nothing more can
+    /// arrive on its synapses and it can wait for its readers to finish in
+    /// Soma::on_stop. the host's terminals are dropped up front. somas in or
+    /// downstream of a cycle are stopped together once nothing else is left.
+    fn drain_somas(&self) -> Box<Future<Item = (), Error = Error>> {
+        self.host_terminals.borrow_mut().clear();
+
+        // connections into nested organelles hold up the organelle as a whole
+        let edges: Vec<_> = self.connections
+            .borrow()
+            .values()
+            .filter_map(|c| {
+                let ends =
+                    (self.outermost(c.dendrite), self.outermost(c.terminal));
+
+                match ends {
+                    (Some(d), Some(t)) if d != t => Some((d, t)),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        let mut upstream: HashMap<Uuid, usize> =
+            self.somas.keys().map(|uuid| (*uuid, 0)).collect();
+
+        for &(_, terminal) in &edges {
+            if let Some(count) = upstream.get_mut(&terminal) {
+                *count += 1;
+            }
+        }
+
+        let mut waves = vec![];
+
+        while !upstream.is_empty() {
+            let mut wave: Vec<_> = upstream
+                .iter()
+                .filter(|&(_, &count)| count == 0)
+                .map(|(uuid, _)| *uuid)
+                .collect();
+
+            if wave.is_empty() {
+                wave = upstream.keys().cloned().collect();
+            }
+
+            wave.sort();
+
+            for uuid in &wave {
+                upstream.remove(uuid);
+            }
+
+            for &(dendrite, terminal) in &edges {
+                if wave.contains(&dendrite) {
+                    if let Some(count) = upstream.get_mut(&terminal) {
+                        *count -= 1;
+                    }
+                }
+            }
+
+            let wave: Vec<StartEntry<T::Synapse>> = wave.into_iter()
+                .map(|uuid| {
+                    (
+                        uuid,
+                        self.somas[&uuid].clone(),
+                        self.contexts[&uuid].stopped_watcher.clone(),
+                    )
+                })
+                .collect();
+
+            waves.push(wave);
+        }
+
+        Box::new(stream::iter_ok::<_, Error>(waves).for_each(|wave| {
+            let stops: Vec<_> = wave.into_iter()
+                .map(|(_, mailbox, stopped)| {
+                    // a soma that is already gone has nothing left to drain
+                    mailbox
+                        .send(Impulse::StopDraining)
+                        .then(move |_| stopped.wait_for(|stopped| *stopped))
+                        .then(|_| Ok::<_, Error>(()))
+                })
+                .collect();
+
+            future::join_all(stops).map(|_| ())
+        }))
+    }
+
+    /// send an impulse to every soma in the organelle, like start_all
+    fn broadcast<F>(&self, imp: F) -> Box<Future<Item = (), Error = Error>>
+    where
+        F: Fn() -> Impulse<T::Synapse>,
+    {
+        let sends: Vec<_> = self.somas
+            .iter()
+            .map(|(uuid, mailbox)| {
+                let uuid = *uuid;
+
+                mailbox.clone().send(imp()).map_err(move |e| {
+                    Error::with_chain(
+                        e,
+                        ErrorKind::Msg(format!(
+                            "unable to send impulse to soma {}",
+                            uuid
+                        )),
+                    )
+                })
+            })
+            .collect();
+
+        Box::new(future::join_all(sends).map(|_| ()))
+    }
+
+    /// find the metadata given to connect_with for each connection
+    ///
+    /// this is a snapshot, so the axons answering a probe see the
+    /// connections as they were when the probe started.
+    fn connection_lookup(&self) -> probe::ConnectionLookup<T::Synapse> {
+        let meta = self.connection_meta.borrow();
+        let connections: Vec<_> = self.connections
+            .borrow()
+            .values()
+            .filter_map(|c| {
+                meta.get(&c.uuid)
+                    .map(|m| (c.dendrite, c.terminal, c.synapse, m.clone()))
+            })
+            .collect();
+
+        Rc::new(
+            move |dendrite: Uuid, terminal: Uuid, synapse: T::Synapse| {
+                connections
+                    .iter()
+                    .find(|&&(d, t, s, _)| {
+                        d == dendrite && t == terminal && s == synapse
+                    })
+                    .map(|&(_, _, _, ref meta)| meta.clone())
+            },
+        )
+    }
+
+    fn annotate_metrics(&self, soma: Uuid, data: &mut SomaData) {
+        let metrics = match data {
+            &mut SomaData::Axon {
+                ref mut metrics,
+                ..
+            }
+            | &mut SomaData::Soma {
+                ref mut metrics,
+                ..
+            } => metrics,
+            _ => return,
+        };
+
+        if let Some(ctx) = self.contexts.get(&soma) {
+            metrics.impulses = Some(ctx.impulses.get());
+            metrics.last_update = ctx.last_update.get().map(|t| t.elapsed());
+        }
+        if let Some(mailbox) = self.somas.get(&soma) {
+            metrics.queued = Some(mailbox.queued());
+        }
+    }
+
+    #[async]
+    fn perform_probe(
+        self,
+        settings: probe::Settings,
+        tx: oneshot::Sender<SomaData>,
+    ) -> Result<Self> {
+        let (organelle, data) = await!(self.probe(settings))?;
+
+        if let Err(_) = tx.send(data) {
+            // rx does not care anymore
+        }
+
+        Ok(organelle)
+    }
+}
+
+impl<T: Soma + 'static> Soma for Organelle<T> {
+    type Synapse = T::Synapse;
+    type Error = Error;
+
+    /// the name given to the organelle, or else the name of its nucleus
+    fn name(&self) -> Cow<str> {
+        match self.name {
+            Some(ref name) => Cow::Borrowed(name),
+            None => Cow::Borrowed(&self.nucleus_name),
+        }
+    }
+
+    fn constraints(&self) -> Option<Constraints<Self::Synapse>> {
+        self.constraints.get(&self.nucleus()).cloned()
+    }
+
+    /// organelles pass Pause and Resume on to their somas
+    fn handles_pause(&self) -> bool {
+        true
+    }
+
+    /// organelles pass RemoveDendrite and RemoveTerminal on to their nucleus,
+    /// whose own event loop decides whether it sees them
+    fn handles_removal(&self) -> bool {
+        true
+    }
+
+    #[async(boxed)]
+    fn probe(self, settings: probe::Settings) -> Result<(Self, SomaData)> {
+        let stable_order = settings.reports_stable_order();
+        let settings = settings.connections(self.connection_lookup());
+        let handle = self.handle.clone();
+
+        let results = await!(
+            stream::iter_ok(self.somas.clone())
+                .map(move |(uuid, sender)| {
+                    probe_soma::<T>(uuid, sender, settings.clone(), &handle)
+                })
+                .collect()
+                .and_then(|receivers| future::join_all(receivers))
+        )?;
+
+        let nucleus_uuid = self.nucleus();
+        let mut nucleus = None;
+
+        let somas = results
+            .into_iter()
+            .filter_map(|(uuid, mut data)| {
+                self.annotate_metrics(uuid, &mut data);
+
+                if uuid == nucleus_uuid {
+                    nucleus = Some(data);
+                    None
+                } else {
+                    Some(data)
+                }
+            })
+            .collect();
+
+        let uuid = self.uuid.unwrap();
+
+        let dropped = self.somas
+            .iter()
+            .map(|(uuid, mailbox)| (*uuid, mailbox.dropped()))
+            .filter(|&(_, dropped)| dropped > 0)
+            .collect();
+
+        let edges = self.connections
+            .borrow()
+            .values()
+            .map(|c| EdgeData {
+                from: c.dendrite,
+                to: c.terminal,
+                role: format!("{:?}", c.synapse),
+            })
+            .collect();
+
+        let mut data = SomaData::Organelle {
+            nucleus: Box::new(nucleus.unwrap()),
+            somas: somas,
+            uuid: uuid,
+            name: self.name().into_owned(),
+            dropped: dropped,
+            edges: edges,
+        };
+
+        if stable_order {
+            data.sort();
+        }
+
+        Ok((self, data))
+    }
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<T::Synapse>) -> Result<Self> {
+        self.reap_finished()?;
+
+        match imp {
+            Impulse::AddDendrite(_, _, _)
+            | Impulse::AddTerminal(_, _, _)
+            | Impulse::RemoveDendrite(_, _)
+            | Impulse::RemoveTerminal(_, _) => {
+                await!(
+                    self.somas
+                        .get(&self.nucleus())
+                        .unwrap()
+                        .clone()
+                        .send(imp)
+                        .map_err(|_| Error::from("unable to forward impulse"))
+                )?;
+                Ok(self)
+            },
+            Impulse::Start(uuid, tx, handle) => {
+                self.uuid = Some(uuid);
+
+                let rx = mem::replace(&mut self.main_rx, None).unwrap();
+
+                handle.spawn(
+                    tx.clone()
+                        .send_all(rx.map_err(|_| unreachable!()))
+                        .map(|_| ())
+                        .map_err(|_| ()),
+                );
+
+                self.started = Some((tx.clone(), handle.clone()));
+
+                self.connect_deferred()?;
+                self.check_variadic_peers()?;
+                self.start_all(tx, handle)?;
+
+                Ok(self)
+            },
+
+            Impulse::Probe(settings, tx) => {
+                if let Some(changes) = settings.change_listener() {
+                    self.notify_changes(changes);
+                }
+
+                await!(self.perform_probe(settings, tx))
+            },
+
+            Impulse::Pause => {
+                await!(self.broadcast(|| Impulse::Pause))?;
+                Ok(self)
+            },
+            Impulse::Resume => {
+                await!(self.broadcast(|| Impulse::Resume))?;
+                Ok(self)
+            },
+
+            Impulse::Stop | Impulse::StopDraining | Impulse::Error(_) => {
+                unreachable!()
+            },
+        }
+    }
+
+    /// convert this soma into a future that can be passed to an event loop
+    #[async(boxed)]
+    fn run(mut self, handle: reactor::Handle) -> Result<()>
+    where
+        Self: 'static,
+    {
+        let (tx, mut rx) = mpsc::channel(self.run_buffer());
+
+        let uuid = Uuid::new_v4();
+
+        await!(
+            tx.clone()
+                .send(Impulse::Start(uuid, tx, handle))
+                .map_err(|_| Error::from("unable to send start signal"))
+        )?;
+
+        loop {
+            let (imp, next) = await!(rx.into_future())
+                .map_err(|_| -> Error { unreachable!() })?;
+            rx = next;
+
+            match imp {
+                Some(Impulse::Error(e)) => bail!(e),
+                Some(Impulse::Stop) | None => break,
+                // let the somas flush their synapses, then refuse new
+                // impulses, but handle the ones already queued
+                Some(Impulse::StopDraining) => {
+                    let drain = self.drain_somas();
+                    await!(drain)?;
+
+                    rx.close();
+                },
+
+                Some(imp) => {
+                    self = await!(self.update(imp))
+                        .map_err(|e| -> Error { e.into() })?
+                },
+            }
+        }
+
+        await!(self.on_stop())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use axon::Axon;
+    use soma::recording;
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    enum TestSynapse {
+        Nothing,
+    }
+
+    impl Synapse for TestSynapse {
+        type Terminal = ();
+        type Dendrite = ();
+
+        fn synapse(self) -> ((), ()) {
+            ((), ())
+        }
+    }
+
+    struct FailSoma;
+
+    impl Soma for FailSoma {
+        type Synapse = TestSynapse;
+        type Error = Error;
+
+        #[async(boxed)]
+        fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+            match imp {
+                Impulse::Start(_, _, _) => bail!("failed to start"),
+                _ => Ok(self),
+            }
+        }
+    }
+
+    #[test]
+    fn test_relays_end_with_soma() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(FailSoma, handle.clone());
+        let (tx, _rx) = mpsc::channel(1);
+
+        let uuids: Vec<_> =
+            (0..100).map(|_| organelle.add_soma(FailSoma)).collect();
+
+        for uuid in &uuids {
+            core.run(organelle.somas[uuid].clone().send(Impulse::Start(
+                *uuid,
+                tx.clone(),
+                handle.clone(),
+            ))).unwrap();
+        }
+
+        // let every soma fail and its relay wind down
+        core.run(reactor::Timeout::new(Duration::from_millis(50), &handle)
+            .unwrap())
+            .unwrap();
+
+        for uuid in &uuids {
+            assert!(
+                core.run(organelle.somas[uuid].clone().send(Impulse::Stop))
+                    .is_err(),
+                "relay for soma {} is still running",
+                uuid
+            );
+        }
+    }
+
+    #[test]
+    fn test_dead_relay_reports_error() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(IdleSoma, handle.clone());
+
+        // a soma that went away without closing its channel
+        let dead = Uuid::new_v4();
+        let (soma_rx, _closed_tx) = organelle
+            .create_soma_channel::<TestSynapse>(dead, OverflowPolicy::Block);
+        drop(soma_rx);
+
+        // a soma that finished on purpose
+        let done = Uuid::new_v4();
+        let (soma_rx, closed_tx) = organelle
+            .create_soma_channel::<TestSynapse>(done, OverflowPolicy::Block);
+        drop(closed_tx);
+        drop(soma_rx);
+
+        for uuid in &[dead, done] {
+            handle.spawn(
+                organelle.somas[uuid]
+                    .clone()
+                    .send(Impulse::Stop)
+                    .map(|_| ())
+                    .map_err(|_| ()),
+            );
+        }
+
+        core.run(reactor::Timeout::new(Duration::from_millis(20), &handle)
+            .unwrap())
+            .unwrap();
+
+        let mut main_rx = organelle.main_rx.take().unwrap();
+        main_rx.close();
+        let imps = core.run(main_rx.collect()).unwrap();
+
+        assert_eq!(imps.len(), 1);
+        match imps[0] {
+            Impulse::Error(ref e) => assert_eq!(
+                e.to_string(),
+                format!(
+                    "relay for soma {} stopped, the soma is no longer \
+                     receiving impulses",
+                    dead
+                )
+            ),
+            _ => panic!("expected an error from the dead relay"),
+        }
+    }
+
+    #[test]
+    fn test_disconnect() {
+        let core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(FailSoma, handle);
+
+        let a = organelle.add_soma(FailSoma);
+        let b = organelle.add_soma(FailSoma);
+
+        let connection = organelle.connect(a, b, TestSynapse::Nothing).unwrap();
+
+        // the connection only exists in one direction
+        assert!(organelle.disconnect(b, a, TestSynapse::Nothing).is_err());
+        assert!(
+            organelle
+                .disconnect(a, Uuid::new_v4(), TestSynapse::Nothing)
+                .is_err()
+        );
+
+        organelle.disconnect(a, b, TestSynapse::Nothing).unwrap();
+
+        assert_eq!(organelle.connection(connection.uuid), None);
+        assert!(organelle.disconnect(a, b, TestSynapse::Nothing).is_err());
+    }
+
+    #[test]
+    fn test_connection_descriptor() {
+        let core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(FailSoma, handle);
+
+        let a = organelle.add_soma(FailSoma);
+        let b = organelle.add_soma(FailSoma);
+
+        let first = organelle.connect(a, b, TestSynapse::Nothing).unwrap();
+        let second = organelle.connect(b, a, TestSynapse::Nothing).unwrap();
+
+        assert_eq!(first.dendrite, a);
+        assert_eq!(first.terminal, b);
+        assert_eq!(first.synapse, TestSynapse::Nothing);
+
+        // every edge gets its own id, even between the same somas
+        assert_ne!(first.uuid, second.uuid);
+        assert_eq!(organelle.connection(first.uuid), Some(first));
+        assert_eq!(organelle.connection(second.uuid), Some(second));
+        assert_eq!(organelle.connection(Uuid::new_v4()), None);
+    }
+
+    /// sends 1 through its terminal on Start and drops it when it's removed
+    struct ValueSource {
+        tx: Option<mpsc::Sender<u32>>,
+    }
+
+    impl Soma for ValueSource {
+        type Synapse = ValueSynapse;
+        type Error = Error;
+
+        fn handles_removal(&self) -> bool {
+            true
+        }
+
+        #[async(boxed)]
+        fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+            match imp {
+                Impulse::AddTerminal(_, ValueSynapse::Value, tx) => {
+                    self.tx = Some(tx);
+                },
+                Impulse::Start(_, _, _) => {
+                    let tx = self.tx.take().unwrap();
+
+                    self.tx = Some(await!(
+                        tx.send(1).map_err(|_| Error::from("sink dropped"))
+                    )?);
+                },
+                Impulse::RemoveTerminal(_, ValueSynapse::Value) => {
+                    self.tx = None;
+                },
+                imp => return Err(imp.unexpected(&self)),
+            }
+
+            Ok(self)
+        }
+    }
+
+    #[test]
+    fn test_disconnect_closes_synapse() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let values = Rc::new(RefCell::new(vec![]));
+        let mut organelle = Organelle::new(
+            ValueSource { tx: None },
+            handle.clone(),
+        );
+
+        let source = organelle.nucleus();
+        let sink = organelle.add_soma(ValueSink::new(&values));
+
+        organelle.connect(source, sink, ValueSynapse::Value).unwrap();
+
+        let (tx, _rx) = mpsc::channel(1);
+
+        for &uuid in &[sink, source] {
+            core.run(organelle.somas[&uuid].clone().send(Impulse::Start(
+                uuid,
+                tx.clone(),
+                handle.clone(),
+            ))).unwrap();
+        }
+
+        let settle = |core: &mut reactor::Core| {
+            core.run(reactor::Timeout::new(Duration::from_millis(20), &handle)
+                .unwrap())
+                .unwrap();
+        };
+
+        settle(&mut core);
+
+        // the value is delivered while the synapse is up
+        assert_eq!(*values.borrow(), vec![Some(1)]);
+
+        organelle.disconnect(source, sink, ValueSynapse::Value).unwrap();
+        settle(&mut core);
+
+        // the source dropped its terminal, so the sink's reader completed,
+        // and the sink, which doesn't handle removal, is still running
+        assert_eq!(*values.borrow(), vec![Some(1), None]);
+
+        let mut main_rx = organelle.main_rx.take().unwrap();
+        main_rx.close();
+        let (imp, _) = core.run(main_rx.into_future()).ok().unwrap();
+        assert!(imp.is_none());
+    }
+
+    /// reads its values slowly and waits for its reader to finish on stop
+    struct SlowSink {
+        values: Rc<RefCell<Vec<Option<u32>>>>,
+        dendrite: Option<mpsc::Receiver<u32>>,
+        reader: Option<oneshot::Receiver<()>>,
+    }
+
+    impl Soma for SlowSink {
+        type Synapse = ValueSynapse;
+        type Error = Error;
+
+        #[async(boxed)]
+        fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+            match imp {
+                Impulse::AddDendrite(_, ValueSynapse::Value, rx) => {
+                    self.dendrite = Some(rx);
+                },
+                Impulse::Start(_, tx, handle) => {
+                    let (done_tx, done_rx) = oneshot::channel();
+                    let values = Rc::clone(&self.values);
+                    let timer = handle.clone();
+
+                    handle.spawn(
+                        self.dendrite
+                            .take()
+                            .unwrap()
+                            .for_each(move |value| {
+                                let values = Rc::clone(&values);
+
+                                reactor::Timeout::new(
+                                    Duration::from_millis(5),
+                                    &timer,
+                                ).unwrap()
+                                    .then(move |_| {
+                                        values.borrow_mut().push(Some(value));
+                                        Ok::<_, ()>(())
+                                    })
+                            })
+                            .then(move |_| done_tx.send(())),
+                    );
+
+                    self.reader = Some(done_rx);
+
+                    // stop while the value is still in flight
+                    await!(
+                        tx.send(Impulse::StopDraining)
+                            .map_err(|_| Error::from("unable to drain"))
+                    )?;
+                },
+                imp => return Err(imp.unexpected(&self)),
+            }
+
+            Ok(self)
+        }
+
+        #[async(boxed)]
+        fn on_stop(self) -> Result<()> {
+            if let Some(reader) = self.reader {
+                await!(
+                    reader.map_err(|_| Error::from("reader was dropped"))
+                )?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_stop_draining_synapses() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let values = Rc::new(RefCell::new(vec![]));
+        let mut organelle = Organelle::new(
+            SlowSink {
+                values: Rc::clone(&values),
+                dendrite: None,
+                reader: None,
+            },
+            handle.clone(),
+        );
+
+        // the source has sent its value before the sink is started
+        let source = organelle.add_soma_with_settings(
+            ValueSource { tx: None },
+            SomaSettings::default().start_priority(1),
+        );
+        let sink = organelle.nucleus();
+
+        organelle.connect(source, sink, ValueSynapse::Value).unwrap();
+
+        let timeout =
+            reactor::Timeout::new(Duration::from_secs(1), &handle).unwrap();
+
+        let result = core.run(
+            organelle
+                .run(handle.clone())
+                .select2(timeout.map_err(|e| -> Error { e.into() })),
+        );
+
+        match result {
+            Ok(Either::A(_)) => (),
+            Ok(Either::B(_)) => panic!("organelle never finished draining"),
+            Err(Either::A((e, _))) | Err(Either::B((e, _))) => {
+                panic!("organelle failed: {:#?}", e)
+            },
+        }
+
+        // the sink was only stopped once the source had dropped its end of
+        // the synapse, so the value still in flight was read
+        assert_eq!(*values.borrow(), vec![Some(1)]);
+    }
+
+    #[test]
+    fn test_remove_soma() {
+        let core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(FailSoma, handle);
+
+        let a = organelle.add_soma(FailSoma);
+        let b = organelle.add_soma(FailSoma);
+
+        organelle.connect(a, b, TestSynapse::Nothing).unwrap();
+
+        let nucleus = organelle.nucleus();
+        assert!(organelle.remove_soma(nucleus).is_err());
+
+        assert!(!organelle.is_empty());
+
+        organelle.remove_soma(a).unwrap();
+        assert_eq!(organelle.soma_count(), 2);
+
+        organelle.remove_soma(b).unwrap();
+        assert_eq!(organelle.soma_count(), 1);
+        assert!(organelle.is_empty());
+        assert!(organelle.remove_soma(a).is_err());
+        assert!(organelle.disconnect(a, b, TestSynapse::Nothing).is_err());
+    }
+
+    #[test]
+    fn test_topology() {
+        let core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(FailSoma, handle);
+
+        let nucleus = organelle.nucleus();
+        let a = organelle.add_soma(FailSoma);
+        let b = organelle.add_soma(FailSoma);
+
+        organelle.connect(nucleus, a, TestSynapse::Nothing).unwrap();
+        organelle.connect(nucleus, b, TestSynapse::Nothing).unwrap();
+        organelle.connect(a, b, TestSynapse::Nothing).unwrap();
+
+        let mut expected = vec![
+            (nucleus, vec![a, b]),
+            (a, vec![b]),
+            (b, vec![]),
+        ];
+        for &mut (_, ref mut targets) in &mut expected {
+            targets.sort();
+        }
+        expected.sort();
+
+        assert_eq!(organelle.topology(), expected);
+
+        organelle.disconnect(a, b, TestSynapse::Nothing).unwrap();
+
+        assert!(
+            organelle
+                .topology()
+                .iter()
+                .all(|&(uuid, ref targets)| uuid != a || targets.is_empty())
+        );
+    }
+
+    #[test]
+    fn test_connect_with() {
+        let core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(FailSoma, handle);
+
+        let a = organelle.add_soma(FailSoma);
+        let b = organelle.add_soma(FailSoma);
+
+        let meta = ConnectionMeta::new().label("feedback").weight(3);
+
+        organelle
+            .connect_with(a, b, TestSynapse::Nothing, meta.clone())
+            .unwrap();
+
+        let settings =
+            probe::Settings::new().connections(organelle.connection_lookup());
+
+        // the synapse is compared by value, and the direction matters
+        assert_eq!(
+            settings.connection_meta(a, b, TestSynapse::Nothing),
+            Some(meta)
+        );
+        assert_eq!(settings.connection_meta(b, a, TestSynapse::Nothing), None);
+
+        // synapses of another type never match
+        assert_eq!(settings.connection_meta(a, b, probe::Synapse::Probe), None);
+
+        organelle.disconnect(a, b, TestSynapse::Nothing).unwrap();
+
+        assert!(organelle.connection_meta.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_connect_checks_constraints() {
+        let core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(FailSoma, handle);
+
+        let sender = organelle.add_soma(Axon::new(
+            FailSoma,
+            vec![],
+            vec![Constraint::One(TestSynapse::Nothing)],
+        ));
+        let a = organelle.add_soma(FailSoma);
+        let b = organelle.add_soma(FailSoma);
+
+        organelle.connect(sender, a, TestSynapse::Nothing).unwrap();
+
+        let e = organelle
+            .connect(sender, b, TestSynapse::Nothing)
+            .unwrap_err();
+
+        assert_eq!(
+            e.to_string(),
+            format!(
+                "invalid synapse given to somas - terminal {} already has a \
+                 One(Nothing) connection",
+                sender
+            )
+        );
+
+        // the receiving end has no dendrite constraints at all
+        assert!(organelle.connect(a, sender, TestSynapse::Nothing).is_err());
+    }
+
+    fn source() -> Axon<IdleSoma> {
+        Axon::new(IdleSoma, vec![], vec![Constraint::One(TestSynapse::Nothing)])
+    }
+
+    #[test]
+    fn test_variadic_peers() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(
+            Axon::new(
+                IdleSoma,
+                vec![Constraint::Variadic(TestSynapse::Nothing)],
+                vec![],
+            ),
+            handle.clone(),
+        );
+
+        let sink = organelle.nucleus();
+        let a = organelle.add_soma(source());
+        let b = organelle.add_soma(source());
+        let stranger = organelle.add_soma(Axon::new(IdleSoma, vec![], vec![]));
+
+        organelle.connect(a, sink, TestSynapse::Nothing).unwrap();
+        organelle.connect(b, sink, TestSynapse::Nothing).unwrap();
+
+        // the offending peer is named when it is connected
+        let e = organelle
+            .connect(stranger, sink, TestSynapse::Nothing)
+            .unwrap_err();
+
+        assert!(
+            e.to_string().contains(&format!(
+                "peer {} has no terminal for Nothing, but it is connected to \
+                 the variadic dendrite of soma {}",
+                stranger, sink
+            )),
+            "unexpected error: {}",
+            e
+        );
+
+        // and when it is started, in case it was swapped in afterwards
+        organelle
+            .replace_soma(b, Axon::new(IdleSoma, vec![], vec![]))
+            .unwrap();
+
+        let e = core.run(organelle.run(handle)).unwrap_err();
+
+        assert!(
+            e.to_string().contains(&format!("peer {} has no terminal", b)),
+            "unexpected error: {}",
+            e
+        );
+    }
+
+    #[test]
+    fn test_builder() {
+        let core = reactor::Core::new().unwrap();
+
+        assert!(
+            Organelle::<FailSoma>::builder(core.handle())
+                .build()
+                .is_err()
+        );
+
+        let organelle = Organelle::builder(core.handle())
+            .nucleus(FailSoma)
+            .name("named".to_string())
+            .config(OrganelleConfig::default().max_somas(1))
+            .build()
+            .unwrap();
+
+        assert_eq!(organelle.soma_count(), 1);
+        assert_eq!(organelle.name, Some("named".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "max_somas must be at least 1")]
+    fn test_max_somas_zero() {
+        OrganelleConfig::default().max_somas(0);
+    }
+
+    #[test]
+    fn test_max_somas() {
+        let core = reactor::Core::new().unwrap();
+
+        let mut organelle = Organelle::builder(core.handle())
+            .nucleus(FailSoma)
+            .config(OrganelleConfig::default().max_somas(2))
+            .build()
+            .unwrap();
+
+        assert!(organelle.try_add_soma(FailSoma).is_ok());
+
+        match organelle.try_add_soma(FailSoma) {
+            Err(Error(ErrorKind::TooManySomas(2), _)) => (),
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(uuid) => panic!("soma {} exceeded max_somas", uuid),
+        }
+
+        assert_eq!(organelle.soma_count(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "try_add_soma_with_settings")]
+    fn test_add_soma_over_max() {
+        let core = reactor::Core::new().unwrap();
+
+        let mut organelle = Organelle::builder(core.handle())
+            .nucleus(FailSoma)
+            .config(OrganelleConfig::default().max_somas(1))
+            .build()
+            .unwrap();
+
+        organelle.add_soma(FailSoma);
+    }
+
+    #[test]
+    fn test_names() {
+        let core = reactor::Core::new().unwrap();
+
+        let axon: Axon<FailSoma> = Axon::new(FailSoma, vec![], vec![]);
+        assert_eq!(axon.name(), FailSoma.name());
+
+        let organelle = Organelle::new(axon, core.handle());
+        assert_eq!(organelle.name(), FailSoma.name());
+
+        let organelle = Organelle::builder(core.handle())
+            .nucleus(FailSoma)
+            .name("named".to_string())
+            .build()
+            .unwrap();
+        assert_eq!(organelle.name(), "named");
+    }
+
+    #[test]
+    fn test_typed_handles() {
+        let core = reactor::Core::new().unwrap();
+
+        let mut organelle = Organelle::new(FailSoma, core.handle());
+
+        let a: SomaHandle<FailSoma> = organelle.add_typed_soma(FailSoma);
+        let b = organelle.add_soma(FailSoma);
+
+        let connection = organelle.connect(a, b, TestSynapse::Nothing).unwrap();
+
+        assert_eq!(connection.dendrite, a.uuid());
+        assert_eq!(Uuid::from(a), a.uuid());
+
+        organelle.disconnect(a, b, TestSynapse::Nothing).unwrap();
+    }
+
+    #[test]
+    fn test_connect_all() {
+        let core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(FailSoma, handle);
+
+        let hub = organelle.add_soma(Axon::new(
+            FailSoma,
+            vec![],
+            vec![Constraint::One(TestSynapse::Nothing)],
+        ));
+        let leaves: Vec<_> =
+            (0..3).map(|_| organelle.add_soma(FailSoma)).collect();
+
+        // the hub only accepts one connection, so nothing is wired
+        assert!(
+            organelle
+                .connect_all(hub, &leaves, TestSynapse::Nothing)
+                .is_err()
+        );
+        assert!(organelle.topology().iter().all(|&(_, ref t)| t.is_empty()));
+
+        let nucleus = organelle.nucleus();
+        organelle
+            .connect_all(nucleus, &leaves, TestSynapse::Nothing)
+            .unwrap();
+
+        let mut expected = leaves.clone();
+        expected.sort();
+
+        assert!(organelle.topology().contains(&(nucleus, expected)));
+    }
+
+    #[test]
+    fn test_reject_cycles() {
+        let core = reactor::Core::new().unwrap();
+
+        let organelle = |reject| {
+            let mut organelle = Organelle::builder(core.handle())
+                .nucleus(FailSoma)
+                .reject_cycles(reject)
+                .build()
+                .unwrap();
+
+            let somas: Vec<_> = (0..3)
+                .map(|_| {
+                    organelle.add_soma(Axon::new(
+                        FailSoma,
+                        vec![Constraint::One(TestSynapse::Nothing)],
+                        vec![Constraint::One(TestSynapse::Nothing)],
+                    ))
+                })
+                .collect();
+
+            organelle
+                .connect(somas[0], somas[1], TestSynapse::Nothing)
+                .unwrap();
+            organelle
+                .connect(somas[1], somas[2], TestSynapse::Nothing)
+                .unwrap();
+
+            (organelle, somas)
+        };
+
+        let (rejecting, somas) = organelle(true);
+        let e = rejecting
+            .connect(somas[2], somas[0], TestSynapse::Nothing)
+            .unwrap_err();
+
+        assert_eq!(
+            e.to_string(),
+            format!(
+                "connecting {}->{} would create a cycle through {}->{}->{}",
+                somas[2], somas[0], somas[0], somas[1], somas[2]
+            )
+        );
+
+        let (permissive, somas) = organelle(false);
+        permissive
+            .connect(somas[2], somas[0], TestSynapse::Nothing)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_buffer_sizes() {
+        let mut core = reactor::Core::new().unwrap();
+
+        // count how many impulses the main channel takes before it is full
+        let mut capacity = |config: OrganelleConfig| {
+            let organelle = Organelle::builder(core.handle())
+                .nucleus(IdleSoma)
+                .config(config)
+                .build()
+                .unwrap();
+            let mut tx = organelle.main_tx.clone();
+
+            core.run(future::lazy(move || {
+                let mut accepted = 0;
+
+                while let AsyncSink::Ready =
+                    tx.start_send(Impulse::Stop).map_err(|_| ())?
+                {
+                    accepted += 1;
+
+                    if accepted > 1000 {
+                        break;
+                    }
+                }
+
+                // the organelle holds on to the receiving end until here
+                drop(organelle);
+
+                Ok::<_, ()>(accepted)
+            })).unwrap()
+        };
+
+        assert_eq!(capacity(OrganelleConfig::default()), 100);
+        assert_eq!(
+            capacity(OrganelleConfig::default().buffer_sizes(3, 10, 1)),
+            3
+        );
+    }
+
+    #[test]
+    fn test_connect_chain() {
+        let core = reactor::Core::new().unwrap();
+
+        let mut organelle = Organelle::new(FailSoma, core.handle());
+        let somas: Vec<_> = (0..6)
+            .map(|_| {
+                organelle.add_soma(Axon::new(
+                    FailSoma,
+                    vec![Constraint::One(TestSynapse::Nothing)],
+                    vec![Constraint::One(TestSynapse::Nothing)],
+                ))
+            })
+            .collect();
+
+        let targets = |soma| {
+            organelle
+                .topology()
+                .into_iter()
+                .find(|&(uuid, _)| uuid == soma)
+                .map(|(_, targets)| targets)
+                .unwrap()
+        };
+
+        organelle
+            .connect_chain(&somas[0..3], TestSynapse::Nothing)
+            .unwrap();
+
+        assert_eq!(targets(somas[0]), vec![somas[1]]);
+        assert_eq!(targets(somas[1]), vec![somas[2]]);
+        assert_eq!(targets(somas[2]), vec![]);
+
+        // somas[1] already has its one dendrite, so the second link fails
+        let chain = [somas[3], somas[4], somas[1]];
+        let e = organelle
+            .connect_chain(&chain, TestSynapse::Nothing)
+            .unwrap_err();
+
+        assert!(
+            e.to_string().contains("link 1"),
+            "unexpected error message: {}",
+            e
+        );
+        assert_eq!(targets(somas[3]), vec![somas[4]]);
+
+        // nothing is connected if any of the somas are missing
+        assert!(
+            organelle
+                .connect_chain(
+                    &[somas[5], somas[4], Uuid::new_v4()],
+                    TestSynapse::Nothing
+                )
+                .is_err()
+        );
+        assert_eq!(targets(somas[5]), vec![]);
+
+        assert!(
+            organelle
+                .connect_chain_with(&somas[4..6], &[])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_spawn_on_reports_error() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let organelle = Organelle::new(FailSoma, handle.clone());
+
+        let e = core.run(organelle.spawn_on(handle)).unwrap_err();
+
+        assert!(
+            e.to_string().contains("FailSoma"),
+            "unexpected error message: {}",
+            e
+        );
+        assert_eq!(
+            e.iter().nth(1).map(|cause| cause.to_string()),
+            Some("failed to start".to_string())
+        );
+    }
+
+    struct IdleSoma;
+
+    impl Soma for IdleSoma {
+        type Synapse = TestSynapse;
+        type Error = Error;
+
+        #[async(boxed)]
+        fn update(self, _imp: Impulse<Self::Synapse>) -> Result<Self> {
+            Ok(self)
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_soma_latency() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(IdleSoma, handle.clone());
+        let soma = organelle.add_soma(IdleSoma);
+
+        assert_eq!(organelle.soma_latency(soma).unwrap().count, 0);
+        assert!(organelle.soma_latency(Uuid::new_v4()).is_err());
+
+        let (tx, _rx) = mpsc::channel(1);
+
+        core.run(organelle.somas[&soma].clone().send(Impulse::Start(
+            soma,
+            tx,
+            handle.clone(),
+        ))).unwrap();
+
+        core.run(reactor::Timeout::new(Duration::from_millis(20), &handle)
+            .unwrap())
+            .unwrap();
+
+        assert_eq!(organelle.soma_latency(soma).unwrap().count, 1);
+    }
+
+    #[cfg(feature = "chaos")]
+    #[test]
+    fn test_inject_latency() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(IdleSoma, handle.clone());
+        let soma = organelle.add_soma(IdleSoma);
+
+        assert!(
+            organelle
+                .inject_latency(Uuid::new_v4(), Duration::from_millis(50))
+                .is_err()
+        );
+        organelle
+            .inject_latency(soma, Duration::from_millis(50))
+            .unwrap();
+
+        let (tx, _rx) = mpsc::channel(1);
+
+        core.run(organelle.somas[&soma].clone().send(Impulse::Start(
+            soma,
+            tx,
+            handle.clone(),
+        ))).unwrap();
+
+        let mut wait = |millis| {
+            let timeout =
+                reactor::Timeout::new(Duration::from_millis(millis), &handle);
+
+            core.run(timeout.unwrap()).unwrap();
+        };
+
+        // still held back by the latency
+        wait(20);
+        assert_eq!(organelle.contexts[&soma].impulses.get(), 0);
+
+        wait(60);
+        assert_eq!(organelle.contexts[&soma].impulses.get(), 1);
+    }
+
+    #[cfg(feature = "chaos")]
+    #[test]
+    fn test_inject_error() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let organelle = Organelle::new(IdleSoma, handle.clone());
+        let nucleus = organelle.nucleus();
+
+        assert!(organelle.inject_error(Uuid::new_v4(), 1.0).is_err());
+        organelle.inject_error(nucleus, 1.0).unwrap();
+
+        // the very first impulse, Start, fails
+        match core.run(organelle.run(handle)) {
+            Err(e) => assert!(
+                e.iter()
+                    .any(|e| e.to_string().contains("synthetic error")),
+                "unexpected error: {:#?}",
+                e
+            ),
+            Ok(_) => panic!("the nucleus should have failed"),
+        }
+    }
+
+    #[test]
+    fn test_soma_history() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(IdleSoma, handle.clone());
+        let peer = organelle.add_soma(IdleSoma);
+        let settings = SomaSettings::default().history(2);
+        let soma = organelle.add_soma_with_settings(IdleSoma, settings);
+
+        assert!(organelle.soma_history(peer).is_err());
+        assert!(organelle.soma_history(Uuid::new_v4()).is_err());
+
+        let (tx, _rx) = mpsc::channel(1);
+
+        for imp in vec![
+            Impulse::AddDendrite(peer, TestSynapse::Nothing, ()),
+            Impulse::AddTerminal(peer, TestSynapse::Nothing, ()),
+            Impulse::Start(soma, tx, handle.clone()),
+        ] {
+            core.run(organelle.somas[&soma].clone().send(imp)).unwrap();
+        }
+
+        core.run(reactor::Timeout::new(Duration::from_millis(20), &handle)
+            .unwrap())
+            .unwrap();
+
+        // only the last two are kept
+        assert_eq!(
+            organelle.soma_history(soma).unwrap(),
+            vec![
+                ImpulseRecord {
+                    kind: ImpulseKind::AddTerminal,
+                    soma: Some(peer),
+                    synapse: Some("Nothing".to_string()),
+                },
+                ImpulseRecord {
+                    kind: ImpulseKind::Start,
+                    soma: Some(soma),
+                    synapse: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_absorb() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let values = Rc::new(RefCell::new(vec![]));
+        let mut organelle =
+            Organelle::new(ValueSink::new(&values), handle.clone());
+
+        let mut other =
+            Organelle::new(ValueSource { tx: None }, handle.clone());
+        let source = other.nucleus();
+        let sink = other.add_soma(ValueSink::new(&values));
+
+        other.connect(source, sink, ValueSynapse::Value).unwrap();
+
+        let remap = organelle.absorb(other).unwrap();
+
+        assert_eq!(remap.len(), 2);
+        assert_eq!(organelle.soma_count(), 3);
+
+        let source = remap[&source];
+        let sink = remap[&sink];
+
+        assert!(organelle.topology().contains(&(source, vec![sink])));
+
+        // the nucleus never stops the organelle, so just give the absorbed
+        // somas some time to pass the value along
+        let timeout =
+            reactor::Timeout::new(Duration::from_millis(100), &handle).unwrap();
+
+        match core.run(
+            organelle
+                .run(handle.clone())
+                .select2(timeout.map_err(|e| -> Error { e.into() })),
+        ) {
+            Ok(Either::B(_)) => (),
+            Ok(Either::A(_)) => panic!("organelle stopped unexpectedly"),
+            Err(Either::A((e, _))) | Err(Either::B((e, _))) => {
+                panic!("organelle failed: {:#?}", e)
+            },
+        }
+
+        assert_eq!(*values.borrow(), vec![Some(1)]);
+    }
+
+    /// logs when it is sent Start and when it is done handling it
+    struct OrderedSoma {
+        name: &'static str,
+        delay: Duration,
+        log: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Soma for OrderedSoma {
+        type Synapse = TestSynapse;
+        type Error = Error;
+
+        #[async(boxed)]
+        fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+            match imp {
+                Impulse::Start(_, _, handle) => {
+                    self.log
+                        .borrow_mut()
+                        .push(format!("{} starting", self.name));
+
+                    await!(reactor::Timeout::new(self.delay, &handle)?)?;
+
+                    self.log
+                        .borrow_mut()
+                        .push(format!("{} started", self.name));
+                },
+                imp => return Err(imp.unexpected(&self)),
+            }
+
+            Ok(self)
+        }
+    }
+
+    /// run the organelle for a while with a config loader and a worker
+    fn start_in_order(config_delay: Duration) -> Vec<String> {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let log = Rc::new(RefCell::new(vec![]));
+        let mut organelle = Organelle::new(IdleSoma, handle.clone());
+
+        organelle.add_soma(OrderedSoma {
+            name: "worker",
+            delay: Duration::from_millis(0),
+            log: Rc::clone(&log),
+        });
+        organelle.add_soma_with_settings(
+            OrderedSoma {
+                name: "config",
+                delay: config_delay,
+                log: Rc::clone(&log),
+            },
+            SomaSettings::default().start_priority(1),
+        );
+
+        let timeout =
+            reactor::Timeout::new(Duration::from_millis(200), &handle).unwrap();
+
+        match core.run(
+            organelle
+                .run(handle.clone())
+                .select2(timeout.map_err(|e| -> Error { e.into() })),
+        ) {
+            Ok(Either::B(_)) => (),
+            Ok(Either::A(_)) => panic!("organelle stopped unexpectedly"),
+            Err(Either::A((e, _))) | Err(Either::B((e, _))) => {
+                panic!("organelle failed: {:#?}", e)
+            },
+        }
+
+        let log = log.borrow().clone();
+
+        log
+    }
+
+    #[test]
+    fn test_start_priority() {
+        assert_eq!(
+            start_in_order(Duration::from_millis(50)),
+            vec![
+                "config starting",
+                "config started",
+                "worker starting",
+                "worker started",
+            ]
+        );
+
+        // the worker waits on the config loader for as long as it takes
+        assert_eq!(
+            start_in_order(Duration::from_secs(10)),
+            vec!["config starting"]
+        );
+    }
+
+    #[test]
+    fn test_late_start() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let log = Rc::new(RefCell::new(vec![]));
+        let (tx, mut rx) = mpsc::channel(10);
+
+        let organelle = Organelle::new(IdleSoma, handle.clone());
+        let mut organelle = core.run(organelle.update(Impulse::Start(
+            Uuid::new_v4(),
+            tx,
+            handle.clone(),
+        ))).unwrap();
+
+        let settle = |core: &mut reactor::Core| {
+            core.run(reactor::Timeout::new(Duration::from_millis(20), &handle)
+                .unwrap())
+                .unwrap();
+        };
+
+        settle(&mut core);
+
+        // somas added after Start are started right away
+        organelle.add_soma(OrderedSoma {
+            name: "late",
+            delay: Duration::from_millis(0),
+            log: Rc::clone(&log),
+        });
+        let failed = organelle.add_soma(FailSoma);
+
+        settle(&mut core);
+
+        assert_eq!(*log.borrow(), vec!["late starting", "late started"]);
+
+        rx.close();
+        let imps = core.run(rx.collect()).unwrap();
+
+        assert_eq!(imps.len(), 1);
+        match imps[0] {
+            Impulse::Error(ref e) => assert!(
+                e.to_string().contains(&failed.to_string()),
+                "unexpected error message: {}",
+                e
+            ),
+            _ => panic!("expected the late soma to fail"),
+        }
+    }
+
+    #[test]
+    fn test_nucleus_state() {
+        let core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(IdleSoma, handle);
+        let (publisher, watcher) = watch::channel(1u32);
+
+        let nucleus = organelle.nucleus();
+        organelle.watch_soma(nucleus, watcher).unwrap();
+
+        publisher.publish(2);
+
+        assert_eq!(organelle.nucleus_state::<u32>().unwrap(), 2);
+        assert!(organelle.nucleus_state::<String>().is_err());
+
+        let other = organelle.add_soma(IdleSoma);
+        assert!(organelle.soma_state::<u32>(other).is_err());
+    }
+
+    #[test]
+    fn test_await_soma_state() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(IdleSoma, handle.clone());
+        let (publisher, watcher) = watch::channel(1u32);
+
+        let nucleus = organelle.nucleus();
+        organelle.watch_soma(nucleus, watcher).unwrap();
+
+        // 2 is replaced before the waiting future gets a chance to see it
+        handle.spawn(
+            reactor::Timeout::new(Duration::from_millis(10), &handle)
+                .unwrap()
+                .map(move |_| {
+                    publisher.publish(2);
+                    publisher.publish(3);
+                })
+                .map_err(|_| ()),
+        );
+
+        let state = core.run(
+            organelle.await_soma_state(nucleus, |state: &u32| *state >= 2),
+        );
+
+        assert_eq!(state.unwrap(), 3);
+
+        // the publisher is gone, so the state can never change again
+        assert!(
+            core.run(
+                organelle.await_soma_state(nucleus, |state: &u32| *state > 3)
+            ).is_err()
+        );
+        assert!(
+            core.run(organelle.await_soma_state(nucleus, |_: &String| true))
+                .is_err()
+        );
+    }
+
+    /// finishes its job as soon as it starts
+    struct OneShotSoma {
+        done: bool,
+    }
+
+    impl Soma for OneShotSoma {
+        type Synapse = TestSynapse;
+        type Error = Error;
+
+        fn is_done(&self) -> bool {
+            self.done
+        }
+
+        #[async(boxed)]
+        fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+            match imp {
+                Impulse::Start(_, _, _) => Ok(Self { done: true }),
+                _ => Ok(self),
+            }
+        }
+    }
+
+    #[test]
+    fn test_done_soma_is_removed() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(IdleSoma, handle.clone());
+        let (tx, _rx) = mpsc::channel(1);
+
+        let nucleus = organelle.nucleus();
+        let oneshot = organelle.add_soma(OneShotSoma { done: false });
+        organelle.connect(nucleus, oneshot, TestSynapse::Nothing).unwrap();
+
+        core.run(organelle.somas[&oneshot].clone().send(Impulse::Start(
+            oneshot,
+            tx,
+            handle.clone(),
+        ))).unwrap();
+
+        // let the soma finish and its relay wind down
+        core.run(reactor::Timeout::new(Duration::from_millis(50), &handle)
+            .unwrap())
+            .unwrap();
+
+        assert_eq!(*organelle.finished.borrow(), vec![oneshot]);
+
+        organelle.reap_finished().unwrap();
+
+        assert_eq!(organelle.soma_count(), 1);
+        assert!(organelle.connections.borrow().is_empty());
+
+        // the relay ended quietly instead of reporting an error
+        let mut main_rx = organelle.main_rx.take().unwrap();
+        main_rx.close();
+        let (imp, _) = core.run(main_rx.into_future()).ok().unwrap();
+        assert!(imp.is_none());
+    }
+
+    #[test]
+    fn test_absorbed_nucleus_is_removed() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(IdleSoma, handle.clone());
+        let other = Organelle::new(OneShotSoma { done: false }, handle.clone());
+        let (tx, _rx) = mpsc::channel(1);
+
+        let oneshot = other.nucleus();
+        let oneshot = organelle.absorb(other).unwrap()[&oneshot];
+
+        core.run(organelle.somas[&oneshot].clone().send(Impulse::Start(
+            oneshot,
+            tx,
+            handle.clone(),
+        ))).unwrap();
+
+        core.run(reactor::Timeout::new(Duration::from_millis(50), &handle)
+            .unwrap())
+            .unwrap();
+
+        // it is reaped like any other soma of this organelle
+        assert_eq!(*organelle.finished.borrow(), vec![oneshot]);
+
+        organelle.reap_finished().unwrap();
+
+        assert_eq!(organelle.soma_count(), 1);
+
+        // and it no longer stops the organelle it came from, which would
+        // have been forwarded here
+        let mut main_rx = organelle.main_rx.take().unwrap();
+        main_rx.close();
+        let (imp, _) = core.run(main_rx.into_future()).ok().unwrap();
+        assert!(imp.is_none());
+    }
+
+    #[test]
+    fn test_done_nucleus_stops_organelle() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let organelle =
+            Organelle::new(OneShotSoma { done: false }, handle.clone());
+
+        let timeout =
+            reactor::Timeout::new(Duration::from_secs(1), &handle).unwrap();
+
+        let result = core.run(
+            organelle
+                .run(handle.clone())
+                .select2(timeout.map_err(|e| -> Error { e.into() })),
+        );
+
+        match result {
+            Ok(Either::A(_)) => (),
+            Ok(Either::B(_)) => panic!("organelle kept running"),
+            Err(Either::A((e, _))) | Err(Either::B((e, _))) => {
+                panic!("organelle failed: {:#?}", e)
+            },
+        }
+    }
+
+    /// queues a couple of impulses behind StopDraining when it starts
+    struct DrainSoma {
+        handled: Rc<RefCell<Vec<ImpulseKind>>>,
+    }
+
+    impl Soma for DrainSoma {
+        type Synapse = TestSynapse;
+        type Error = Error;
+
+        fn run_buffer(&self) -> usize {
+            3
+        }
+
+        #[async(boxed)]
+        fn on_stop(self) -> Result<()> {
+            self.handled.borrow_mut().push(ImpulseKind::Stop);
+
+            Ok(())
+        }
+
+        #[async(boxed)]
+        fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+            self.handled.borrow_mut().push(imp.kind());
+
+            if let Impulse::Start(uuid, tx, _) = imp {
+                let remove = || {
+                    Impulse::RemoveDendrite(uuid, TestSynapse::Nothing)
+                };
+
+                await!(
+                    tx.send_all(stream::iter_ok(vec![
+                        Impulse::StopDraining,
+                        remove(),
+                        remove(),
+                    ])).map_err(|_| Error::from("unable to queue impulses"))
+                )?;
+            }
+
+            Ok(self)
+        }
+    }
+
+    #[test]
+    fn test_stop_draining() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let handled = Rc::new(RefCell::new(vec![]));
+
+        core.run(
+            DrainSoma {
+                handled: Rc::clone(&handled),
+            }.run(handle),
+        ).unwrap();
+
+        // the impulses queued behind StopDraining are still handled before
+        // the soma is stopped
+        assert_eq!(
+            *handled.borrow(),
+            vec![
+                ImpulseKind::Start,
+                ImpulseKind::RemoveDendrite,
+                ImpulseKind::RemoveDendrite,
+                ImpulseKind::Stop,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recording() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let (soma, record) = recording(DrainSoma {
+            handled: Rc::new(RefCell::new(vec![])),
+        });
+
+        core.run(soma.run(handle)).unwrap();
+
+        // StopDraining is handled by the event loop, so it isn't recorded
+        assert_eq!(
+            record.kinds(),
+            vec![
+                ImpulseKind::Start,
+                ImpulseKind::RemoveDendrite,
+                ImpulseKind::RemoveDendrite,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pause_soma() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(IdleSoma, handle.clone());
+
+        let (soma, record) = recording(IdleSoma);
+        let uuid = organelle.add_soma(soma);
+
+        let settle = |core: &mut reactor::Core| {
+            core.run(reactor::Timeout::new(Duration::from_millis(20), &handle)
+                .unwrap())
+                .unwrap();
+        };
+
+        organelle.pause(uuid).unwrap();
+        settle(&mut core);
+
+        core.run(organelle.somas[&uuid].clone().send(Impulse::AddDendrite(
+            organelle.nucleus(),
+            TestSynapse::Nothing,
+            (),
+        ))).unwrap();
+        settle(&mut core);
+
+        // held back until the soma is resumed
+        assert!(record.kinds().is_empty());
+
+        organelle.resume(uuid).unwrap();
+        settle(&mut core);
+
+        assert_eq!(record.kinds(), vec![ImpulseKind::AddDendrite]);
+
+        assert!(organelle.pause(Uuid::new_v4()).is_err());
+    }
+
+    /// takes longer to start than its deadline allows
+    struct SlowSoma;
+
+    impl Soma for SlowSoma {
+        type Synapse = TestSynapse;
+        type Error = Error;
+
+        fn update_deadline(&self) -> Option<Duration> {
+            Some(Duration::from_millis(10))
+        }
+
+        #[async(boxed)]
+        fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+            if let Impulse::Start(_, _, handle) = imp {
+                await!(reactor::Timeout::new(
+                    Duration::from_millis(50),
+                    &handle
+                )?)?;
+            }
+
+            Ok(self)
+        }
+    }
+
+    #[test]
+    fn test_slow_update() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::new(IdleSoma, handle.clone());
+        let (tx, _rx) = mpsc::channel(1);
+
+        let (events_tx, events_rx) = mpsc::unbounded();
+        organelle.on_topology_change(events_tx);
+
+        let slow = organelle.add_soma(SlowSoma);
+
+        core.run(organelle.somas[&slow].clone().send(Impulse::Start(
+            slow,
+            tx,
+            handle.clone(),
+        ))).unwrap();
+
+        core.run(reactor::Timeout::new(Duration::from_millis(100), &handle)
+            .unwrap())
+            .unwrap();
+
+        mem::drop(organelle);
+
+        let events = core.run(events_rx.collect()).unwrap();
+
+        assert_eq!(events.len(), 2);
+        match events[1] {
+            TopologyEvent::SlowUpdate { soma, elapsed } => {
+                assert_eq!(soma, slow);
+                assert!(elapsed >= Duration::from_millis(10));
+            },
+            ref event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    /// never finishes handling Start
+    struct HangSoma;
+
+    impl Soma for HangSoma {
+        type Synapse = TestSynapse;
+        type Error = Error;
+
+        #[async(boxed)]
+        fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+            if let Impulse::Start(_, _, _) = imp {
+                await!(future::empty::<(), Error>())?;
+            }
+
+            Ok(self)
+        }
+    }
+
+    #[test]
+    fn test_start_timeout() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle = Organelle::builder(handle.clone())
+            .nucleus(IdleSoma)
+            .start_timeout(Duration::from_millis(50))
+            .build()
+            .unwrap();
+
+        let hung = organelle.add_soma(HangSoma);
+
+        match core.run(organelle.run(handle)) {
+            Err(Error(ErrorKind::StartTimeout(somas), _)) => {
+                assert_eq!(somas, vec![hung])
+            },
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("organelle should not have started"),
+        }
+    }
+
+    /// never answers a probe
+    struct DeafSoma;
+
+    impl Soma for DeafSoma {
+        type Synapse = TestSynapse;
+        type Error = Error;
+
+        #[async(boxed)]
+        fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+            if let Impulse::Probe(_, _) = imp {
+                await!(future::empty::<(), Error>())?;
+            }
+
+            Ok(self)
+        }
+    }
+
+    #[test]
+    fn test_probe_timeout() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let mut organelle =
+            Organelle::new(Axon::new(IdleSoma, vec![], vec![]), handle);
+        organelle.uuid = Some(Uuid::new_v4());
+
+        let deaf = organelle.add_soma(DeafSoma);
+
+        let settings =
+            probe::Settings::new().timeout(Duration::from_millis(50));
+        let (_, data) = core.run(organelle.probe(settings)).unwrap();
+
+        match data {
+            SomaData::Organelle { nucleus, somas, .. } => {
+                match *nucleus {
+                    // the nucleus is still handling the probe
+                    SomaData::Axon { ref metrics, .. } => {
+                        assert_eq!(metrics.impulses, Some(0));
+                        assert_eq!(metrics.queued, Some(0));
+                    },
+                    data => panic!("unexpected nucleus {:?}", data),
+                }
+
+                assert_eq!(somas.len(), 1);
+                match somas[0] {
+                    SomaData::Unavailable { uuid, .. } => {
+                        assert_eq!(uuid, deaf)
+                    },
+                    ref data => panic!("unexpected soma {:?}", data),
+                }
+            },
+            data => panic!("unexpected probe data {:?}", data),
+        }
+    }
+
+    /// send a burst of probes to a soma while it is busy starting
+    ///
+    /// the mailbox only holds two impulses, so all but a few of the probes
+    /// overflow it.
+    fn saturate_soma(
+        core: &mut reactor::Core,
+        policy: OverflowPolicy,
+    ) -> (
+        Organelle<IdleSoma>,
+        Uuid,
+        Vec<Result<SomaData>>,
+        Vec<TopologyEvent<TestSynapse>>,
+    ) {
+        let handle = core.handle();
+
+        let mut organelle = Organelle::builder(handle.clone())
+            .nucleus(IdleSoma)
+            .config(OrganelleConfig::default().buffer_sizes(100, 2, 1))
+            .build()
+            .unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded();
+        organelle.on_topology_change(tx);
+
+        let slow = OrderedSoma {
+            name: "slow",
+            delay: Duration::from_millis(50),
+            log: Rc::new(RefCell::new(vec![])),
+        };
+        let soma = organelle.add_soma_with_settings(
+            Axon::new(slow, vec![], vec![]),
+            SomaSettings::default().overflow(policy),
+        );
+
+        let (main_tx, _main_rx) = mpsc::channel(1);
+        let mailbox = core.run(organelle.somas[&soma].clone().send(
+            Impulse::Start(soma, main_tx, handle.clone()),
+        )).unwrap();
+
+        // let the soma get stuck in Start
+        core.run(reactor::Timeout::new(Duration::from_millis(10), &handle)
+            .unwrap())
+            .unwrap();
+
+        let probes: Vec<_> = (0..10)
+            .map(|_| {
+                probe_soma::<IdleSoma>(
+                    soma,
+                    mailbox.clone(),
+                    probe::Settings::new(),
+                    &handle,
+                ).map(|(_, data)| data)
+                    .then(|result| Ok::<_, ()>(result))
+            })
+            .collect();
+
+        let results = core.run(future::join_all(probes)).unwrap();
+
+        rx.close();
+        let events = core.run(rx.collect()).unwrap();
+
+        (organelle, soma, results, events)
+    }
+
+    /// check that every drop was counted, announced and answered
+    fn check_drops(policy: OverflowPolicy) {
+        let mut core = reactor::Core::new().unwrap();
+
+        let (organelle, soma, results, events) =
+            saturate_soma(&mut core, policy);
+
+        let dropped = organelle.dropped_impulses(soma).unwrap();
+
+        let unavailable = results
+            .iter()
+            .filter(|result| match result {
+                &&Ok(SomaData::Unavailable { .. }) => true,
+                _ => false,
+            })
+            .count();
+        let answered = results
+            .iter()
+            .filter(|result| match result {
+                &&Ok(SomaData::Unavailable { .. }) | &&Err(_) => false,
+                _ => true,
+            })
+            .count();
+        let announced = events
+            .into_iter()
+            .filter(|event| {
+                *event == TopologyEvent::ImpulseDropped {
+                    soma: soma,
+                    kind: ImpulseKind::Probe,
+                }
+            })
+            .count();
+
+        assert!(dropped > 0, "nothing was dropped");
+        assert!(answered > 0, "every probe was dropped");
+        assert_eq!(answered + dropped, results.len());
+        assert_eq!(unavailable, dropped);
+        assert_eq!(announced, dropped);
+    }
+
+    #[test]
+    fn test_overflow_block() {
+        let mut core = reactor::Core::new().unwrap();
+
+        let (organelle, soma, results, _) =
+            saturate_soma(&mut core, OverflowPolicy::Block);
+
+        // every probe waited for room instead
+        assert_eq!(organelle.dropped_impulses(soma).unwrap(), 0);
+        for result in results {
+            match result {
+                Ok(SomaData::Unavailable { .. }) | Err(_) => {
+                    panic!("unexpected probe result {:?}", result)
+                },
+                Ok(_) => (),
+            }
+        }
+    }
+
+    #[test]
+    fn test_overflow_drop_newest() {
+        check_drops(OverflowPolicy::DropNewest);
+    }
+
+    #[test]
+    fn test_overflow_drop_oldest() {
+        check_drops(OverflowPolicy::DropOldest);
+    }
+
+    #[test]
+    fn test_overflow_error() {
+        let mut core = reactor::Core::new().unwrap();
+
+        let (organelle, soma, results, _) =
+            saturate_soma(&mut core, OverflowPolicy::Error);
+
+        let msg = format!("mailbox for soma {} is full", soma);
+        let full = results
+            .iter()
+            .filter(|result| match result {
+                &&Err(ref e) => e.iter().any(|e| e.to_string() == msg),
+                _ => false,
+            })
+            .count();
+
+        assert!(full > 0, "the mailbox never filled up");
+        assert!(full < results.len(), "no probe got through");
+        assert_eq!(organelle.dropped_impulses(soma).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_probe_dropped() {
+        let mut core = reactor::Core::new().unwrap();
+
+        let (mut organelle, soma, _, _) =
+            saturate_soma(&mut core, OverflowPolicy::DropNewest);
+        organelle.uuid = Some(Uuid::new_v4());
+
+        let dropped = organelle.dropped_impulses(soma).unwrap();
+
+        assert!(dropped > 0);
+        assert!(organelle.dropped_impulses(Uuid::new_v4()).is_err());
+
+        let settings =
+            probe::Settings::new().timeout(Duration::from_millis(50));
+        let (_, data) = core.run(organelle.probe(settings)).unwrap();
+
+        // somas that haven't dropped anything are left out
+        match data {
+            SomaData::Organelle { dropped: ref counts, .. } => {
+                assert_eq!(counts.len(), 1);
+                assert_eq!(counts.get(&soma), Some(&dropped));
+            },
+            data => panic!("unexpected probe data {:?}", data),
+        }
+    }
+
+    #[test]
+    fn test_probe_diamond() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let any = || {
+            Axon::new(
+                IdleSoma,
+                vec![Constraint::Variadic(TestSynapse::Nothing)],
+                vec![Constraint::Variadic(TestSynapse::Nothing)],
+            )
+        };
+
+        let mut organelle = Organelle::new(any(), handle.clone());
+        organelle.uuid = Some(Uuid::new_v4());
+
+        let a = organelle.nucleus();
+        let b = organelle.add_soma(any());
+        let c = organelle.add_soma(any());
+        let d = organelle.add_soma(any());
+
+        for &(dendrite, terminal) in &[(a, b), (a, c), (b, d), (c, d)] {
+            organelle
+                .connect(dendrite, terminal, TestSynapse::Nothing)
+                .unwrap();
+        }
+
+        core.run(reactor::Timeout::new(Duration::from_millis(10), &handle)
+            .unwrap())
+            .unwrap();
+
+        let (_, data) =
+            core.run(organelle.probe(probe::Settings::new())).unwrap();
+
+        // d is reachable through both b and c, but somas are only ever
+        // reported by the organelle that owns them, so it shows up once
+        let mut uuids: Vec<_> =
+            data.flatten().into_iter().filter_map(|r| r.uuid).collect();
+        let count = uuids.len();
+
+        uuids.sort();
+        uuids.dedup();
+
+        assert_eq!(uuids.len(), count);
+        assert_eq!(count, 5);
+
+        match data {
+            SomaData::Organelle { ref edges, .. } => assert_eq!(edges.len(), 4),
+            _ => unreachable!(),
+        }
+    }
+
+    /// fails to start on its first attempt
+    struct FlakySoma {
+        attempts: Rc<RefCell<u32>>,
+    }
+
+    impl Soma for FlakySoma {
+        type Synapse = TestSynapse;
+        type Error = Error;
+
+        #[async(boxed)]
+        fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+            match imp {
+                Impulse::Start(_, tx, _) => {
+                    *self.attempts.borrow_mut() += 1;
+
+                    if *self.attempts.borrow() == 1 {
+                        bail!("failed to start")
+                    }
+
+                    await!(
+                        tx.send(Impulse::Stop)
+                            .map_err(|_| Error::from("unable to stop"))
+                    )?;
+
+                    Ok(self)
+                },
+                _ => Ok(self),
+            }
+        }
+    }
+
+    #[test]
+    fn test_restart_policy() {
+        let run = |policy| {
+            let mut core = reactor::Core::new().unwrap();
+            let handle = core.handle();
+
+            let attempts = Rc::new(RefCell::new(0));
+
+            let mut organelle = Organelle::new(IdleSoma, handle.clone());
+
+            let (tx, mut rx) = mpsc::unbounded();
+            organelle.on_topology_change(tx);
+
+            let factory_attempts = Rc::clone(&attempts);
+            let soma = organelle.add_soma_with_policy(
+                move || FlakySoma {
+                    attempts: Rc::clone(&factory_attempts),
+                },
+                policy,
+            );
+
+            let result = core.run(organelle.run(handle));
+            let attempts = *attempts.borrow();
+
+            rx.close();
+            let restarts = core.run(rx.collect())
+                .unwrap()
+                .into_iter()
+                .filter(|event| {
+                    *event == TopologyEvent::SomaRestarted {
+                        soma: soma,
+                        attempt: 1,
+                    }
+                })
+                .count();
+
+            (result.is_ok(), attempts, restarts)
+        };
+
+        assert_eq!(run(RestartPolicy::Never), (false, 1, 0));
+        assert_eq!(run(RestartPolicy::MaxRetries(1)), (true, 2, 1));
+        assert_eq!(run(RestartPolicy::Always), (true, 2, 1));
+    }
+
+    #[cfg(feature = "chaos")]
+    #[test]
+    fn test_inject_error_restarts() {
+        let run = |policy| {
+            let mut core = reactor::Core::new().unwrap();
+            let handle = core.handle();
+
+            // never fails on its own, so only the injected fault gets in
+            // the way of the Stop it sends on Start
+            let attempts = Rc::new(RefCell::new(1));
+
+            let mut organelle = Organelle::new(IdleSoma, handle.clone());
+
+            let (tx, mut rx) = mpsc::unbounded();
+            organelle.on_topology_change(tx);
+
+            let factory_attempts = Rc::clone(&attempts);
+            let soma = organelle.add_soma_with_policy(
+                move || FlakySoma {
+                    attempts: Rc::clone(&factory_attempts),
+                },
+                policy,
+            );
+
+            organelle.inject_error(soma, 1.0).unwrap();
+
+            let result = core.run(organelle.run(handle));
+
+            if let Err(ref e) = result {
+                assert!(
+                    e.iter()
+                        .any(|e| e.to_string().contains("synthetic error")),
+                    "unexpected error: {:#?}",
+                    e
+                );
+            }
+
+            // the replacement is started directly by the restart, so it
+            // doesn't see the fault that replaced the first Start
+            let attempts = *attempts.borrow() - 1;
+
+            rx.close();
+            let restarts = core.run(rx.collect())
+                .unwrap()
+                .into_iter()
+                .filter(|event| {
+                    *event == TopologyEvent::SomaRestarted {
+                        soma: soma,
+                        attempt: 1,
+                    }
+                })
+                .count();
+
+            (result.is_ok(), attempts, restarts)
+        };
+
+        assert_eq!(run(RestartPolicy::Never), (false, 0, 0));
+        assert_eq!(run(RestartPolicy::MaxRetries(1)), (true, 1, 1));
+    }
+
+    #[test]
+    fn test_replace_connected_soma() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let values = Rc::new(RefCell::new(vec![]));
+        let mut organelle =
+            Organelle::new(ValueSink::new(&values), handle.clone());
+
+        let sink = organelle.nucleus();
+        let source = organelle.add_soma(ValueSource { tx: None });
+
+        organelle.connect(source, sink, ValueSynapse::Value).unwrap();
+
+        // the sink doesn't handle removal, so it must not be sent the
+        // RemoveDendrite for the old synapse
+        organelle
+            .replace_soma(source, ValueSource { tx: None })
+            .unwrap();
+
+        let (tx, _rx) = mpsc::channel(1);
+
+        for &uuid in &[sink, source] {
+            core.run(organelle.somas[&uuid].clone().send(Impulse::Start(
+                uuid,
+                tx.clone(),
+                handle.clone(),
+            ))).unwrap();
+        }
+
+        core.run(reactor::Timeout::new(Duration::from_millis(20), &handle)
+            .unwrap())
+            .unwrap();
 
-impl<T: Soma + 'static> Organelle<T> {
-    /// create a new organelle
-    pub fn new(main: T, handle: reactor::Handle) -> Self {
-        let (tx, rx) = mpsc::channel(100);
+        // the replacement reached the sink through the new synapse
+        let delivered: Vec<_> =
+            values.borrow().iter().filter_map(|v| *v).collect();
+        assert_eq!(delivered, vec![1]);
 
-        let mut organelle = Self {
-            handle: handle,
+        let mut main_rx = organelle.main_rx.take().unwrap();
+        main_rx.close();
+        let (imp, _) = core.run(main_rx.into_future()).ok().unwrap();
+        assert!(imp.is_none());
+    }
 
-            uuid: None,
+    #[test]
+    fn test_send_to_unknown_soma() {
+        let mut core = reactor::Core::new().unwrap();
 
-            main: Uuid::new_v4(),
-            main_tx: tx,
-            main_rx: Some(rx),
+        let organelle = Organelle::new(IdleSoma, core.handle());
 
-            somas: HashMap::new(),
-        };
+        let send =
+            organelle.send(Uuid::new_v4(), TestSynapse::Nothing, (), |_, _| {
+                Box::new(future::ok(()))
+            });
 
-        let main = organelle.add_soma(main);
-        organelle.main = main;
+        assert!(core.run(send).is_err());
+    }
 
-        organelle
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    enum ValueSynapse {
+        Value,
     }
 
-    /// get the main soma's uuid
-    pub fn nucleus(&self) -> Uuid {
-        self.main
+    impl Synapse for ValueSynapse {
+        type Terminal = mpsc::Sender<u32>;
+        type Dendrite = mpsc::Receiver<u32>;
+
+        fn synapse(self) -> (Self::Terminal, Self::Dendrite) {
+            mpsc::channel(10)
+        }
     }
 
-    fn create_soma_channel<R>(&mut self) -> (Uuid, mpsc::Receiver<Impulse<R>>)
-    where
-        R: Synapse + From<T::Synapse> + Into<T::Synapse> + 'static,
-        R::Dendrite: From<<T::Synapse as Synapse>::Dendrite>
-            + Into<<T::Synapse as Synapse>::Dendrite>
-            + 'static,
-        R::Terminal: From<<T::Synapse as Synapse>::Terminal>
-            + Into<<T::Synapse as Synapse>::Terminal>
-            + 'static,
-    {
-        let uuid = Uuid::new_v4();
+    fn send_value(
+        tx: &mpsc::Sender<u32>,
+        value: u32,
+    ) -> Box<Future<Item = (), Error = Error>> {
+        Box::new(
+            tx.clone()
+                .send(value)
+                .map(|_| ())
+                .map_err(|_| Error::from("value receiver was dropped")),
+        )
+    }
+
+    /// records every value it receives, and None once a synapse closes
+    struct ValueSink {
+        values: Rc<RefCell<Vec<Option<u32>>>>,
+        dendrites: Vec<mpsc::Receiver<u32>>,
+    }
 
-        let (tx, rx) = mpsc::channel::<Impulse<T::Synapse>>(10);
+    impl ValueSink {
+        fn new(values: &Rc<RefCell<Vec<Option<u32>>>>) -> Self {
+            Self {
+                values: Rc::clone(values),
+                dendrites: vec![],
+            }
+        }
+    }
 
-        let (soma_tx, soma_rx) = mpsc::channel::<Impulse<R>>(1);
+    impl Soma for ValueSink {
+        type Synapse = ValueSynapse;
+        type Error = Error;
 
-        self.handle.spawn(
-            soma_tx
-                .send_all(rx.map(|imp| match imp {
-                    Impulse::Start(uuid, sender, handle) => {
-                        let (tx, rx) = mpsc::channel::<Impulse<R>>(1);
+        #[async(boxed)]
+        fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+            match imp {
+                Impulse::AddDendrite(_, ValueSynapse::Value, rx) => {
+                    self.dendrites.push(rx);
+                },
+                Impulse::Start(_, _, handle) => {
+                    for rx in self.dendrites.drain(..) {
+                        let values = Rc::clone(&self.values);
+                        let closed = Rc::clone(&self.values);
 
                         handle.spawn(
-                            sender
-                                .send_all(rx.map(move |imp| {
-                                    Impulse::<T::Synapse>::convert_from(imp)
-                                }).map_err(|_| unreachable!()))
-                                .map(|_| ())
-                                .map_err(|_| ()),
+                            rx.for_each(move |value| {
+                                values.borrow_mut().push(Some(value));
+                                Ok(())
+                            }).then(move |_| {
+                                closed.borrow_mut().push(None);
+                                Ok(())
+                            }),
                         );
+                    }
+                },
+                imp => return Err(imp.unexpected(&self)),
+            }
 
-                        Impulse::Start(uuid, tx, handle)
-                    },
-                    _ => Impulse::<R>::convert_from(imp),
-                }).map_err(|_| unreachable!()))
-                .map(|_| ())
-                .map_err(|_| ()),
+            Ok(self)
+        }
+    }
+
+    #[test]
+    fn test_conditional_connections() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let values = Rc::new(RefCell::new(vec![]));
+        let mut organelle =
+            Organelle::new(ValueSink::new(&values), handle.clone());
+
+        let sink = organelle.nucleus();
+        let source = organelle.add_soma(ValueSource { tx: None });
+
+        assert!(
+            organelle
+                .connect_if(false, source, sink, ValueSynapse::Value)
+                .unwrap()
+                .is_none()
         );
+        assert!(organelle.connections.borrow().is_empty());
 
-        self.somas.insert(uuid, tx);
+        // only true once the last soma has been added, so this also checks
+        // that the condition is evaluated at Start
+        organelle.connect_when(source, sink, ValueSynapse::Value, |o| {
+            o.soma_count() == 3
+        });
 
-        (uuid, soma_rx)
-    }
+        // a ValueSink fails if it is given a terminal
+        let idle = organelle.add_soma(ValueSink::new(&values));
+        organelle.connect_when(idle, sink, ValueSynapse::Value, |_| false);
 
-    #[async]
-    fn run_soma<U: Soma + 'static>(
-        mut soma: U,
-        soma_rx: mpsc::Receiver<Impulse<U::Synapse>>,
-    ) -> std::result::Result<(), Error> {
-        #[async]
-        for imp in soma_rx.map_err(|_| -> Error { unreachable!() }) {
-            soma = await!(soma.update(imp)).map_err(|e| e.into())?;
+        let timeout =
+            reactor::Timeout::new(Duration::from_millis(100), &handle).unwrap();
+
+        match core.run(
+            organelle
+                .run(handle.clone())
+                .select2(timeout.map_err(|e| -> Error { e.into() })),
+        ) {
+            Ok(Either::B(_)) => (),
+            Ok(Either::A(_)) => panic!("organelle stopped unexpectedly"),
+            Err(Either::A((e, _))) | Err(Either::B((e, _))) => {
+                panic!("organelle failed: {:#?}", e)
+            },
         }
 
-        Ok(())
+        assert_eq!(*values.borrow(), vec![Some(1)]);
     }
 
-    /// add a soma to the organelle
-    pub fn add_soma<U: Soma + 'static>(&mut self, soma: U) -> Uuid
+    /// feed the values to a sink and run the organelle for a while
+    fn feed<S>(
+        stream: S,
+        stop: bool,
+    ) -> (Option<Result<()>>, Vec<Option<u32>>)
     where
-        U::Synapse: From<T::Synapse> + Into<T::Synapse>,
-        <U::Synapse as Synapse>::Dendrite: From<<T::Synapse as Synapse>::Dendrite>
-            + Into<<T::Synapse as Synapse>::Dendrite>,
-        <U::Synapse as Synapse>::Terminal: From<<T::Synapse as Synapse>::Terminal>
-            + Into<<T::Synapse as Synapse>::Terminal>,
+        S: Stream<Item = u32, Error = Error> + 'static,
     {
-        let (uuid, soma_rx) = self.create_soma_channel::<U::Synapse>();
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
 
-        let main_tx = self.main_tx.clone();
+        let values = Rc::new(RefCell::new(vec![]));
+        let organelle = Organelle::new(ValueSink::new(&values), handle.clone());
+        let nucleus = organelle.nucleus();
 
-        self.handle
-            .spawn(Self::run_soma(soma, soma_rx).or_else(move |e| {
-                main_tx
-                    .send(Impulse::Error(e.into()))
-                    .map(|_| ())
-                    .map_err(|_| ())
-            }));
+        organelle
+            .feed(nucleus, ValueSynapse::Value, stream, stop, send_value)
+            .unwrap();
 
-        uuid
-    }
+        let timeout =
+            reactor::Timeout::new(Duration::from_millis(100), &handle).unwrap();
 
-    /// connect two somas together using the specified synapse
-    pub fn connect(
-        &self,
-        dendrite: Uuid,
-        terminal: Uuid,
-        synapse: T::Synapse,
-    ) -> Result<()> {
-        let (tx, rx) = synapse.synapse();
+        let result = match core.run(
+            organelle
+                .run(handle.clone())
+                .select2(timeout.map_err(|e| -> Error { e.into() })),
+        ) {
+            Ok(Either::A(_)) => Some(Ok(())),
+            Ok(Either::B(_)) => None,
+            Err(Either::A((e, _))) => Some(Err(e)),
+            Err(Either::B((e, _))) => panic!("timeout failed: {:#?}", e),
+        };
 
-        self.add_terminal((terminal, tx), dendrite, synapse)?;
-        self.add_dendrite((dendrite, rx), terminal, synapse)?;
+        let values = values.borrow().clone();
 
-        Ok(())
+        (result, values)
     }
 
-    /// send a dendrite to the specified soma
-    pub fn add_dendrite(
-        &self,
-        dendrite: (Uuid, <T::Synapse as Synapse>::Dendrite),
-        terminal: Uuid,
-        synapse: T::Synapse,
-    ) -> Result<()> {
-        let terminal_sender = if let Some(sender) = self.somas.get(&terminal) {
-            sender.clone()
-        } else {
-            bail!("unable to find terminal")
-        };
+    #[test]
+    fn test_feed() {
+        // the end of the stream only closes the synapse
+        let (result, values) = feed(stream::iter_ok(vec![1, 2, 3]), false);
 
-        self.handle.spawn(
-            terminal_sender
-                .send(Impulse::AddDendrite(dendrite.0, synapse, dendrite.1))
-                .map(|_| ())
-                .map_err(|_| {
-                    eprintln!("unable to add dendrite");
-                }),
-        );
+        assert!(result.is_none());
+        assert_eq!(values, vec![Some(1), Some(2), Some(3), None]);
 
-        Ok(())
+        let (result, _) = feed(stream::iter_ok(vec![1, 2, 3]), true);
+
+        assert!(result.unwrap().is_ok());
+
+        let failing = stream::iter_result(vec![
+            Ok(1),
+            Err(Error::from("command source failed")),
+        ]);
+        let (result, _) = feed(failing, true);
+
+        assert!(
+            result
+                .unwrap()
+                .unwrap_err()
+                .iter()
+                .any(|e| e.to_string() == "command source failed")
+        );
     }
 
-    /// send a terminal to the specified soma
-    pub fn add_terminal(
-        &self,
-        terminal: (Uuid, <T::Synapse as Synapse>::Terminal),
-        dendrite: Uuid,
-        synapse: T::Synapse,
-    ) -> Result<()> {
-        let dendrite_sender = if let Some(sender) = self.somas.get(&dendrite) {
-            sender.clone()
-        } else {
-            bail!("unable to find dendrite")
-        };
+    #[test]
+    fn test_connect_path() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
 
-        self.handle.spawn(
-            dendrite_sender
-                .send(Impulse::AddTerminal(terminal.0, synapse, terminal.1))
-                .map(|_| ())
-                .map_err(|_| {
-                    eprintln!("unable to add terminal");
-                }),
+        let values = Rc::new(RefCell::new(vec![]));
+
+        let mut child = Organelle::new(ValueSink::new(&values), handle.clone());
+        let leaf = child.add_soma(ValueSink::new(&values));
+
+        let mut parent =
+            Organelle::new(ValueSink::new(&values), handle.clone());
+        let source = parent.add_soma(ValueSource { tx: None });
+        let nested = parent.add_organelle(child);
+
+        assert!(
+            parent
+                .connect_path(&[source], &[], ValueSynapse::Value)
+                .is_err()
+        );
+        assert!(
+            parent
+                .connect_path(
+                    &[source],
+                    &[nested, Uuid::new_v4()],
+                    ValueSynapse::Value
+                )
+                .is_err()
         );
 
-        Ok(())
-    }
+        let connection = parent
+            .connect_path(&[source], &[nested, leaf], ValueSynapse::Value)
+            .unwrap();
 
-    fn start_all(
-        &self,
-        tx: mpsc::Sender<Impulse<T::Synapse>>,
-        handle: reactor::Handle,
-    ) -> Result<()> {
-        for (uuid, sender) in &self.somas {
-            self.handle.spawn(
-                sender
-                    .clone()
-                    .send(Impulse::Start(*uuid, tx.clone(), handle.clone()))
-                    .then(|_| future::ok(())),
-            );
-        }
+        assert_eq!(connection.dendrite, source);
+        assert_eq!(connection.terminal, leaf);
+        assert_eq!(parent.connection(connection.uuid), Some(connection));
 
-        Ok(())
-    }
+        // the nested end shows up as the organelle that contains it
+        assert!(parent.topology().contains(&(source, vec![nested])));
 
-    #[async]
-    fn perform_probe(
-        self,
-        settings: probe::Settings,
-        tx: oneshot::Sender<SomaData>,
-    ) -> Result<Self> {
-        let (organelle, data) = await!(self.probe(settings))?;
+        let timeout =
+            reactor::Timeout::new(Duration::from_millis(100), &handle).unwrap();
 
-        if let Err(_) = tx.send(data) {
-            // rx does not care anymore
+        match core.run(
+            parent
+                .run(handle.clone())
+                .select2(timeout.map_err(|e| -> Error { e.into() })),
+        ) {
+            Ok(Either::B(_)) => (),
+            Ok(Either::A(_)) => panic!("organelle stopped unexpectedly"),
+            Err(Either::A((e, _))) | Err(Either::B((e, _))) => {
+                panic!("organelle failed: {:#?}", e)
+            },
         }
 
-        Ok(organelle)
+        // the value went straight from the source to the nested leaf
+        assert_eq!(*values.borrow(), vec![Some(1)]);
     }
-}
 
-impl<T: Soma + 'static> Soma for Organelle<T> {
-    type Synapse = T::Synapse;
-    type Error = Error;
+    #[test]
+    fn test_connect_path_constraints() {
+        let core = reactor::Core::new().unwrap();
+        let handle = core.handle();
 
-    #[async(boxed)]
-    fn probe(self, settings: probe::Settings) -> Result<(Self, SomaData)> {
-        let results = await!(
-            stream::iter_ok(self.somas.clone())
-                .map(move |(uuid, sender)| {
-                    let (tx, rx) = oneshot::channel();
+        let mut child = Organelle::new(IdleSoma, handle.clone());
+        let leaf = child.add_soma(Axon::new(
+            IdleSoma,
+            vec![Constraint::One(TestSynapse::Nothing)],
+            vec![],
+        ));
 
-                    sender
-                        .send(Impulse::Probe(settings.clone(), tx))
-                        .map_err(|_| {
-                            Error::from("unable to send probe impulse")
-                        })
-                        .and_then(move |_| {
-                            rx.map(move |rx| (uuid, rx)).map_err(|e| e.into())
-                        })
-                })
-                .collect()
-                .and_then(|receivers| future::join_all(receivers))
-        )?;
+        let mut parent = Organelle::new(IdleSoma, handle.clone());
+        let a = parent.add_soma(IdleSoma);
+        let b = parent.add_soma(IdleSoma);
+        let nested = parent.add_organelle(child);
 
-        let nucleus_uuid = self.nucleus();
-        let mut nucleus = None;
+        parent
+            .connect_path(&[a], &[nested, leaf], TestSynapse::Nothing)
+            .unwrap();
 
-        let somas = results
-            .into_iter()
-            .filter_map(|(uuid, data)| {
-                if uuid == nucleus_uuid {
-                    nucleus = Some(data);
-                    None
-                } else {
-                    Some(data)
-                }
-            })
-            .collect();
+        // the leaf only takes one dendrite, even across the boundary
+        assert!(
+            parent
+                .connect_path(&[b], &[nested, leaf], TestSynapse::Nothing)
+                .is_err()
+        );
 
-        let uuid = self.uuid.unwrap();
+        parent.disconnect(a, leaf, TestSynapse::Nothing).unwrap();
 
-        Ok((
-            self,
-            SomaData::Organelle {
-                nucleus: Box::new(nucleus.unwrap()),
-                somas: somas,
-                uuid: uuid,
-                name: unsafe { intrinsics::type_name::<Self>().into() },
-            },
-        ))
+        assert!(parent.topology().contains(&(a, vec![])));
+
+        // disconnecting frees the leaf's dendrite up again
+        parent
+            .connect_path(&[b], &[nested, leaf], TestSynapse::Nothing)
+            .unwrap();
+
+        assert!(parent.topology().contains(&(b, vec![nested])));
     }
 
-    #[async(boxed)]
-    fn update(mut self, imp: Impulse<T::Synapse>) -> Result<Self> {
-        match imp {
-            Impulse::AddDendrite(_, _, _) | Impulse::AddTerminal(_, _, _) => {
-                await!(
-                    self.somas
-                        .get(&self.nucleus())
-                        .unwrap()
-                        .clone()
-                        .send(imp)
-                        .map_err(|_| Error::from("unable to forward impulse"))
-                )?;
-                Ok(self)
-            },
-            Impulse::Start(uuid, tx, handle) => {
-                self.uuid = Some(uuid);
+    #[test]
+    fn test_send_from_host() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
 
-                let rx = mem::replace(&mut self.main_rx, None).unwrap();
+        let values = Rc::new(RefCell::new(vec![]));
+        let organelle = Organelle::new(ValueSink::new(&values), handle.clone());
+        let nucleus = organelle.nucleus();
 
-                handle.spawn(
-                    tx.clone()
-                        .send_all(rx.map_err(|_| unreachable!()))
-                        .map(|_| ())
-                        .map_err(|_| ()),
-                );
+        // nothing can be sent until the host is connected
+        let send = organelle.send(nucleus, ValueSynapse::Value, 0, send_value);
 
-                self.start_all(tx, handle)?;
+        assert!(core.run(send).is_err());
 
-                Ok(self)
-            },
+        organelle.connect_host(nucleus, ValueSynapse::Value).unwrap();
 
-            Impulse::Probe(settings, tx) => {
-                await!(self.perform_probe(settings, tx))
-            },
+        // every send goes through the same synapse
+        assert!(
+            organelle
+                .connect_host(nucleus, ValueSynapse::Value)
+                .is_err()
+        );
 
-            Impulse::Stop | Impulse::Error(_) => unreachable!(),
+        for value in 1..4 {
+            core.run(organelle.send(
+                nucleus,
+                ValueSynapse::Value,
+                value,
+                send_value,
+            )).unwrap();
         }
+
+        let (tx, _rx) = mpsc::channel(1);
+
+        core.run(organelle.somas[&nucleus].clone().send(Impulse::Start(
+            nucleus,
+            tx,
+            handle.clone(),
+        ))).unwrap();
+        core.run(reactor::Timeout::new(Duration::from_millis(20), &handle)
+            .unwrap())
+            .unwrap();
+
+        assert_eq!(*values.borrow(), vec![Some(1), Some(2), Some(3)]);
+
+        // failures to forward the signal are handed back to the caller
+        let send = organelle.send(nucleus, ValueSynapse::Value, 4, |_, _| {
+            Box::new(future::err(Error::from("unable to forward")))
+        });
+
+        assert!(core.run(send).is_err());
     }
 
-    /// convert this soma into a future that can be passed to an event loop
-    #[async(boxed)]
-    fn run(mut self, handle: reactor::Handle) -> Result<()>
-    where
-        Self: 'static,
-    {
-        let (tx, rx) = mpsc::channel(1);
+    #[test]
+    fn test_replace_soma() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
 
-        let uuid = Uuid::new_v4();
+        let mut organelle = Organelle::new(IdleSoma, handle.clone());
 
-        await!(
-            tx.clone()
-                .send(Impulse::Start(uuid, tx, handle))
-                .map_err(|_| Error::from("unable to send start signal"))
-        )?;
+        let soma = organelle.add_soma(FailSoma);
 
-        #[async]
-        for imp in rx.map_err(|_| -> Error { unreachable!() }) {
-            match imp {
-                Impulse::Error(e) => bail!(e),
-                Impulse::Stop => break,
+        // past its first attempt, so it stops the organelle once started
+        let attempts = Rc::new(RefCell::new(1));
 
-                _ => {
-                    self = await!(self.update(imp))
-                        .map_err(|e| -> Error { e.into() })?
+        assert!(
+            organelle
+                .replace_soma(
+                    Uuid::new_v4(),
+                    FlakySoma {
+                        attempts: Rc::clone(&attempts),
+                    }
+                )
+                .is_err()
+        );
+
+        organelle
+            .replace_soma(
+                soma,
+                FlakySoma {
+                    attempts: Rc::clone(&attempts),
                 },
-            }
-        }
+            )
+            .unwrap();
 
-        Ok(())
+        core.run(organelle.run(handle)).unwrap();
+
+        assert_eq!(*attempts.borrow(), 2);
+    }
+
+    #[test]
+    fn test_topology_events() {
+        let mut core = reactor::Core::new().unwrap();
+
+        let mut organelle = Organelle::new(FailSoma, core.handle());
+
+        let (tx, rx) = mpsc::unbounded();
+        organelle.on_topology_change(tx);
+
+        let a = organelle.add_soma(FailSoma);
+        let b = organelle.add_soma(FailSoma);
+
+        organelle.connect(a, b, TestSynapse::Nothing).unwrap();
+        organelle.disconnect(a, b, TestSynapse::Nothing).unwrap();
+        organelle.remove_soma(a).unwrap();
+
+        mem::drop(organelle);
+
+        let events = core.run(rx.collect()).unwrap();
+
+        assert_eq!(
+            events,
+            vec![
+                TopologyEvent::SomaAdded(a),
+                TopologyEvent::SomaAdded(b),
+                TopologyEvent::Connected {
+                    dendrite: a,
+                    terminal: b,
+                    synapse: TestSynapse::Nothing,
+                },
+                TopologyEvent::Disconnected {
+                    dendrite: a,
+                    terminal: b,
+                    synapse: TestSynapse::Nothing,
+                },
+                TopologyEvent::SomaRemoved(a),
+            ]
+        );
     }
 }
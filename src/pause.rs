@@ -0,0 +1,197 @@
+use std::collections::VecDeque;
+
+use futures::prelude::*;
+
+use soma::{Impulse, Synapse};
+
+/// holds back impulses between Impulse::Pause and Impulse::Resume
+///
+/// the inner stream is still drained while paused so that Resume can be
+/// seen, which means everything sent in the meantime is buffered here without
+/// a bound. impulses are replayed in the order they arrived once resumed, and
+/// anything still buffered when the inner stream ends is flushed before this
+/// stream ends too. probes are never held back, so a paused soma can still be
+/// inspected, and neither are Stop, StopDraining and Error, so a paused soma
+/// can still be shut down.
+///
+/// somas that handle Pause and Resume themselves (see Soma::handles_pause)
+/// have them passed through untouched.
+pub struct Pausable<S: Stream> {
+    stream: S,
+    enabled: bool,
+    paused: bool,
+    buffered: VecDeque<S::Item>,
+}
+
+impl<S: Stream> Pausable<S> {
+    /// wrap a stream of impulses, passing Pause and Resume on if not enabled
+    pub fn new(stream: S, enabled: bool) -> Self {
+        Self {
+            stream: stream,
+            enabled: enabled,
+            paused: false,
+            buffered: VecDeque::new(),
+        }
+    }
+
+    /// get the inner stream
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+}
+
+impl<R, S> Stream for Pausable<S>
+where
+    R: Synapse,
+    S: Stream<Item = Impulse<R>>,
+{
+    type Item = Impulse<R>;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if !self.enabled {
+            return self.stream.poll();
+        }
+
+        if !self.paused {
+            if let Some(imp) = self.buffered.pop_front() {
+                return Ok(Async::Ready(Some(imp)));
+            }
+        }
+
+        loop {
+            let imp = match self.stream.poll()? {
+                Async::Ready(imp) => imp,
+                Async::NotReady => return Ok(Async::NotReady),
+            };
+
+            match imp {
+                Some(Impulse::Pause) => self.paused = true,
+                Some(Impulse::Resume) => {
+                    self.paused = false;
+
+                    if let Some(imp) = self.buffered.pop_front() {
+                        return Ok(Async::Ready(Some(imp)));
+                    }
+                },
+                Some(imp @ Impulse::Probe(_, _))
+                | Some(imp @ Impulse::Stop)
+                | Some(imp @ Impulse::StopDraining)
+                | Some(imp @ Impulse::Error(_)) => {
+                    return Ok(Async::Ready(Some(imp)))
+                },
+                Some(imp) => {
+                    if self.paused {
+                        self.buffered.push_back(imp);
+                    } else {
+                        return Ok(Async::Ready(Some(imp)));
+                    }
+                },
+                None => {
+                    self.paused = false;
+
+                    return Ok(Async::Ready(self.buffered.pop_front()));
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::future;
+    use futures::unsync::mpsc;
+    use tokio_core::reactor;
+    use uuid::Uuid;
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    enum TestSynapse {
+        Nothing,
+    }
+
+    impl Synapse for TestSynapse {
+        type Terminal = ();
+        type Dendrite = ();
+
+        fn synapse(self) -> ((), ()) {
+            ((), ())
+        }
+    }
+
+    type Rx = Pausable<mpsc::UnboundedReceiver<Impulse<TestSynapse>>>;
+
+    /// poll once, giving None if the stream is not ready
+    fn next(core: &mut reactor::Core, rx: &mut Rx) -> Option<Option<Uuid>> {
+        let poll = core.run(future::poll_fn(|| -> Poll<_, ()> {
+            Ok(Async::Ready(rx.poll()))
+        })).unwrap();
+
+        match poll {
+            Ok(Async::Ready(imp)) => {
+                Some(imp.and_then(|imp| imp.record().soma))
+            },
+            Ok(Async::NotReady) => None,
+            Err(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_pause() {
+        let mut core = reactor::Core::new().unwrap();
+
+        let (tx, rx) = mpsc::unbounded();
+        let mut rx = Pausable::new(rx, true);
+
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let remove = |uuid| Impulse::RemoveDendrite(uuid, TestSynapse::Nothing);
+
+        tx.unbounded_send(remove(a)).unwrap();
+        tx.unbounded_send(Impulse::Pause).unwrap();
+        tx.unbounded_send(remove(b)).unwrap();
+
+        assert_eq!(next(&mut core, &mut rx), Some(Some(a)));
+        // b is held back until Resume
+        assert_eq!(next(&mut core, &mut rx), None);
+
+        tx.unbounded_send(Impulse::Resume).unwrap();
+
+        assert_eq!(next(&mut core, &mut rx), Some(Some(b)));
+
+        tx.unbounded_send(Impulse::Pause).unwrap();
+        tx.unbounded_send(remove(a)).unwrap();
+        drop(tx);
+
+        // nothing buffered is lost when the stream ends
+        assert_eq!(next(&mut core, &mut rx), Some(Some(a)));
+        assert_eq!(next(&mut core, &mut rx), Some(None));
+    }
+
+    #[test]
+    fn test_stop_while_paused() {
+        let mut core = reactor::Core::new().unwrap();
+
+        let (tx, rx) = mpsc::unbounded();
+        let mut rx = Pausable::new(rx, true);
+
+        let a = Uuid::new_v4();
+
+        tx.unbounded_send(Impulse::Pause).unwrap();
+        tx.unbounded_send(Impulse::RemoveDendrite(a, TestSynapse::Nothing))
+            .unwrap();
+        tx.unbounded_send(Impulse::Stop).unwrap();
+
+        let poll = core.run(future::poll_fn(|| -> Poll<_, ()> {
+            Ok(Async::Ready(rx.poll()))
+        })).unwrap();
+
+        // Stop skips ahead of the impulse held back by Pause
+        match poll {
+            Ok(Async::Ready(Some(Impulse::Stop))) => (),
+            _ => panic!("Stop was held back while paused"),
+        }
+
+        assert_eq!(next(&mut core, &mut rx), None);
+    }
+}
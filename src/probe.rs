@@ -1,3 +1,9 @@
+use std::any::Any;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+use std::time::Duration;
+
 use futures::prelude::*;
 use futures::unsync::{mpsc, oneshot};
 use tokio_core::reactor;
@@ -6,6 +12,40 @@ use uuid::Uuid;
 use super::{Error, Result};
 use axon::{Axon, Constraint};
 use soma::{self, Impulse};
+use synapse::{ask, AskDendrite, AskTerminal};
+
+/// the current version of the probe api
+///
+/// only bump this for changes that older clients can't ignore, like new soma
+/// or constraint types. fields that are skipped when empty are additive and
+/// don't need a new version. every version up to this one is still served.
+///
+/// v2 added the optional, range, and unavailable types.
+pub const API_VERSION: u32 = 2;
+
+/// versioned wrapper around serialized probe data
+#[derive(Debug, Clone, Serialize, Eq, PartialEq)]
+pub struct Envelope<T> {
+    /// the version of the probe api that produced the data
+    pub version: u32,
+    /// the probe data
+    pub data: T,
+}
+
+impl<T> Envelope<T> {
+    /// wrap probe data with the current api version
+    pub fn new(data: T) -> Self {
+        Self::versioned(API_VERSION, data)
+    }
+
+    /// wrap probe data that was produced for an older api version
+    pub fn versioned(version: u32, data: T) -> Self {
+        Self {
+            version: version,
+            data: data,
+        }
+    }
+}
 
 /// data associated with a synapse between two somas
 #[derive(Debug, Clone, Serialize, Eq, PartialEq)]
@@ -22,6 +62,25 @@ pub enum ConstraintData {
         variant: String,
         /// the other soma involved in the synapse
         soma: Uuid,
+        /// whether the constraint has all of the connections it needs
+        satisfied: bool,
+        /// metadata given when the synapse was connected
+        #[serde(skip_serializing_if = "Option::is_none")]
+        meta: Option<ConnectionMeta>,
+    },
+
+    /// at most one synapse of the given variant
+    #[serde(rename = "optional")]
+    Optional {
+        /// the enum variant for the synapse
+        variant: String,
+        /// the other soma involved in the synapse, if one is connected
+        soma: Option<Uuid>,
+        /// whether the constraint has all of the connections it needs
+        satisfied: bool,
+        /// metadata given when the synapse was connected
+        #[serde(skip_serializing_if = "Option::is_none")]
+        meta: Option<ConnectionMeta>,
     },
 
     /// any number of synapses of the given variant
@@ -31,9 +90,157 @@ pub enum ConstraintData {
         variant: String,
         /// the other somas involved in the synapses
         somas: Vec<Uuid>,
+        /// whether the constraint has all of the connections it needs
+        satisfied: bool,
+        /// metadata given when each synapse was connected, keyed by soma
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        meta: BTreeMap<Uuid, ConnectionMeta>,
+    },
+
+    /// a bounded number of synapses of the given variant
+    #[serde(rename = "range")]
+    Range {
+        /// the enum variant for the synapse
+        variant: String,
+        /// the other somas involved in the synapses
+        somas: Vec<Uuid>,
+        /// the fewest synapses accepted
+        min: usize,
+        /// the most synapses accepted
+        max: usize,
+        /// whether the constraint has all of the connections it needs
+        satisfied: bool,
+        /// metadata given when each synapse was connected, keyed by soma
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        meta: BTreeMap<Uuid, ConnectionMeta>,
     },
 }
 
+/// runtime counters for a soma
+///
+/// the organelle running the soma fills in the impulses, last update, and
+/// queue depth, so these are only reported for somas inside an organelle.
+/// somas can add counters of their own with Soma::metrics.
+#[derive(Debug, Clone, Serialize, Eq, PartialEq)]
+pub struct SomaMetrics {
+    /// the number of impulses the soma has handled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub impulses: Option<u64>,
+    /// how long ago the soma last finished handling an impulse
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_update: Option<Duration>,
+    /// the number of impulses waiting in the soma's mailbox
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queued: Option<usize>,
+    /// counters reported by the soma itself
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub counters: BTreeMap<String, u64>,
+}
+
+impl SomaMetrics {
+    /// create empty metrics
+    pub fn new() -> Self {
+        Self {
+            impulses: None,
+            last_update: None,
+            queued: None,
+            counters: BTreeMap::new(),
+        }
+    }
+
+    /// report a counter of the soma's own
+    pub fn counter<S: Into<String>>(mut self, name: S, value: u64) -> Self {
+        self.counters.insert(name.into(), value);
+        self
+    }
+
+    /// check if nothing is reported
+    pub fn is_empty(&self) -> bool {
+        self.impulses.is_none() && self.last_update.is_none()
+            && self.queued.is_none() && self.counters.is_empty()
+    }
+}
+
+impl Default for SomaMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// a connection between two somas of an organelle
+#[derive(Debug, Clone, Serialize, Eq, PartialEq, Ord, PartialOrd)]
+pub struct EdgeData {
+    /// the soma that sends on the connection
+    pub from: Uuid,
+    /// the soma that receives on the connection
+    pub to: Uuid,
+    /// the synapse the somas are connected with
+    pub role: String,
+}
+
+/// optional metadata attached to a connection with Organelle::connect_with
+#[derive(Debug, Clone, Serialize, Eq, PartialEq)]
+pub struct ConnectionMeta {
+    /// a label shown alongside the connection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// a weight shown alongside the connection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<i64>,
+}
+
+impl ConnectionMeta {
+    /// create metadata without a label or weight
+    pub fn new() -> Self {
+        Self {
+            label: None,
+            weight: None,
+        }
+    }
+
+    /// label the connection
+    pub fn label<S: Into<String>>(self, label: S) -> Self {
+        Self {
+            label: Some(label.into()),
+            ..self
+        }
+    }
+
+    /// weight the connection
+    pub fn weight(self, weight: i64) -> Self {
+        Self {
+            weight: Some(weight),
+            ..self
+        }
+    }
+}
+
+impl Default for ConnectionMeta {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// how many connections a constraint has and how many it needs
+#[derive(Debug, Clone, Serialize, Eq, PartialEq)]
+pub struct ConstraintStatus {
+    /// whether the constraint is on a dendrite or a terminal
+    pub kind: String,
+    /// the enum variant for the synapse
+    pub variant: String,
+    /// the number of synapses connected so far
+    pub connected: usize,
+    /// the number of synapses needed before the soma can start
+    pub required: usize,
+}
+
+impl ConstraintStatus {
+    /// check if the constraint has all of the connections it needs
+    pub fn is_satisfied(&self) -> bool {
+        self.connected >= self.required
+    }
+}
+
 /// data associated with a soma, organelle, or axon
 #[derive(Debug, Clone, Serialize, Eq, PartialEq)]
 #[serde(tag = "type")]
@@ -49,6 +256,13 @@ pub enum SomaData {
         uuid: Uuid,
         /// name of the organelle
         name: String,
+        /// impulses dropped by each soma's overflow policy, if any were
+        ///
+        /// see SomaSettings::overflow.
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        dropped: BTreeMap<Uuid, usize>,
+        /// every connection made between the somas of the organelle
+        edges: Vec<EdgeData>,
     },
 
     /// data associated with the axon of a soma
@@ -58,10 +272,23 @@ pub enum SomaData {
         terminals: Vec<ConstraintData>,
         /// data associated with the dendrites for this soma
         dendrites: Vec<ConstraintData>,
-        /// unique id of the axon
+        /// unique id of the axon, nil if it has not started yet
         uuid: Uuid,
         /// name of the axon
         name: String,
+        /// constraints that are still waiting for connections
+        ///
+        /// constraints that are connected are reported in terminals and
+        /// dendrites, so together these show how far along the wiring is.
+        /// this is only ever non-empty while the axon has not started.
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pending: Vec<ConstraintStatus>,
+        /// the category of the wrapped soma, see Soma::category
+        #[serde(skip_serializing_if = "Option::is_none")]
+        category: Option<String>,
+        /// runtime counters for the wrapped soma
+        #[serde(skip_serializing_if = "SomaMetrics::is_empty")]
+        metrics: SomaMetrics,
     },
 
     /// data associated with a custom soma
@@ -71,19 +298,520 @@ pub enum SomaData {
         synapse: SynapseData,
         /// the name of the soma
         name: String,
+        /// the concrete rust type of the soma
+        ///
+        /// this is only reported if the probe settings ask for it.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        type_name: Option<String>,
+        /// the category of the soma, see Soma::category
+        #[serde(skip_serializing_if = "Option::is_none")]
+        category: Option<String>,
+        /// runtime counters for the soma
+        #[serde(skip_serializing_if = "SomaMetrics::is_empty")]
+        metrics: SomaMetrics,
     },
+
+    /// placeholder for a soma that did not answer the probe
+    ///
+    /// see Settings::timeout.
+    #[serde(rename = "unavailable")]
+    Unavailable {
+        /// unique id of the soma
+        uuid: Uuid,
+        /// why the soma's data is missing
+        reason: String,
+    },
+}
+
+/// a single soma in the flattened form of a probe
+///
+/// this shape is easier to consume from command-line tools like jq than the
+/// nested SomaData.
+#[derive(Debug, Clone, Serialize, Eq, PartialEq)]
+pub struct FlatSomaData {
+    /// unique id of the soma, if it has one
+    pub uuid: Option<Uuid>,
+    /// unique id of the organelle containing the soma
+    pub parent: Option<Uuid>,
+    /// the kind of soma (organelle, axon, or soma)
+    pub kind: String,
+    /// name of the soma
+    pub name: String,
+    /// the other somas this soma is connected to
+    pub connections: Vec<Uuid>,
+}
+
+/// picks out somas of interest with SomaData::filter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    /// somas with the given category, see Soma::category
+    Category(String),
+    /// somas whose name starts with the given prefix
+    ///
+    /// a trailing `*` is ignored, so `motor_*` and `motor_` are the same.
+    Name(String),
+}
+
+impl Filter {
+    /// check if the soma matches the filter
+    ///
+    /// organelles are only matched by name, since they have no category.
+    pub fn matches(&self, data: &SomaData) -> bool {
+        match (self, data) {
+            (
+                &Filter::Category(ref category),
+                &SomaData::Axon {
+                    category: Some(ref c),
+                    ..
+                },
+            )
+            | (
+                &Filter::Category(ref category),
+                &SomaData::Soma {
+                    category: Some(ref c),
+                    ..
+                },
+            ) => c == category,
+            (&Filter::Name(ref prefix), &SomaData::Organelle { ref name, .. })
+            | (&Filter::Name(ref prefix), &SomaData::Axon { ref name, .. })
+            | (&Filter::Name(ref prefix), &SomaData::Soma { ref name, .. }) => {
+                name.starts_with(prefix.trim_right_matches('*'))
+            },
+            _ => false,
+        }
+    }
+}
+
+/// a soma in the adjacency-list form of a probe, see render_graph
+#[derive(Debug, Clone, Serialize, Eq, PartialEq)]
+pub struct GraphNode {
+    /// unique id of the soma
+    pub id: Uuid,
+    /// unique id of the organelle containing the soma
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<Uuid>,
+    /// the kind of soma (organelle, axon, or unavailable)
+    pub kind: String,
+    /// name of the soma
+    pub name: String,
+}
+
+/// the adjacency-list form of a probe, see render_graph
+#[derive(Debug, Clone, Serialize, Eq, PartialEq)]
+pub struct GraphJson {
+    /// every soma in the tree that has a uuid, parents first
+    pub nodes: Vec<GraphNode>,
+    /// every connection made in the tree, including nested organelles
+    pub edges: Vec<EdgeData>,
+}
+
+/// flatten the tree into the nodes and edges shape graph libraries expect
+///
+/// somas without a uuid can't be the end of an edge, so they are left out.
+pub fn render_graph(data: SomaData) -> GraphJson {
+    let nodes = data.flatten()
+        .into_iter()
+        .filter_map(|record| {
+            record.uuid.map(|uuid| GraphNode {
+                id: uuid,
+                parent: record.parent,
+                kind: record.kind,
+                name: record.name,
+            })
+        })
+        .collect();
+
+    let mut edges = vec![];
+    collect_edges(data, &mut edges);
+
+    GraphJson {
+        nodes: nodes,
+        edges: edges,
+    }
+}
+
+fn collect_edges(data: SomaData, edges: &mut Vec<EdgeData>) {
+    if let SomaData::Organelle {
+        nucleus,
+        somas,
+        edges: own,
+        ..
+    } = data
+    {
+        edges.extend(own);
+
+        collect_edges(*nucleus, edges);
+
+        for soma in somas {
+            collect_edges(soma, edges);
+        }
+    }
+}
+
+impl ConstraintData {
+    /// the enum variant for the synapse
+    pub fn variant(&self) -> &str {
+        match self {
+            &ConstraintData::One { ref variant, .. } => variant,
+            &ConstraintData::Optional { ref variant, .. } => variant,
+            &ConstraintData::Variadic { ref variant, .. } => variant,
+            &ConstraintData::Range { ref variant, .. } => variant,
+        }
+    }
+
+    /// the other somas involved in the synapses, along with their metadata
+    pub fn connections(&self) -> Vec<(Uuid, Option<&ConnectionMeta>)> {
+        match self {
+            &ConstraintData::One { soma, ref meta, .. } => {
+                vec![(soma, meta.as_ref())]
+            },
+            &ConstraintData::Optional { soma, ref meta, .. } => {
+                soma.into_iter().map(|soma| (soma, meta.as_ref())).collect()
+            },
+            &ConstraintData::Variadic {
+                ref somas,
+                ref meta,
+                ..
+            }
+            | &ConstraintData::Range {
+                ref somas,
+                ref meta,
+                ..
+            } => somas.iter().map(|soma| (*soma, meta.get(soma))).collect(),
+        }
+    }
+
+    /// check if the constraint has all of the connections it needs
+    pub fn is_satisfied(&self) -> bool {
+        match self {
+            &ConstraintData::One { satisfied, .. }
+            | &ConstraintData::Optional { satisfied, .. }
+            | &ConstraintData::Variadic { satisfied, .. }
+            | &ConstraintData::Range { satisfied, .. } => satisfied,
+        }
+    }
+
+    fn somas(&self) -> Vec<Uuid> {
+        self.connections()
+            .into_iter()
+            .map(|(soma, _)| soma)
+            .collect()
+    }
+}
+
+impl SomaData {
+    /// sort the tree so that the same topology always gives the same data
+    ///
+    /// somas are ordered by name and then by uuid, and constraints by
+    /// variant. uuids are random, so somas with the same name only keep
+    /// their order between runs if they are given the same uuids.
+    pub fn sort(&mut self) {
+        match self {
+            &mut SomaData::Organelle {
+                ref mut nucleus,
+                ref mut somas,
+                ref mut edges,
+                ..
+            } => {
+                nucleus.sort();
+
+                for soma in somas.iter_mut() {
+                    soma.sort();
+                }
+
+                somas.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+                edges.sort();
+            },
+            &mut SomaData::Axon {
+                ref mut terminals,
+                ref mut dendrites,
+                ..
+            } => {
+                sort_constraints(terminals);
+                sort_constraints(dendrites);
+            },
+            &mut SomaData::Soma { .. }
+            | &mut SomaData::Unavailable { .. } => (),
+        }
+    }
+
+    /// take the soma with the given uuid out of the tree, if it is in there
+    ///
+    /// organelles and axons are the only somas with uuids, so a custom soma
+    /// can only be reached through the axon or organelle that holds it.
+    pub fn into_subtree(self, target: Uuid) -> Option<SomaData> {
+        let found = match &self {
+            &SomaData::Organelle { uuid, .. }
+            | &SomaData::Axon { uuid, .. }
+            | &SomaData::Unavailable { uuid, .. } => uuid == target,
+            &SomaData::Soma { .. } => false,
+        };
+
+        if found {
+            return Some(self);
+        }
+
+        match self {
+            SomaData::Organelle { nucleus, somas, .. } => {
+                nucleus.into_subtree(target).or_else(|| {
+                    somas
+                        .into_iter()
+                        .filter_map(|soma| soma.into_subtree(target))
+                        .next()
+                })
+            },
+            _ => None,
+        }
+    }
+
+    /// keep only the somas matching the filter and their direct neighbors
+    ///
+    /// the organelles containing the somas that are kept are kept as well,
+    /// along with their nuclei, so the tree keeps its shape. edges are only
+    /// kept between somas that are both still in the tree.
+    pub fn filter(self, filter: &Filter) -> SomaData {
+        let mut matched = HashSet::new();
+        self.find_matches(filter, &mut matched);
+
+        let mut keep = matched.clone();
+        self.find_neighbors(&matched, &mut keep);
+
+        self.prune(filter, &keep)
+    }
+
+    fn uuid(&self) -> Option<Uuid> {
+        match self {
+            &SomaData::Organelle { uuid, .. }
+            | &SomaData::Axon { uuid, .. }
+            | &SomaData::Unavailable { uuid, .. } => Some(uuid),
+            &SomaData::Soma { .. } => None,
+        }
+    }
+
+    fn find_matches(&self, filter: &Filter, matched: &mut HashSet<Uuid>) {
+        if filter.matches(self) {
+            if let Some(uuid) = self.uuid() {
+                matched.insert(uuid);
+            }
+        }
+
+        if let &SomaData::Organelle {
+            ref nucleus,
+            ref somas,
+            ..
+        } = self
+        {
+            nucleus.find_matches(filter, matched);
+
+            for soma in somas {
+                soma.find_matches(filter, matched);
+            }
+        }
+    }
+
+    fn find_neighbors(
+        &self,
+        matched: &HashSet<Uuid>,
+        keep: &mut HashSet<Uuid>,
+    ) {
+        if let &SomaData::Organelle {
+            ref nucleus,
+            ref somas,
+            ref edges,
+            ..
+        } = self
+        {
+            for edge in edges {
+                if matched.contains(&edge.from) {
+                    keep.insert(edge.to);
+                }
+                if matched.contains(&edge.to) {
+                    keep.insert(edge.from);
+                }
+            }
+
+            nucleus.find_neighbors(matched, keep);
+
+            for soma in somas {
+                soma.find_neighbors(matched, keep);
+            }
+        }
+    }
+
+    fn is_kept(&self, filter: &Filter, keep: &HashSet<Uuid>) -> bool {
+        match self {
+            &SomaData::Organelle {
+                ref nucleus,
+                ref somas,
+                uuid,
+                ..
+            } => {
+                keep.contains(&uuid) || nucleus.is_kept(filter, keep)
+                    || somas.iter().any(|soma| soma.is_kept(filter, keep))
+            },
+            &SomaData::Soma { .. } => filter.matches(self),
+            data => data.uuid().map_or(false, |uuid| keep.contains(&uuid)),
+        }
+    }
+
+    fn prune(self, filter: &Filter, keep: &HashSet<Uuid>) -> SomaData {
+        match self {
+            SomaData::Organelle {
+                nucleus,
+                somas,
+                uuid,
+                name,
+                dropped,
+                edges,
+            } => SomaData::Organelle {
+                nucleus: Box::new(nucleus.prune(filter, keep)),
+                somas: somas
+                    .into_iter()
+                    .filter(|soma| soma.is_kept(filter, keep))
+                    .map(|soma| soma.prune(filter, keep))
+                    .collect(),
+                uuid: uuid,
+                name: name,
+                dropped: dropped,
+                edges: edges
+                    .into_iter()
+                    .filter(|e| keep.contains(&e.from) && keep.contains(&e.to))
+                    .collect(),
+            },
+            data => data,
+        }
+    }
+
+    fn sort_key(&self) -> (&str, Option<Uuid>) {
+        match self {
+            &SomaData::Organelle { ref name, uuid, .. }
+            | &SomaData::Axon { ref name, uuid, .. } => (name, Some(uuid)),
+            &SomaData::Soma { ref name, .. } => (name, None),
+            &SomaData::Unavailable { uuid, .. } => ("", Some(uuid)),
+        }
+    }
+
+    /// flatten the tree into a list of records, parents first
+    pub fn flatten(&self) -> Vec<FlatSomaData> {
+        let mut records = vec![];
+
+        self.flatten_into(None, &mut records);
+
+        records
+    }
+
+    fn flatten_into(
+        &self,
+        parent: Option<Uuid>,
+        records: &mut Vec<FlatSomaData>,
+    ) {
+        match self {
+            &SomaData::Organelle {
+                ref nucleus,
+                ref somas,
+                uuid,
+                ref name,
+                ..
+            } => {
+                records.push(FlatSomaData {
+                    uuid: Some(uuid),
+                    parent: parent,
+                    kind: "organelle".into(),
+                    name: name.clone(),
+                    connections: vec![],
+                });
+
+                nucleus.flatten_into(Some(uuid), records);
+
+                for soma in somas {
+                    soma.flatten_into(Some(uuid), records);
+                }
+            },
+            &SomaData::Axon {
+                ref terminals,
+                ref dendrites,
+                uuid,
+                ref name,
+                ..
+            } => records.push(FlatSomaData {
+                uuid: Some(uuid),
+                parent: parent,
+                kind: "axon".into(),
+                name: name.clone(),
+                connections: terminals
+                    .iter()
+                    .chain(dendrites.iter())
+                    .flat_map(|c| c.somas())
+                    .collect(),
+            }),
+            &SomaData::Soma { ref name, .. } => records.push(FlatSomaData {
+                uuid: None,
+                parent: parent,
+                kind: "soma".into(),
+                name: name.clone(),
+                connections: vec![],
+            }),
+            &SomaData::Unavailable { uuid, .. } => {
+                records.push(FlatSomaData {
+                    uuid: Some(uuid),
+                    parent: parent,
+                    kind: "unavailable".into(),
+                    name: String::new(),
+                    connections: vec![],
+                })
+            },
+        }
+    }
+}
+
+fn sort_constraints(constraints: &mut Vec<ConstraintData>) {
+    for constraint in constraints.iter_mut() {
+        match constraint {
+            &mut ConstraintData::Variadic { ref mut somas, .. }
+            | &mut ConstraintData::Range { ref mut somas, .. } => somas.sort(),
+            _ => (),
+        }
+    }
+
+    constraints.sort_by(|a, b| a.variant().cmp(b.variant()));
 }
 
+type Changes = Box<Stream<Item = (), Error = ()>>;
+type Subscription = (Settings, mpsc::UnboundedSender<SomaData>);
+
 /// soma that probes the internal structure of an organelle
 pub struct Soma {
     dendrites: Vec<Dendrite>,
+    changes: Option<Changes>,
 }
 
 impl Soma {
     /// create a new probe soma
+    ///
+    /// subscribers are sent new data whenever the topology of the organelle
+    /// it probes changes.
     pub fn axon() -> Axon<Self> {
+        Self::new(None)
+    }
+
+    /// create a probe soma that also pushes updates on other changes
+    ///
+    /// every item on `changes` re-probes the organelle for each subscriber,
+    /// and the new data is pushed if it differs from what they saw last.
+    /// topology changes are already pushed without this, see Soma::axon.
+    pub fn watching<St>(changes: St) -> Axon<Self>
+    where
+        St: Stream + 'static,
+    {
+        Self::new(Some(Box::new(changes.map(|_| ()).map_err(|_| ()))))
+    }
+
+    fn new(changes: Option<Changes>) -> Axon<Self> {
         Axon::new(
-            Self { dendrites: vec![] },
+            Self {
+                dendrites: vec![],
+                changes: changes,
+            },
             vec![Constraint::Variadic(Synapse::Probe)],
             vec![],
         )
@@ -97,56 +825,217 @@ pub enum Synapse {
     Probe,
 }
 
+/// finds the metadata given to the connection between a dendrite and a
+/// terminal over a synapse
+///
+/// see Settings::connections.
+pub type ConnectionLookup<S> = Rc<Fn(Uuid, Uuid, S) -> Option<ConnectionMeta>>;
+
 /// settings for a probe operation
-#[derive(Debug, Clone)]
-pub struct Settings;
+#[derive(Clone)]
+pub struct Settings {
+    type_names: bool,
+    stable_order: bool,
+    timeout: Option<Duration>,
+    // a ConnectionLookup for the synapse of the organelle being probed
+    connections: Option<Rc<Any>>,
+    changes: Option<mpsc::UnboundedSender<()>>,
+}
+
+impl fmt::Debug for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Settings")
+            .field("type_names", &self.type_names)
+            .field("stable_order", &self.stable_order)
+            .field("timeout", &self.timeout)
+            .field("connections", &self.connections.is_some())
+            .field("changes", &self.changes.is_some())
+            .finish()
+    }
+}
 
 impl Settings {
     /// create settings
     pub fn new() -> Self {
-        Self {}
+        Self {
+            type_names: false,
+            stable_order: false,
+            timeout: None,
+            connections: None,
+            changes: None,
+        }
     }
-}
 
-#[derive(Debug)]
-enum Request {
-    Probe(Settings, oneshot::Sender<SomaData>),
+    /// report the concrete rust type of each soma
+    pub fn type_names(self, flag: bool) -> Self {
+        Self {
+            type_names: flag,
+            ..self
+        }
+    }
+
+    /// report somas and constraints in a stable order, see SomaData::sort
+    pub fn stable_order(self, flag: bool) -> Self {
+        Self {
+            stable_order: flag,
+            ..self
+        }
+    }
+
+    /// give up on somas that take longer than this to answer
+    ///
+    /// organelles probe their somas concurrently, and any soma that has not
+    /// answered in time is reported as SomaData::Unavailable instead of
+    /// holding up the rest. nested organelles apply the timeout again for
+    /// their own somas. by default, probes wait as long as it takes.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// check if the concrete rust type of each soma should be reported
+    pub fn reports_type_names(&self) -> bool {
+        self.type_names
+    }
+
+    /// check if somas and constraints should be reported in a stable order
+    pub fn reports_stable_order(&self) -> bool {
+        self.stable_order
+    }
+
+    /// get the time each soma has to answer, if there is a limit
+    pub fn probe_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// report the metadata given to each connection with its constraint
+    ///
+    /// organelles set this before probing their somas, so there's no need
+    /// to set it by hand.
+    pub fn connections<S: 'static>(self, lookup: ConnectionLookup<S>) -> Self {
+        Self {
+            connections: Some(Rc::new(lookup)),
+            ..self
+        }
+    }
+
+    /// get the connection lookup for synapses of type S, if there is one
+    pub fn connection_lookup<S: 'static>(&self) -> Option<ConnectionLookup<S>> {
+        self.connections
+            .as_ref()
+            .and_then(|lookup| lookup.downcast_ref::<ConnectionLookup<S>>())
+            .cloned()
+    }
+
+    /// notify `tx` whenever the topology of a probed organelle changes
+    ///
+    /// each organelle that answers the probe subscribes `tx` to its topology
+    /// changes (see Organelle::on_topology_change), so this only needs to be
+    /// set on one probe. the probe soma does this for its subscribers.
+    pub fn on_change(self, tx: mpsc::UnboundedSender<()>) -> Self {
+        Self {
+            changes: Some(tx),
+            ..self
+        }
+    }
+
+    /// get the sender to notify of topology changes, if there is one
+    pub fn change_listener(&self) -> Option<mpsc::UnboundedSender<()>> {
+        self.changes.clone()
+    }
+
+    /// find the metadata given to a connection, if any
+    ///
+    /// the synapse is compared by value, so this is None for synapses of a
+    /// different type than the organelle's.
+    pub fn connection_meta<S: 'static>(
+        &self,
+        dendrite: Uuid,
+        terminal: Uuid,
+        synapse: S,
+    ) -> Option<ConnectionMeta> {
+        self.connection_lookup::<S>()
+            .and_then(|lookup| (*lookup)(dendrite, terminal, synapse))
+    }
 }
 
 /// sender for a probe operation
 #[derive(Debug, Clone)]
 pub struct Terminal {
-    tx: mpsc::Sender<Request>,
+    tx: AskTerminal<Settings, SomaData>,
+    subscribe_tx: mpsc::Sender<Subscription>,
 }
 
 impl Terminal {
     /// perform the probe
     #[async]
     pub fn probe(self, settings: Settings) -> Result<SomaData> {
-        let (tx, rx) = oneshot::channel();
+        await!(self.tx.ask(settings))
+    }
 
-        await!(
-            self.tx
-                .send(Request::Probe(settings, tx))
-                .map(|_| ())
-                .map_err(|_| Error::from("unable to send probe request"))
-        )?;
+    /// perform the probe, keeping only the soma with the given uuid
+    ///
+    /// see SomaData::into_subtree. the whole tree is still gathered, but
+    /// only the requested part is handed back.
+    #[async]
+    pub fn probe_subtree(
+        self,
+        settings: Settings,
+        uuid: Uuid,
+    ) -> Result<SomaData> {
+        let data = await!(self.tx.ask(settings))?;
 
-        await!(rx.map_err(|_| Error::from("unable to receive probe response")))
+        match data.into_subtree(uuid) {
+            Some(data) => Ok(data),
+            None => bail!("no soma {} found in probe", uuid),
+        }
+    }
+
+    /// subscribe to live probe data
+    ///
+    /// the current data is sent right away, followed by new data whenever the
+    /// topology of the organelle changes. the stream ends once the probe soma
+    /// stops, and dropping it unsubscribes.
+    pub fn subscribe(
+        self,
+        settings: Settings,
+    ) -> Box<Stream<Item = SomaData, Error = Error>> {
+        let (tx, rx) = mpsc::unbounded();
+
+        Box::new(
+            self.subscribe_tx
+                .send((settings, tx))
+                .map_err(|_| Error::from("unable to send subscription"))
+                .map(move |_| rx.map_err(|_| -> Error { unreachable!() }))
+                .flatten_stream(),
+        )
     }
 }
 
 /// receive for a probe operation
 #[derive(Debug)]
 pub struct Dendrite {
-    rx: mpsc::Receiver<Request>,
+    rx: AskDendrite<Settings, SomaData>,
+    subscribe_rx: mpsc::Receiver<Subscription>,
 }
 
 /// create a junction between two probe-ready somas
 pub fn synapse() -> (Terminal, Dendrite) {
-    let (tx, rx) = mpsc::channel(10);
+    let (tx, rx) = ask();
+    let (subscribe_tx, subscribe_rx) = mpsc::channel(10);
 
-    (Terminal { tx: tx }, Dendrite { rx: rx })
+    (
+        Terminal {
+            tx: tx,
+            subscribe_tx: subscribe_tx,
+        },
+        Dendrite {
+            rx: rx,
+            subscribe_rx: subscribe_rx,
+        },
+    )
 }
 
 impl soma::Synapse for Synapse {
@@ -179,6 +1068,7 @@ impl soma::Soma for Soma {
                         main_tx.clone(),
                         handle.clone(),
                         self.dendrites,
+                        self.changes,
                     ).or_else(move |e| {
                         main_tx
                             .send(Impulse::Error(e))
@@ -187,14 +1077,23 @@ impl soma::Soma for Soma {
                     }),
                 );
 
-                Ok(Self { dendrites: vec![] })
+                Ok(Self {
+                    dendrites: vec![],
+                    changes: None,
+                })
             },
 
-            _ => bail!("unexpected impulse"),
+            imp => Err(imp.unexpected(&self)),
         }
     }
 }
 
+enum ProbeEvent {
+    Probe((Settings, oneshot::Sender<SomaData>)),
+    Subscribe(Subscription),
+    Changed,
+}
+
 struct ProbeTask;
 
 impl ProbeTask {
@@ -203,22 +1102,60 @@ impl ProbeTask {
         main_tx: mpsc::Sender<Impulse<Synapse>>,
         handle: reactor::Handle,
         dendrites: Vec<Dendrite>,
+        changes: Option<Changes>,
     ) -> Result<()> {
         let (tx, rx) = mpsc::channel(10);
 
         for dendrite in dendrites {
+            let Dendrite {
+                rx: probe_rx,
+                subscribe_rx,
+            } = dendrite;
+
             handle.spawn(
                 tx.clone()
-                    .send_all(dendrite.rx.map_err(|_| unreachable!()))
+                    .send_all(
+                        probe_rx
+                            .map(ProbeEvent::Probe)
+                            .map_err(|_| unreachable!()),
+                    )
+                    .map(|_| ())
+                    .map_err(|_| ()),
+            );
+            handle.spawn(
+                tx.clone()
+                    .send_all(
+                        subscribe_rx
+                            .map(ProbeEvent::Subscribe)
+                            .map_err(|_| unreachable!()),
+                    )
+                    .map(|_| ())
+                    .map_err(|_| ()),
+            );
+        }
+
+        if let Some(changes) = changes {
+            handle.spawn(
+                tx.clone()
+                    .send_all(
+                        changes
+                            .map(|_| ProbeEvent::Changed)
+                            .map_err(|_| unreachable!()),
+                    )
                     .map(|_| ())
                     .map_err(|_| ()),
             );
         }
 
+        // each subscriber along with the last data it was sent
+        let mut subscribers: Vec<(Subscription, SomaData)> = vec![];
+        // whether the organelle has been asked to notify us of its changes
+        let mut notified = false;
+
         #[async]
-        for req in rx.map_err(|_| -> Error { unreachable!() }) {
-            match req {
-                Request::Probe(settings, tx) => {
+        for event in rx.map_err(|_| -> Error { unreachable!() }) {
+            match event {
+                ProbeEvent::Probe((settings, tx)) => {
                     await!(
                         main_tx
                             .clone()
@@ -226,9 +1163,339 @@ impl ProbeTask {
                             .map_err(|_| "unable to send probe impulse")
                     )?;
                 },
+                ProbeEvent::Subscribe((settings, sub_tx)) => {
+                    let mut probe_settings = settings.clone();
+
+                    // the first probe registers for topology changes
+                    if !notified {
+                        let (changes_tx, changes_rx) = mpsc::unbounded();
+
+                        handle.spawn(
+                            tx.clone()
+                                .send_all(
+                                    changes_rx
+                                        .map(|_| ProbeEvent::Changed)
+                                        .map_err(|_| unreachable!()),
+                                )
+                                .map(|_| ())
+                                .map_err(|_| ()),
+                        );
+
+                        probe_settings = probe_settings.on_change(changes_tx);
+                        notified = true;
+                    }
+
+                    let data =
+                        await!(Self::probe(main_tx.clone(), probe_settings))?;
+
+                    if sub_tx.unbounded_send(data.clone()).is_ok() {
+                        subscribers.push(((settings, sub_tx), data));
+                    }
+                },
+                ProbeEvent::Changed => {
+                    let mut kept = vec![];
+
+                    for ((settings, tx), last) in subscribers {
+                        let data = await!(Self::probe(
+                            main_tx.clone(),
+                            settings.clone()
+                        ))?;
+
+                        if data == last {
+                            kept.push(((settings, tx), last));
+                        } else if tx.unbounded_send(data.clone()).is_ok() {
+                            kept.push(((settings, tx), data));
+                        }
+                    }
+
+                    subscribers = kept;
+                },
             }
         }
 
         Ok(())
     }
+
+    #[async]
+    fn probe(
+        main_tx: mpsc::Sender<Impulse<Synapse>>,
+        settings: Settings,
+    ) -> Result<SomaData> {
+        let (tx, rx) = oneshot::channel();
+
+        await!(
+            main_tx
+                .send(Impulse::Probe(settings, tx))
+                .map_err(|_| "unable to send probe impulse")
+        )?;
+
+        await!(rx.map_err(|_| Error::from("unable to receive probe data")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json;
+
+    fn axon(name: &str, uuid: Uuid, peers: Vec<Uuid>) -> SomaData {
+        SomaData::Axon {
+            terminals: vec![
+                ConstraintData::Variadic {
+                    variant: "Output".into(),
+                    somas: peers,
+                    satisfied: true,
+                    meta: BTreeMap::new(),
+                },
+                ConstraintData::One {
+                    variant: "Control".into(),
+                    soma: uuid,
+                    satisfied: true,
+                    meta: None,
+                },
+            ],
+            dendrites: vec![],
+            uuid: uuid,
+            name: name.into(),
+            pending: vec![],
+            category: None,
+            metrics: SomaMetrics::new(),
+        }
+    }
+
+    #[test]
+    fn test_subtree() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let (inner, outer) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let data = SomaData::Organelle {
+            nucleus: Box::new(axon("nucleus", a, vec![])),
+            somas: vec![
+                SomaData::Organelle {
+                    nucleus: Box::new(axon("b", b, vec![])),
+                    somas: vec![axon("c", c, vec![])],
+                    uuid: inner,
+                    name: "inner".into(),
+                    dropped: BTreeMap::new(),
+                    edges: vec![],
+                },
+            ],
+            uuid: outer,
+            name: "outer".into(),
+            dropped: BTreeMap::new(),
+            edges: vec![],
+        };
+
+        assert_eq!(
+            data.clone().into_subtree(c),
+            Some(axon("c", c, vec![]))
+        );
+        assert_eq!(
+            data.clone().into_subtree(inner).map(|data| data.flatten().len()),
+            Some(3)
+        );
+        assert_eq!(data.clone().into_subtree(outer), Some(data.clone()));
+        assert_eq!(data.into_subtree(Uuid::new_v4()), None);
+    }
+
+    #[test]
+    fn test_render_graph() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let (inner, outer) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let edge = |from, to| EdgeData {
+            from: from,
+            to: to,
+            role: "Output".into(),
+        };
+
+        let data = SomaData::Organelle {
+            nucleus: Box::new(axon("nucleus", a, vec![inner])),
+            somas: vec![
+                SomaData::Organelle {
+                    nucleus: Box::new(axon("b", b, vec![c])),
+                    somas: vec![axon("c", c, vec![])],
+                    uuid: inner,
+                    name: "inner".into(),
+                    dropped: BTreeMap::new(),
+                    edges: vec![edge(b, c)],
+                },
+            ],
+            uuid: outer,
+            name: "outer".into(),
+            dropped: BTreeMap::new(),
+            edges: vec![edge(a, inner)],
+        };
+
+        let graph = render_graph(data);
+
+        assert_eq!(
+            graph
+                .nodes
+                .iter()
+                .map(|node| (node.id, node.parent))
+                .collect::<Vec<_>>(),
+            vec![
+                (outer, None),
+                (a, Some(outer)),
+                (inner, Some(outer)),
+                (b, Some(inner)),
+                (c, Some(inner)),
+            ]
+        );
+        assert_eq!(graph.edges, vec![edge(a, inner), edge(b, c)]);
+    }
+
+    #[test]
+    fn test_filter() {
+        let (a, b, c, d) = (
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+        let outer = Uuid::new_v4();
+
+        let edge = |from, to| EdgeData {
+            from: from,
+            to: to,
+            role: "Output".into(),
+        };
+
+        let mut sensor = axon("sensor", b, vec![c]);
+        if let SomaData::Axon {
+            ref mut category, ..
+        } = sensor
+        {
+            *category = Some("sensor".into());
+        }
+
+        let data = SomaData::Organelle {
+            nucleus: Box::new(axon("nucleus", a, vec![b])),
+            somas: vec![
+                sensor,
+                axon("motor_c", c, vec![d]),
+                axon("motor_d", d, vec![]),
+            ],
+            uuid: outer,
+            name: "outer".into(),
+            dropped: BTreeMap::new(),
+            edges: vec![edge(a, b), edge(b, c), edge(c, d)],
+        };
+
+        let uuids = |data: &SomaData| {
+            data.flatten()
+                .into_iter()
+                .filter_map(|record| record.uuid)
+                .collect::<Vec<_>>()
+        };
+
+        let sensors = data.clone()
+            .filter(&Filter::Category("sensor".into()));
+
+        assert_eq!(uuids(&sensors), vec![outer, a, b, c]);
+        match sensors {
+            SomaData::Organelle { ref edges, .. } => {
+                assert_eq!(*edges, vec![edge(a, b), edge(b, c)])
+            },
+            _ => unreachable!(),
+        }
+
+        let motors = data.filter(&Filter::Name("motor_d*".into()));
+
+        // the nucleus is always kept along with its organelle
+        assert_eq!(uuids(&motors), vec![outer, a, c, d]);
+    }
+
+    #[test]
+    fn test_stable_order() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let uuid = Uuid::new_v4();
+        let edges = vec![
+            EdgeData {
+                from: c,
+                to: b,
+                role: "Output".into(),
+            },
+            EdgeData {
+                from: c,
+                to: a,
+                role: "Output".into(),
+            },
+        ];
+
+        let mut first = SomaData::Organelle {
+            nucleus: Box::new(axon("nucleus", c, vec![a, b])),
+            somas: vec![axon("b", b, vec![]), axon("a", a, vec![])],
+            uuid: uuid,
+            name: "organelle".into(),
+            dropped: BTreeMap::new(),
+            edges: edges.clone(),
+        };
+        let mut edges = edges;
+        edges.reverse();
+
+        let mut second = SomaData::Organelle {
+            nucleus: Box::new(axon("nucleus", c, vec![b, a])),
+            somas: vec![axon("a", a, vec![]), axon("b", b, vec![])],
+            uuid: uuid,
+            name: "organelle".into(),
+            dropped: BTreeMap::new(),
+            edges: edges.clone(),
+        };
+
+        first.sort();
+        second.sort();
+
+        assert_eq!(first, second);
+        assert_eq!(
+            serde_json::to_string(&first).unwrap(),
+            serde_json::to_string(&second).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_flatten() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let outer = Uuid::new_v4();
+
+        let data = SomaData::Organelle {
+            nucleus: Box::new(axon("nucleus", a, vec![b])),
+            somas: vec![
+                axon("b", b, vec![]),
+                SomaData::Unavailable {
+                    uuid: c,
+                    reason: "timed out".into(),
+                },
+            ],
+            uuid: outer,
+            name: "outer".into(),
+            dropped: BTreeMap::new(),
+            edges: vec![],
+        };
+
+        let records = data.flatten();
+
+        assert_eq!(
+            records
+                .iter()
+                .map(|r| (r.uuid, r.parent, r.kind.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (Some(outer), None, "organelle"),
+                (Some(a), Some(outer), "axon"),
+                (Some(b), Some(outer), "axon"),
+                (Some(c), Some(outer), "unavailable"),
+            ]
+        );
+        assert_eq!(records[1].connections, vec![b, a]);
+
+        // each record is a flat object that jq can pick apart
+        let json = serde_json::to_value(&records).unwrap();
+
+        assert_eq!(json[1]["name"], "nucleus");
+        assert_eq!(json[1]["parent"], outer.to_string());
+        assert_eq!(json[1]["connections"][0], b.to_string());
+    }
 }
@@ -0,0 +1,416 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use futures::prelude::*;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+use tokio_core::reactor;
+use uuid::Uuid;
+
+use super::{Error, Result};
+use soma::{self, Impulse, Synapse};
+
+/// a signal captured in a recorded impulse log
+///
+/// logs are written with a Recorder, or by hand as a JSON array of these.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedSignal<P> {
+    /// time since the start of the recording in milliseconds
+    pub offset_ms: u64,
+    /// the soma that received the signal
+    pub dest: Uuid,
+    /// the signal itself
+    pub signal: P,
+}
+
+/// captures signals as they are sent so they can be replayed later
+///
+/// signals only ever pass through the terminals handed to somas, so the
+/// organelle can't see them on its own. call record from wherever signals
+/// are sent (e.g. the `emit` function given to a soma) and save the log
+/// once the scenario is over. clones share the same log.
+#[derive(Debug)]
+pub struct Recorder<P> {
+    start: Instant,
+    signals: Rc<RefCell<Vec<RecordedSignal<P>>>>,
+}
+
+impl<P> Clone for Recorder<P> {
+    fn clone(&self) -> Self {
+        Self {
+            start: self.start,
+            signals: Rc::clone(&self.signals),
+        }
+    }
+}
+
+impl<P> Recorder<P> {
+    /// start a recording, offsets are measured from now
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            signals: Rc::new(RefCell::new(vec![])),
+        }
+    }
+
+    /// record a signal sent to the given soma
+    pub fn record(&self, dest: Uuid, signal: P) {
+        let elapsed = self.start.elapsed();
+
+        self.signals.borrow_mut().push(RecordedSignal {
+            offset_ms: elapsed.as_secs() * 1000
+                + u64::from(elapsed.subsec_nanos() / 1_000_000),
+            dest: dest,
+            signal: signal,
+        });
+    }
+
+    /// write the log in the format that Soma reads
+    pub fn save<T: AsRef<Path>>(&self, path: T) -> Result<()>
+    where
+        P: Serialize,
+    {
+        let json = serde_json::to_vec(&*self.signals.borrow())?;
+
+        File::create(path)?.write_all(&json)?;
+
+        Ok(())
+    }
+}
+
+impl<P> Default for Recorder<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// replay settings
+#[derive(Debug, Clone)]
+pub struct Settings {
+    path: PathBuf,
+    speed: f64,
+}
+
+impl Settings {
+    /// replay the log at the given path
+    pub fn new<T: Into<PathBuf>>(path: T) -> Self {
+        Self {
+            path: path.into(),
+            speed: 1.0,
+        }
+    }
+
+    /// set the playback speed multiplier
+    ///
+    /// 2.0 plays the log back twice as fast as it was recorded.
+    pub fn speed(self, speed: f64) -> Self {
+        Self {
+            speed: speed,
+            ..self
+        }
+    }
+}
+
+type Emit<S, P> = Rc<
+    Fn(&<S as Synapse>::Terminal, P) -> Box<Future<Item = (), Error = Error>>,
+>;
+
+/// soma that replays a recorded log of signals into the organelle
+///
+/// the log is a JSON array of RecordedSignal, see Recorder. the soma should
+/// be connected to each destination in the log, and each signal is handed to
+/// `emit` along with the terminal for its destination. signals addressed to
+/// somas that are no longer connected are logged and skipped.
+pub struct Soma<S: Synapse, P> {
+    settings: Settings,
+    emit: Emit<S, P>,
+    terminals: HashMap<Uuid, S::Terminal>,
+}
+
+impl<S: Synapse, P> Soma<S, P> {
+    /// create a replay soma
+    pub fn new<F>(settings: Settings, emit: F) -> Self
+    where
+        F: Fn(&S::Terminal, P) -> Box<Future<Item = (), Error = Error>>
+            + 'static,
+    {
+        Self {
+            settings: settings,
+            emit: Rc::new(emit),
+            terminals: HashMap::new(),
+        }
+    }
+
+    fn load(&self) -> Result<Vec<RecordedSignal<P>>>
+    where
+        P: DeserializeOwned,
+    {
+        let mut json = String::new();
+
+        File::open(&self.settings.path)?.read_to_string(&mut json)?;
+
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+impl<S, P> soma::Soma for Soma<S, P>
+where
+    S: Synapse + 'static,
+    S::Terminal: 'static,
+    P: DeserializeOwned + 'static,
+{
+    type Synapse = S;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddTerminal(uuid, _, tx) => {
+                self.terminals.insert(uuid, tx);
+
+                Ok(self)
+            },
+
+            Impulse::Start(_, main_tx, handle) => {
+                let signals = self.load()?;
+
+                handle.spawn(
+                    ReplayTask::run::<S, P>(
+                        signals,
+                        self.settings.speed,
+                        self.terminals,
+                        Rc::clone(&self.emit),
+                        handle.clone(),
+                    ).or_else(move |e| {
+                        main_tx
+                            .send(Impulse::Error(e))
+                            .map(|_| ())
+                            .map_err(|_| ())
+                    }),
+                );
+
+                Ok(Self {
+                    settings: self.settings,
+                    emit: self.emit,
+                    terminals: HashMap::new(),
+                })
+            },
+
+            imp => Err(imp.unexpected(&self)),
+        }
+    }
+}
+
+struct ReplayTask;
+
+impl ReplayTask {
+    #[async]
+    fn run<S, P>(
+        signals: Vec<RecordedSignal<P>>,
+        speed: f64,
+        terminals: HashMap<Uuid, S::Terminal>,
+        emit: Emit<S, P>,
+        handle: reactor::Handle,
+    ) -> Result<()>
+    where
+        S: Synapse + 'static,
+        S::Terminal: 'static,
+        P: 'static,
+    {
+        let start = Instant::now();
+
+        for recorded in signals {
+            let offset = (recorded.offset_ms as f64 / speed) as u64;
+
+            await!(reactor::Timeout::new_at(
+                start + Duration::from_millis(offset),
+                &handle
+            )?)?;
+
+            let dest = recorded.dest;
+            let signal = recorded.signal;
+            let emitted = terminals.get(&dest).map(|tx| emit(tx, signal));
+
+            if let Some(emitted) = emitted {
+                await!(emitted)?;
+            } else {
+                eprintln!(
+                    "skipping recorded signal for missing soma {}",
+                    dest
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::fs;
+
+    use futures::future::Either;
+    use futures::unsync::mpsc;
+    use organelle::Organelle;
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    enum TestSynapse {
+        Value,
+    }
+
+    impl Synapse for TestSynapse {
+        type Terminal = mpsc::Sender<u32>;
+        type Dendrite = mpsc::Receiver<u32>;
+
+        fn synapse(self) -> (Self::Terminal, Self::Dendrite) {
+            mpsc::channel(10)
+        }
+    }
+
+    /// forwards every replayed value to the test
+    struct Sink {
+        rx: Option<mpsc::Receiver<u32>>,
+        values: mpsc::UnboundedSender<u32>,
+    }
+
+    impl soma::Soma for Sink {
+        type Synapse = TestSynapse;
+        type Error = Error;
+
+        #[async(boxed)]
+        fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+            match imp {
+                Impulse::AddDendrite(_, TestSynapse::Value, rx) => {
+                    self.rx = Some(rx);
+                },
+                Impulse::Start(_, _, handle) => {
+                    handle.spawn(
+                        self.rx
+                            .take()
+                            .unwrap()
+                            .forward(self.values.clone().sink_map_err(|_| ()))
+                            .map(|_| ()),
+                    );
+                },
+                imp => return Err(imp.unexpected(&self)),
+            }
+
+            Ok(self)
+        }
+    }
+
+    fn emit(
+        tx: &mpsc::Sender<u32>,
+        value: u32,
+    ) -> Box<Future<Item = (), Error = Error>> {
+        Box::new(
+            tx.clone()
+                .send(value)
+                .map(|_| ())
+                .map_err(|_| Error::from("unable to emit value")),
+        )
+    }
+
+    #[test]
+    fn test_replay() {
+        let mut core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let (tx, rx) = mpsc::unbounded();
+        let mut organelle = Organelle::new(
+            Sink {
+                rx: None,
+                values: tx,
+            },
+            handle.clone(),
+        );
+        let sink = organelle.nucleus();
+
+        let signal = |offset_ms, dest, signal| RecordedSignal {
+            offset_ms: offset_ms,
+            dest: dest,
+            signal: signal,
+        };
+        let log = vec![
+            signal(0, sink, 1),
+            // the soma this was recorded for is gone, so it gets skipped
+            signal(100, Uuid::new_v4(), 2),
+            signal(200, sink, 3),
+        ];
+
+        let path = env::temp_dir()
+            .join(format!("organelle-replay-test-{}.json", Uuid::new_v4()));
+
+        File::create(&path)
+            .unwrap()
+            .write_all(serde_json::to_string(&log).unwrap().as_bytes())
+            .unwrap();
+
+        let replay = organelle.add_soma(Soma::<TestSynapse, u32>::new(
+            Settings::new(path.clone()).speed(10.0),
+            emit,
+        ));
+        organelle.connect(replay, sink, TestSynapse::Value).unwrap();
+
+        handle.spawn(
+            organelle
+                .run(handle.clone())
+                .map_err(|e| panic!("organelle failed: {:#?}", e)),
+        );
+
+        let start = Instant::now();
+        let timeout =
+            reactor::Timeout::new(Duration::from_secs(5), &handle).unwrap();
+
+        let values = match core.run(rx.take(2).collect().select2(timeout)) {
+            Ok(Either::A((values, _))) => values,
+            _ => panic!("recorded signals were never replayed"),
+        };
+
+        assert_eq!(values, vec![1, 3]);
+
+        // the last signal was recorded 200ms in, which is 20ms at 10x speed
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_recorder() {
+        let recorder = Recorder::new();
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+
+        recorder.record(a, 1u32);
+        recorder.clone().record(b, 2);
+
+        let path = env::temp_dir()
+            .join(format!("organelle-record-test-{}.json", Uuid::new_v4()));
+
+        recorder.save(&path).unwrap();
+
+        // the replay soma reads back exactly what was recorded
+        let replay =
+            Soma::<TestSynapse, u32>::new(Settings::new(path.clone()), emit);
+        let signals = replay.load().unwrap();
+
+        assert_eq!(
+            signals
+                .iter()
+                .map(|s| (s.dest, s.signal))
+                .collect::<Vec<_>>(),
+            vec![(a, 1), (b, 2)]
+        );
+        assert!(signals[0].offset_ms <= signals[1].offset_ms);
+
+        fs::remove_file(path).unwrap();
+    }
+}
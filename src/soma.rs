@@ -1,7 +1,11 @@
 use std;
+use std::borrow::Cow;
+use std::cell::RefCell;
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::intrinsics;
+use std::rc::Rc;
+use std::time::Duration;
 
 use futures::prelude::*;
 use futures::unsync::{mpsc, oneshot};
@@ -9,7 +13,13 @@ use tokio_core::reactor;
 use uuid::Uuid;
 
 use super::{Error, Result};
-use probe::{self, SomaData, SynapseData};
+use axon::{Axon, Constraint};
+use map::Soma as MapSoma;
+use pause::Pausable;
+use probe::{self, SomaData, SomaMetrics, SynapseData};
+use source::Soma as SourceSoma;
+
+pub use synapse::{ask, AskDendrite, AskTerminal};
 
 /// trait alias to express requirements of a Synapse type
 pub trait Synapse: Debug + Copy + Clone + Hash + PartialEq + Eq {
@@ -42,16 +52,62 @@ pub enum Impulse<R: Synapse> {
     /// outputs. if your soma has outputs, it is best to wrap it with an Axon
     /// which can be used for validation purposes.
     AddTerminal(Uuid, R, R::Terminal),
+    /// remove the dendrite connected to the given soma
+    ///
+    /// the soma should drop its end of the synapse so that any tasks reading
+    /// from it can complete.
+    RemoveDendrite(Uuid, R),
+    /// remove the terminal connected to the given soma
+    ///
+    /// the soma should drop its end of the synapse so that any tasks writing
+    /// to it can complete.
+    RemoveTerminal(Uuid, R),
     /// notify the soma that it has received all of its inputs and outputs
     ///
     /// you should always expect to handle this impulse because it will be
     /// passed to each soma regardless of configuration
+    ///
+    /// the uuid is the soma's own, the same one its organelle reports for it
+    /// in probes and topology events, so it can be stored and included in
+    /// outgoing payloads. a soma run on its own with Soma::run is given a
+    /// fresh uuid instead.
     Start(Uuid, mpsc::Sender<Impulse<R>>, reactor::Handle),
     /// stop the event loop and exit gracefully
     ///
     /// you should not expect to handle this impulse at any time, it is handled
     /// for you by the event loop
     Stop,
+    /// stop the event loop once the impulses already queued are handled
+    ///
+    /// the event loop stops accepting new impulses, then handles the ones
+    /// that were already queued in the order they were sent before exiting
+    /// gracefully, giving the soma a chance to flush its own buffered work in
+    /// Soma::on_stop. impulses still waiting in a relay that hadn't reached
+    /// the event loop yet are dropped. use Stop to exit without handling them.
+    ///
+    /// an organelle drains its somas first, sending each one StopDraining
+    /// once every soma feeding its dendrites has stopped, so a soma can wait
+    /// in Soma::on_stop for the signals still in flight on its synapses.
+    ///
+    /// you should not expect to handle this impulse at any time, it is handled
+    /// for you by the event loop
+    StopDraining,
+    /// hold back impulses until Resume is received
+    ///
+    /// impulses sent in the meantime are buffered without a bound rather
+    /// than applying backpressure, since Resume arrives through the same
+    /// channel. they are handled in order once resumed. probes are still
+    /// handled while paused. organelles pass this on to each of their somas
+    /// to pause the whole subtree.
+    ///
+    /// you should not expect to handle this impulse unless the soma opts in
+    /// with Soma::handles_pause, it is handled for you by the event loop
+    Pause,
+    /// handle the impulses held back since Pause and continue as normal
+    ///
+    /// you should not expect to handle this impulse unless the soma opts in
+    /// with Soma::handles_pause, it is handled for you by the event loop
+    Resume,
     /// terminate the event loop with an error
     ///
     /// this impulse will automatically be triggered if a soma update resolves
@@ -64,10 +120,99 @@ pub enum Impulse<R: Synapse> {
     Probe(probe::Settings, oneshot::Sender<SomaData>),
 }
 
+/// the variant of an impulse without any of its data
+#[derive(Debug, Copy, Clone, Serialize, PartialEq, Eq)]
+pub enum ImpulseKind {
+    /// Impulse::AddDendrite
+    AddDendrite,
+    /// Impulse::AddTerminal
+    AddTerminal,
+    /// Impulse::RemoveDendrite
+    RemoveDendrite,
+    /// Impulse::RemoveTerminal
+    RemoveTerminal,
+    /// Impulse::Start
+    Start,
+    /// Impulse::Stop
+    Stop,
+    /// Impulse::StopDraining
+    StopDraining,
+    /// Impulse::Pause
+    Pause,
+    /// Impulse::Resume
+    Resume,
+    /// Impulse::Error
+    Error,
+    /// Impulse::Probe
+    Probe,
+}
+
+/// a lightweight record of an impulse received by a soma
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ImpulseRecord {
+    /// the variant of the impulse
+    pub kind: ImpulseKind,
+    /// the other soma involved in the impulse, if any
+    pub soma: Option<Uuid>,
+    /// the synapse involved in the impulse, if any
+    pub synapse: Option<String>,
+}
+
 impl<R> Impulse<R>
 where
     R: Synapse,
 {
+    /// get the variant of this impulse
+    pub fn kind(&self) -> ImpulseKind {
+        match self {
+            &Impulse::AddDendrite(_, _, _) => ImpulseKind::AddDendrite,
+            &Impulse::AddTerminal(_, _, _) => ImpulseKind::AddTerminal,
+            &Impulse::RemoveDendrite(_, _) => ImpulseKind::RemoveDendrite,
+            &Impulse::RemoveTerminal(_, _) => ImpulseKind::RemoveTerminal,
+            &Impulse::Start(_, _, _) => ImpulseKind::Start,
+            &Impulse::Stop => ImpulseKind::Stop,
+            &Impulse::StopDraining => ImpulseKind::StopDraining,
+            &Impulse::Pause => ImpulseKind::Pause,
+            &Impulse::Resume => ImpulseKind::Resume,
+            &Impulse::Error(_) => ImpulseKind::Error,
+            &Impulse::Probe(_, _) => ImpulseKind::Probe,
+        }
+    }
+
+    /// create an error for an impulse that the soma did not expect
+    ///
+    /// the error names the impulse and the soma by Soma::name, so the last
+    /// arm of an update can be `imp => Err(imp.unexpected(&self))`.
+    pub fn unexpected<S: Soma>(&self, soma: &S) -> Error {
+        Error::from(format!(
+            "unexpected {:?} impulse for soma {}",
+            self.kind(),
+            soma.name()
+        ))
+    }
+
+    /// create a record of this impulse
+    pub fn record(&self) -> ImpulseRecord {
+        let (soma, synapse) = match self {
+            &Impulse::AddDendrite(uuid, synapse, _)
+            | &Impulse::AddTerminal(uuid, synapse, _) => {
+                (Some(uuid), Some(format!("{:?}", synapse)))
+            },
+            &Impulse::RemoveDendrite(uuid, synapse)
+            | &Impulse::RemoveTerminal(uuid, synapse) => {
+                (Some(uuid), Some(format!("{:?}", synapse)))
+            },
+            &Impulse::Start(uuid, _, _) => (Some(uuid), None),
+            _ => (None, None),
+        };
+
+        ImpulseRecord {
+            kind: self.kind(),
+            soma: soma,
+            synapse: synapse,
+        }
+    }
+
     /// convert from another type of impulse
     pub fn convert_from<T>(imp: Impulse<T>) -> Self
     where
@@ -75,14 +220,37 @@ where
         T::Dendrite: Into<R::Dendrite>,
         T::Terminal: Into<R::Terminal>,
     {
-        match imp {
+        imp.map_synapse(|synapse| synapse.into())
+    }
+
+    /// convert into another type of impulse, mapping synapses with `f`
+    ///
+    /// this is the same as convert_from, but for synapses that can't be
+    /// converted with a blanket From impl.
+    pub fn map_synapse<T, F>(self, f: F) -> Impulse<T>
+    where
+        T: Synapse,
+        F: Fn(R) -> T,
+        R::Dendrite: Into<T::Dendrite>,
+        R::Terminal: Into<T::Terminal>,
+    {
+        match self {
             Impulse::AddDendrite(uuid, synapse, dendrite) => {
-                Impulse::AddDendrite(uuid, synapse.into(), dendrite.into())
+                Impulse::AddDendrite(uuid, f(synapse), dendrite.into())
             },
             Impulse::AddTerminal(uuid, synapse, terminal) => {
-                Impulse::AddTerminal(uuid, synapse.into(), terminal.into())
+                Impulse::AddTerminal(uuid, f(synapse), terminal.into())
+            },
+            Impulse::RemoveDendrite(uuid, synapse) => {
+                Impulse::RemoveDendrite(uuid, f(synapse))
+            },
+            Impulse::RemoveTerminal(uuid, synapse) => {
+                Impulse::RemoveTerminal(uuid, f(synapse))
             },
             Impulse::Stop => Impulse::Stop,
+            Impulse::StopDraining => Impulse::StopDraining,
+            Impulse::Pause => Impulse::Pause,
+            Impulse::Resume => Impulse::Resume,
             Impulse::Error(e) => Impulse::Error(e),
 
             Impulse::Start(_, _, _) => {
@@ -94,6 +262,9 @@ where
     }
 }
 
+/// the dendrite and terminal constraints of a soma, in that order
+pub type Constraints<S> = (Vec<Constraint<S>>, Vec<Constraint<S>>);
+
 /// a singular cell of functionality that can be ported between organelles
 ///
 /// you can think of a soma as a stream of impulses folded over a structure.
@@ -111,21 +282,139 @@ pub trait Soma: Sized {
     #[async(boxed)]
     fn probe(
         self,
-        _settings: probe::Settings,
+        settings: probe::Settings,
     ) -> std::result::Result<(Self, SomaData), Self::Error>
     where
         Self: 'static,
     {
+        let type_name = unsafe { intrinsics::type_name::<Self>().to_string() };
+        let name = self.name().into_owned();
+        let category = self.category().map(|c| c.into_owned());
+        let metrics = self.metrics();
+
         Ok((
             self,
             SomaData::Soma {
                 synapse: Self::Synapse::data(),
-                name: unsafe { intrinsics::type_name::<Self>().to_string() },
+                name: name,
+                type_name: if settings.reports_type_names() {
+                    Some(type_name)
+                } else {
+                    None
+                },
+                category: category,
+                metrics: metrics,
             },
         ))
     }
 
+    /// a human-readable name for this soma, reported in probes and errors
+    ///
+    /// defaults to the type name of the soma. when a soma in an organelle
+    /// fails, its error is chained with this name and its uuid.
+    fn name(&self) -> Cow<str> {
+        Cow::Borrowed(unsafe { intrinsics::type_name::<Self>() })
+    }
+
+    /// a tag for the kind of job this soma does, like "sensor" or "sink"
+    ///
+    /// this is reported in probes so that the visualizer can color and
+    /// group somas with the same category. somas have no category by
+    /// default.
+    fn category(&self) -> Option<Cow<str>> {
+        None
+    }
+
+    /// counters of the soma's own to report in probes
+    ///
+    /// organelles add the number of impulses handled, the time since the
+    /// last update, and the depth of the soma's mailbox on top of these.
+    fn metrics(&self) -> SomaMetrics {
+        SomaMetrics::new()
+    }
+
+    /// the dendrite and terminal constraints on this soma, if it has any
+    ///
+    /// organelles use these to reject invalid connections as soon as they
+    /// are made. somas wrapped with an Axon report the Axon's constraints.
+    fn constraints(&self) -> Option<Constraints<Self::Synapse>> {
+        None
+    }
+
+    /// whether the soma has finished its job and can be dropped
+    ///
+    /// this is checked after each update. once it returns true, the soma
+    /// receives no more impulses and its organelle removes it the next time
+    /// it handles an impulse, as though Organelle::remove_soma had been
+    /// called. the rest of the organelle keeps running. a soma run on its
+    /// own simply exits its event loop.
+    fn is_done(&self) -> bool {
+        false
+    }
+
+    /// whether Pause and Resume are passed on to update
+    ///
+    /// by default the event loop handles them, holding back impulses while
+    /// the soma is paused. somas that need to react to them can opt in
+    /// instead, in which case nothing is held back for them.
+    fn handles_pause(&self) -> bool {
+        false
+    }
+
+    /// whether RemoveDendrite and RemoveTerminal are passed on to update
+    ///
+    /// by default the event loop drops them, so somas that never expect a
+    /// connection to be torn down don't fail when one is. the soma keeps its
+    /// end of the synapse until it is dropped. somas that need to close their
+    /// end, so that the peer sees the synapse end, can opt in instead.
+    fn handles_removal(&self) -> bool {
+        false
+    }
+
+    /// how long an update may take before it is reported as slow
+    ///
+    /// organelles send TopologyEvent::SlowUpdate to their subscribers when an
+    /// update runs past this, but the update is never cut short. by default,
+    /// updates are not timed.
+    fn update_deadline(&self) -> Option<Duration> {
+        None
+    }
+
+    /// the buffer size of the channel the event loop in Soma::run reads from
+    ///
+    /// the soma is handed the sending end in Start. a soma that sends itself
+    /// a batch of impulses while handling Start should raise this, since it
+    /// can't drain the channel until Start is done. defaults to 1.
+    fn run_buffer(&self) -> usize {
+        1
+    }
+
+    /// clean up once the event loop exits gracefully
+    ///
+    /// this is the soma's last chance to flush any work it has buffered. it
+    /// is called after Stop, after StopDraining once the queued impulses
+    /// have been handled, once every sender has been dropped, and once
+    /// is_done returns true, but never after an error. if Stop is queued
+    /// behind StopDraining, the drain is cut short there and the impulses
+    /// after it are dropped, but on_stop is still called. somas in an
+    /// organelle are stopped once the organelle drops them, or when they are
+    /// sent StopDraining.
+    #[async(boxed)]
+    fn on_stop(self) -> std::result::Result<(), Self::Error>
+    where
+        Self: 'static,
+    {
+        Ok(())
+    }
+
     /// react to a single impulse
+    ///
+    /// updates never overlap. each one takes the soma by value and hands it
+    /// back, so the next impulse waits until the previous update resolves.
+    /// somas whose work is independent should spawn it onto the handle given
+    /// in Start rather than awaiting it here, or spread it across several
+    /// worker somas with a workqueue::Soma. raise Soma::run_buffer if
+    /// impulses pile up while an update is in progress.
     fn update(
         self,
         imp: Impulse<Self::Synapse>,
@@ -138,7 +427,8 @@ pub trait Soma: Sized {
         Self: 'static,
     {
         // it's important that tx live through this function
-        let (tx, rx) = mpsc::channel(1);
+        let (tx, rx) = mpsc::channel(self.run_buffer());
+        let mut rx = Pausable::new(rx, !self.handles_pause());
 
         let uuid = Uuid::new_v4();
 
@@ -148,16 +438,183 @@ pub trait Soma: Sized {
                 .map_err(|_| Error::from("unable to send start signal"))
         )?;
 
-        #[async]
-        for imp in rx.map_err(|_| -> Error { unreachable!() }) {
+        loop {
+            let (imp, next) = await!(rx.into_future())
+                .map_err(|_| -> Error { unreachable!() })?;
+            rx = next;
+
             match imp {
-                Impulse::Error(e) => bail!(e),
-                Impulse::Stop => break,
+                Some(Impulse::Error(e)) => bail!(e),
+                Some(Impulse::Stop) | None => break,
+                // refuse new impulses, but handle the ones already queued
+                Some(Impulse::StopDraining) => rx.get_mut().close(),
+
+                Some(imp) => {
+                    self = await!(self.update(imp)).map_err(|e| e.into())?;
 
-                _ => self = await!(self.update(imp)).map_err(|e| e.into())?,
+                    if self.is_done() {
+                        break;
+                    }
+                },
             }
         }
 
-        Ok(())
+        await!(self.on_stop()).map_err(|e| e.into())
+    }
+}
+
+/// read the impulses received by a RecordingSoma
+#[derive(Debug, Clone)]
+pub struct RecordHandle {
+    kinds: Rc<RefCell<Vec<ImpulseKind>>>,
+}
+
+impl RecordHandle {
+    /// get the variants of the impulses received so far, in order
+    pub fn kinds(&self) -> Vec<ImpulseKind> {
+        self.kinds.borrow().clone()
     }
 }
+
+/// soma that records each impulse passed to an inner soma, see recording
+pub struct RecordingSoma<S: Soma> {
+    soma: S,
+    kinds: Rc<RefCell<Vec<ImpulseKind>>>,
+}
+
+/// wrap a soma so that the impulses passed to its update can be checked
+///
+/// the wrapper behaves exactly like the inner soma. only impulses that reach
+/// update are recorded, so the ones handled by the event loop itself (Stop,
+/// StopDraining, and Pause and Resume unless the soma handles them) are not.
+pub fn recording<S: Soma>(soma: S) -> (RecordingSoma<S>, RecordHandle) {
+    let kinds = Rc::new(RefCell::new(vec![]));
+
+    (
+        RecordingSoma {
+            soma: soma,
+            kinds: Rc::clone(&kinds),
+        },
+        RecordHandle { kinds: kinds },
+    )
+}
+
+impl<S: Soma + 'static> Soma for RecordingSoma<S> {
+    type Synapse = S::Synapse;
+    type Error = S::Error;
+
+    #[async(boxed)]
+    fn probe(
+        self,
+        settings: probe::Settings,
+    ) -> std::result::Result<(Self, SomaData), Self::Error> {
+        let kinds = self.kinds;
+        let (soma, data) = await!(self.soma.probe(settings))?;
+
+        Ok((
+            Self {
+                soma: soma,
+                kinds: kinds,
+            },
+            data,
+        ))
+    }
+
+    fn name(&self) -> Cow<str> {
+        self.soma.name()
+    }
+
+    fn category(&self) -> Option<Cow<str>> {
+        self.soma.category()
+    }
+
+    fn metrics(&self) -> SomaMetrics {
+        self.soma.metrics()
+    }
+
+    fn constraints(&self) -> Option<Constraints<Self::Synapse>> {
+        self.soma.constraints()
+    }
+
+    fn is_done(&self) -> bool {
+        self.soma.is_done()
+    }
+
+    fn handles_pause(&self) -> bool {
+        self.soma.handles_pause()
+    }
+
+    fn handles_removal(&self) -> bool {
+        self.soma.handles_removal()
+    }
+
+    fn update_deadline(&self) -> Option<Duration> {
+        self.soma.update_deadline()
+    }
+
+    fn run_buffer(&self) -> usize {
+        self.soma.run_buffer()
+    }
+
+    #[async(boxed)]
+    fn on_stop(self) -> std::result::Result<(), Self::Error> {
+        await!(self.soma.on_stop())
+    }
+
+    #[async(boxed)]
+    fn update(
+        self,
+        imp: Impulse<Self::Synapse>,
+    ) -> std::result::Result<Self, Self::Error> {
+        self.kinds.borrow_mut().push(imp.kind());
+
+        let kinds = self.kinds;
+        let soma = await!(self.soma.update(imp))?;
+
+        Ok(Self {
+            soma: soma,
+            kinds: kinds,
+        })
+    }
+}
+
+/// create a soma that transforms each signal before passing it on
+///
+/// see map::Soma for how `receive`, `f`, and `emit` are used.
+pub fn map<S, P, Q, R, F, E>(
+    input: S,
+    output: S,
+    receive: R,
+    f: F,
+    emit: E,
+) -> Axon<MapSoma<S, P, Q>>
+where
+    S: Synapse + 'static,
+    S::Dendrite: 'static,
+    S::Terminal: 'static,
+    P: 'static,
+    Q: 'static,
+    R: Fn(S::Dendrite) -> Box<Stream<Item = P, Error = Error>> + 'static,
+    F: Fn(P) -> Q + 'static,
+    E: Fn(&S::Terminal, Q) -> Box<Future<Item = (), Error = Error>> + 'static,
+{
+    MapSoma::axon(input, output, receive, f, emit)
+}
+
+/// create a soma that bridges an external stream into the organelle
+///
+/// see source::Soma for how `emit` is used.
+pub fn from_stream<S, St, F>(
+    output: S,
+    stream: St,
+    emit: F,
+) -> Axon<SourceSoma<S, St>>
+where
+    S: Synapse + 'static,
+    S::Terminal: 'static,
+    St: Stream<Error = Error> + 'static,
+    F: Fn(&S::Terminal, St::Item) -> Box<Future<Item = (), Error = Error>>
+        + 'static,
+{
+    SourceSoma::axon(output, stream, emit)
+}
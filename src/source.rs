@@ -0,0 +1,106 @@
+use std::rc::Rc;
+
+use futures::prelude::*;
+use futures::unsync::oneshot;
+
+use super::{Error, Result};
+use axon::{Axon, Constraint};
+use soma::{self, Impulse, Synapse};
+
+type Emit<S, P> = Rc<
+    Fn(&<S as Synapse>::Terminal, P) -> Box<Future<Item = (), Error = Error>>,
+>;
+
+/// soma that bridges an external stream into the organelle
+///
+/// the stream is driven on the reactor once the soma starts, and each item is
+/// handed to `emit` along with the output terminal. an error from the stream
+/// or from `emit` fails the organelle. the stream is dropped as soon as the
+/// soma is, so it stops along with the organelle.
+pub struct Soma<S: Synapse, St: Stream> {
+    stream: Option<St>,
+    emit: Emit<S, St::Item>,
+    output: Option<S::Terminal>,
+    // dropped with the soma to stop the stream
+    stop: Option<oneshot::Sender<()>>,
+}
+
+impl<S, St> Soma<S, St>
+where
+    S: Synapse + 'static,
+    S::Terminal: 'static,
+    St: Stream<Error = Error> + 'static,
+{
+    /// create a source soma for the output synapse
+    pub fn axon<F>(output: S, stream: St, emit: F) -> Axon<Self>
+    where
+        F: Fn(&S::Terminal, St::Item) -> Box<Future<Item = (), Error = Error>>
+            + 'static,
+    {
+        Axon::new(
+            Self {
+                stream: Some(stream),
+                emit: Rc::new(emit),
+                output: None,
+                stop: None,
+            },
+            vec![],
+            vec![Constraint::One(output)],
+        )
+    }
+}
+
+impl<S, St> soma::Soma for Soma<S, St>
+where
+    S: Synapse + 'static,
+    S::Terminal: 'static,
+    St: Stream<Error = Error> + 'static,
+{
+    type Synapse = S;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddTerminal(_, _, tx) => {
+                self.output = Some(tx);
+
+                Ok(self)
+            },
+
+            Impulse::Start(_, main_tx, handle) => {
+                let stream = match self.stream.take() {
+                    Some(stream) => stream,
+                    None => bail!("source has already started"),
+                };
+                let output = match self.output.take() {
+                    Some(output) => output,
+                    None => bail!("source has no output"),
+                };
+
+                let emit = Rc::clone(&self.emit);
+                let (stop_tx, stop_rx) = oneshot::channel();
+
+                handle.spawn(
+                    stream
+                        .for_each(move |item| emit(&output, item))
+                        .or_else(move |e| {
+                            main_tx
+                                .send(Impulse::Error(e))
+                                .map(|_| ())
+                                .map_err(|_| ())
+                        })
+                        .select(stop_rx.then(|_| Ok(())))
+                        .map(|_| ())
+                        .map_err(|_| ()),
+                );
+
+                self.stop = Some(stop_tx);
+
+                Ok(self)
+            },
+
+            imp => Err(imp.unexpected(&self)),
+        }
+    }
+}
@@ -0,0 +1,435 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use futures::prelude::*;
+use futures::unsync::mpsc;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+
+use super::{Error, Result};
+use axon::{Axon, Constraint};
+use soma::{self, Impulse, Synapse};
+
+/// spill settings
+#[derive(Debug, Clone)]
+pub struct Settings {
+    threshold: usize,
+    dir: PathBuf,
+}
+
+impl Settings {
+    /// keep at most `threshold` signals in memory before spilling to disk
+    pub fn threshold(self, threshold: usize) -> Self {
+        Self {
+            threshold: threshold,
+            ..self
+        }
+    }
+
+    /// write spilled signals to a file in the given directory
+    pub fn dir<T: Into<PathBuf>>(self, dir: T) -> Self {
+        Self {
+            dir: dir.into(),
+            ..self
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            threshold: 1024,
+            dir: env::temp_dir(),
+        }
+    }
+}
+
+type Receive<S, P> = Rc<
+    Fn(<S as Synapse>::Dendrite) -> Box<Stream<Item = P, Error = Error>>,
+>;
+type Emit<S, P> = Rc<
+    Fn(&<S as Synapse>::Terminal, P) -> Box<Future<Item = (), Error = Error>>,
+>;
+
+/// soma that buffers signals between a fast producer and a slow consumer
+///
+/// signals are read from the input dendrite with `receive` and held in
+/// memory up to the threshold. beyond that, they are appended to a spill
+/// file and read back in order as the consumer catches up. each signal is
+/// handed to `emit` along with the output terminal. the spill file is
+/// removed once the soma stops or its task is dropped.
+pub struct Soma<S: Synapse, P> {
+    settings: Settings,
+    receive: Receive<S, P>,
+    emit: Emit<S, P>,
+    input: Option<S::Dendrite>,
+    output: Option<S::Terminal>,
+    buffer: Option<Rc<RefCell<Buffer<P>>>>,
+}
+
+impl<S, P> Soma<S, P>
+where
+    S: Synapse + 'static,
+    S::Dendrite: 'static,
+    S::Terminal: 'static,
+    P: Serialize + DeserializeOwned + 'static,
+{
+    /// create a spill soma between the input and output synapses
+    pub fn axon<R, F>(
+        settings: Settings,
+        input: S,
+        output: S,
+        receive: R,
+        emit: F,
+    ) -> Axon<Self>
+    where
+        R: Fn(S::Dendrite) -> Box<Stream<Item = P, Error = Error>> + 'static,
+        F: Fn(&S::Terminal, P) -> Box<Future<Item = (), Error = Error>>
+            + 'static,
+    {
+        Axon::new(
+            Self {
+                settings: settings,
+                receive: Rc::new(receive),
+                emit: Rc::new(emit),
+                input: None,
+                output: None,
+                buffer: None,
+            },
+            vec![Constraint::One(input)],
+            vec![Constraint::One(output)],
+        )
+    }
+}
+
+impl<S, P> soma::Soma for Soma<S, P>
+where
+    S: Synapse + 'static,
+    S::Dendrite: 'static,
+    S::Terminal: 'static,
+    P: Serialize + DeserializeOwned + 'static,
+{
+    type Synapse = S;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddDendrite(_, _, rx) => {
+                self.input = Some(rx);
+
+                Ok(self)
+            },
+            Impulse::AddTerminal(_, _, tx) => {
+                self.output = Some(tx);
+
+                Ok(self)
+            },
+
+            Impulse::Start(uuid, main_tx, handle) => {
+                let input = match self.input.take() {
+                    Some(input) => input,
+                    None => bail!("spill has no input"),
+                };
+                let output = match self.output.take() {
+                    Some(output) => output,
+                    None => bail!("spill has no output"),
+                };
+
+                let buffer = Rc::new(RefCell::new(Buffer::new(
+                    self.settings
+                        .dir
+                        .join(format!("organelle-spill-{}.jsonl", uuid)),
+                    self.settings.threshold,
+                )));
+
+                // one token per buffered signal
+                let (tx, rx) = mpsc::unbounded();
+
+                handle.spawn(
+                    SpillTask::fill((self.receive)(input), buffer.clone(), tx)
+                        .join(SpillTask::drain::<S, P>(
+                            rx,
+                            buffer.clone(),
+                            output,
+                            Rc::clone(&self.emit),
+                        ))
+                        .map(|_| ())
+                        .or_else(move |e| {
+                            main_tx
+                                .send(Impulse::Error(e))
+                                .map(|_| ())
+                                .map_err(|_| ())
+                        }),
+                );
+
+                self.buffer = Some(buffer);
+
+                Ok(self)
+            },
+
+            imp => Err(imp.unexpected(&self)),
+        }
+    }
+
+    #[async(boxed)]
+    fn on_stop(self) -> Result<()> {
+        if let Some(buffer) = self.buffer {
+            buffer.borrow_mut().clear()?;
+        }
+
+        Ok(())
+    }
+}
+
+struct SpillTask;
+
+impl SpillTask {
+    #[async]
+    fn fill<P>(
+        input: Box<Stream<Item = P, Error = Error>>,
+        buffer: Rc<RefCell<Buffer<P>>>,
+        tx: mpsc::UnboundedSender<()>,
+    ) -> Result<()>
+    where
+        P: Serialize + DeserializeOwned + 'static,
+    {
+        #[async]
+        for signal in input {
+            buffer.borrow_mut().push(signal)?;
+
+            if let Err(_) = tx.unbounded_send(()) {
+                // consumer is gone
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[async]
+    fn drain<S, P>(
+        rx: mpsc::UnboundedReceiver<()>,
+        buffer: Rc<RefCell<Buffer<P>>>,
+        output: S::Terminal,
+        emit: Emit<S, P>,
+    ) -> Result<()>
+    where
+        S: Synapse + 'static,
+        S::Terminal: 'static,
+        P: Serialize + DeserializeOwned + 'static,
+    {
+        #[async]
+        for _ in rx.map_err(|_| -> Error { unreachable!() }) {
+            let signal = buffer.borrow_mut().pop()?;
+
+            if let Some(signal) = signal {
+                let send = emit(&output, signal);
+
+                await!(send)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// signals held in memory up to the threshold, then in a spill file
+///
+/// the oldest signals are always in memory, so reading back from the front
+/// of the file preserves the order they were pushed in.
+struct Buffer<P> {
+    path: PathBuf,
+    threshold: usize,
+    memory: VecDeque<P>,
+    spill: Option<(File, BufReader<File>)>,
+    spilled: usize,
+}
+
+impl<P> Buffer<P> {
+    fn new(path: PathBuf, threshold: usize) -> Self {
+        Self {
+            path: path,
+            threshold: threshold,
+            memory: VecDeque::new(),
+            spill: None,
+            spilled: 0,
+        }
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        self.memory.clear();
+        self.spilled = 0;
+
+        if self.spill.take().is_some() {
+            fs::remove_file(&self.path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<P> Buffer<P>
+where
+    P: Serialize + DeserializeOwned,
+{
+    fn push(&mut self, signal: P) -> Result<()> {
+        if self.spilled == 0 && self.memory.len() < self.threshold {
+            self.memory.push_back(signal);
+
+            return Ok(());
+        }
+
+        if self.spill.is_none() {
+            let writer = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            let reader = BufReader::new(File::open(&self.path)?);
+
+            self.spill = Some((writer, reader));
+        }
+
+        if let Some((ref mut writer, _)) = self.spill {
+            serde_json::to_writer(&mut *writer, &signal)?;
+            writer.write_all(b"\n")?;
+        }
+
+        self.spilled += 1;
+
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Option<P>> {
+        if self.memory.is_empty() && self.spilled > 0 {
+            self.unspill()?;
+        }
+
+        let signal = self.memory.pop_front();
+
+        while self.spilled > 0 && self.memory.len() < self.threshold {
+            self.unspill()?;
+        }
+
+        Ok(signal)
+    }
+
+    fn unspill(&mut self) -> Result<()> {
+        let mut line = String::new();
+
+        if let Some((ref mut writer, ref mut reader)) = self.spill {
+            reader.read_line(&mut line)?;
+            self.spilled -= 1;
+
+            // start the file over once everything has been read back
+            if self.spilled == 0 {
+                writer.set_len(0)?;
+                reader.seek(SeekFrom::Start(0))?;
+            }
+        }
+
+        self.memory.push_back(serde_json::from_str(&line)?);
+
+        Ok(())
+    }
+}
+
+impl<P> Drop for Buffer<P> {
+    fn drop(&mut self) {
+        if let Err(e) = self.clear() {
+            eprintln!("unable to remove spill file - {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio_core::reactor;
+    use uuid::Uuid;
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    enum TestSynapse {
+        Nothing,
+    }
+
+    impl Synapse for TestSynapse {
+        type Terminal = ();
+        type Dendrite = ();
+
+        fn synapse(self) -> ((), ()) {
+            ((), ())
+        }
+    }
+
+    fn temp_path() -> PathBuf {
+        env::temp_dir()
+            .join(format!("organelle-spill-test-{}.jsonl", Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_spill_order() {
+        let path = temp_path();
+
+        let mut buffer = Buffer::<u32>::new(path.clone(), 2);
+
+        for i in 0..5 {
+            buffer.push(i).unwrap();
+        }
+
+        assert!(path.exists());
+        assert_eq!(buffer.pop().unwrap(), Some(0));
+
+        for i in 5..8 {
+            buffer.push(i).unwrap();
+        }
+
+        let mut popped = vec![];
+
+        while let Some(signal) = buffer.pop().unwrap() {
+            popped.push(signal);
+        }
+
+        assert_eq!(popped, (1..8).collect::<Vec<_>>());
+
+        drop(buffer);
+
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_stop_removes_spill_file() {
+        let mut core = reactor::Core::new().unwrap();
+
+        let path = temp_path();
+        let buffer = Rc::new(RefCell::new(Buffer::<u32>::new(path.clone(), 0)));
+
+        buffer.borrow_mut().push(1).unwrap();
+        assert!(path.exists());
+
+        let spill: Soma<TestSynapse, u32> = Soma {
+            settings: Settings::default(),
+            receive: Rc::new(|_| -> Box<Stream<Item = u32, Error = Error>> {
+                unreachable!()
+            }),
+            emit: Rc::new(|_: &(), _| -> Box<Future<Item = (), Error = Error>> {
+                unreachable!()
+            }),
+            input: None,
+            output: None,
+            buffer: Some(Rc::clone(&buffer)),
+        };
+
+        core.run(soma::Soma::on_stop(spill)).unwrap();
+
+        // the file is gone even though the buffer itself is still alive
+        assert!(!path.exists());
+    }
+}
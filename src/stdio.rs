@@ -0,0 +1,350 @@
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+use std::thread;
+
+use futures::prelude::*;
+use futures::sync;
+use futures::unsync::mpsc;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+
+use super::{Error, Result};
+use axon::{Axon, Constraint};
+use soma::{self, Impulse, Synapse};
+
+/// converts signals to and from single lines of bytes
+pub struct Codec<P> {
+    decode: Rc<Fn(&[u8]) -> Result<P>>,
+    encode: Rc<Fn(&P) -> Result<Vec<u8>>>,
+}
+
+impl<P> Codec<P> {
+    /// create a codec from a decoder and an encoder
+    ///
+    /// the encoder must not produce any newlines, since each signal is
+    /// written as a single line.
+    pub fn new<D, E>(decode: D, encode: E) -> Self
+    where
+        D: Fn(&[u8]) -> Result<P> + 'static,
+        E: Fn(&P) -> Result<Vec<u8>> + 'static,
+    {
+        Self {
+            decode: Rc::new(decode),
+            encode: Rc::new(encode),
+        }
+    }
+}
+
+impl<P> Codec<P>
+where
+    P: Serialize + DeserializeOwned,
+{
+    /// newline-delimited JSON
+    pub fn json() -> Self {
+        Self::new(
+            |line: &[u8]| Ok(serde_json::from_slice(line)?),
+            |signal: &P| Ok(serde_json::to_vec(signal)?),
+        )
+    }
+}
+
+impl<P> Clone for Codec<P> {
+    fn clone(&self) -> Self {
+        Self {
+            decode: Rc::clone(&self.decode),
+            encode: Rc::clone(&self.encode),
+        }
+    }
+}
+
+type Emit<S, P> = Rc<
+    Fn(&<S as Synapse>::Terminal, P) -> Box<Future<Item = (), Error = Error>>,
+>;
+type Receive<S, P> = Rc<
+    Fn(<S as Synapse>::Dendrite) -> Box<Stream<Item = P, Error = Error>>,
+>;
+
+/// soma that bridges signals over stdin and stdout
+///
+/// each line read from stdin is decoded into a signal and handed to `emit`
+/// along with the soma's terminal. signals from every dendrite, read with
+/// `receive`, are encoded and written to stdout one per line. blank and
+/// malformed lines are logged and skipped, a final line without a newline
+/// is still read, and the organelle is sent Stop once stdin is closed.
+pub struct Soma<S: Synapse, P> {
+    codec: Codec<P>,
+    emit: Emit<S, P>,
+    receive: Receive<S, P>,
+    terminal: Option<S::Terminal>,
+    dendrites: Vec<S::Dendrite>,
+}
+
+impl<S, P> Soma<S, P>
+where
+    S: Synapse + 'static,
+    S::Dendrite: 'static,
+    S::Terminal: 'static,
+    P: 'static,
+{
+    /// create a stdio soma
+    ///
+    /// signals from stdin are sent through the `output` synapse and any
+    /// number of `input` synapses can feed stdout.
+    pub fn axon<F, R>(
+        codec: Codec<P>,
+        input: S,
+        output: S,
+        emit: F,
+        receive: R,
+    ) -> Axon<Self>
+    where
+        F: Fn(&S::Terminal, P) -> Box<Future<Item = (), Error = Error>>
+            + 'static,
+        R: Fn(S::Dendrite) -> Box<Stream<Item = P, Error = Error>> + 'static,
+    {
+        Axon::new(
+            Self {
+                codec: codec,
+                emit: Rc::new(emit),
+                receive: Rc::new(receive),
+                terminal: None,
+                dendrites: vec![],
+            },
+            vec![Constraint::Variadic(input)],
+            vec![Constraint::One(output)],
+        )
+    }
+}
+
+impl<S, P> soma::Soma for Soma<S, P>
+where
+    S: Synapse + 'static,
+    S::Dendrite: 'static,
+    S::Terminal: 'static,
+    P: 'static,
+{
+    type Synapse = S;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddDendrite(_, _, rx) => {
+                self.dendrites.push(rx);
+
+                Ok(self)
+            },
+            Impulse::AddTerminal(_, _, tx) => {
+                self.terminal = Some(tx);
+
+                Ok(self)
+            },
+
+            Impulse::Start(_, main_tx, handle) => {
+                let terminal = match self.terminal {
+                    Some(terminal) => terminal,
+                    None => bail!("stdio has no terminal"),
+                };
+
+                for dendrite in self.dendrites {
+                    let main_tx = main_tx.clone();
+
+                    handle.spawn(
+                        StdioTask::write(
+                            self.codec.clone(),
+                            (self.receive)(dendrite),
+                        ).or_else(move |e| {
+                            main_tx
+                                .send(Impulse::Error(e))
+                                .map(|_| ())
+                                .map_err(|_| ())
+                        }),
+                    );
+                }
+
+                let error_tx = main_tx.clone();
+
+                handle.spawn(
+                    StdioTask::read::<S, P>(
+                        self.codec.clone(),
+                        terminal,
+                        Rc::clone(&self.emit),
+                        main_tx,
+                    ).or_else(move |e| {
+                        error_tx
+                            .send(Impulse::Error(e))
+                            .map(|_| ())
+                            .map_err(|_| ())
+                    }),
+                );
+
+                Ok(Self {
+                    codec: self.codec,
+                    emit: self.emit,
+                    receive: self.receive,
+                    terminal: None,
+                    dendrites: vec![],
+                })
+            },
+
+            imp => Err(imp.unexpected(&self)),
+        }
+    }
+}
+
+struct StdioTask;
+
+impl StdioTask {
+    #[async]
+    fn read<S, P>(
+        codec: Codec<P>,
+        terminal: S::Terminal,
+        emit: Emit<S, P>,
+        main_tx: mpsc::Sender<Impulse<S>>,
+    ) -> Result<()>
+    where
+        S: Synapse + 'static,
+        S::Terminal: 'static,
+        P: 'static,
+    {
+        let (tx, rx) = sync::mpsc::channel(16);
+
+        // stdin can only be read with blocking calls
+        thread::spawn(move || {
+            let stdin = io::stdin();
+
+            read_lines(stdin.lock(), tx);
+        });
+
+        #[async]
+        for line in rx.map_err(|_| -> Error { unreachable!() }) {
+            let signal = {
+                let trimmed = trim_newline(&line);
+
+                if trimmed.is_empty() {
+                    continue;
+                }
+
+                (codec.decode)(trimmed)
+            };
+
+            match signal {
+                Ok(signal) => {
+                    let emitted = emit(&terminal, signal);
+
+                    await!(emitted)?;
+                },
+                Err(e) => eprintln!("malformed signal on stdin - {}", e),
+            }
+        }
+
+        await!(
+            main_tx
+                .send(Impulse::Stop)
+                .map_err(|_| Error::from("unable to send stop impulse"))
+        )?;
+
+        Ok(())
+    }
+
+    #[async]
+    fn write<P: 'static>(
+        codec: Codec<P>,
+        signals: Box<Stream<Item = P, Error = Error>>,
+    ) -> Result<()> {
+        #[async]
+        for signal in signals {
+            let stdout = io::stdout();
+
+            write_line(&mut stdout.lock(), &codec, &signal)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// send each line to the channel until the reader or the channel is closed
+fn read_lines<R: BufRead>(
+    mut reader: R,
+    mut tx: sync::mpsc::Sender<Vec<u8>>,
+) {
+    loop {
+        let mut line = vec![];
+
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            Ok(_) => match tx.send(line).wait() {
+                Ok(sender) => tx = sender,
+                Err(_) => break,
+            },
+            Err(e) => {
+                eprintln!("unable to read from stdin - {}", e);
+                break;
+            },
+        }
+    }
+}
+
+/// encode the signal and write it as a single line
+fn write_line<W, P>(writer: &mut W, codec: &Codec<P>, signal: &P) -> Result<()>
+where
+    W: Write,
+{
+    let mut line = (codec.encode)(signal)?;
+    line.push(b'\n');
+
+    writer.write_all(&line)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn trim_newline(line: &[u8]) -> &[u8] {
+    let line = if line.ends_with(b"\n") {
+        &line[..line.len() - 1]
+    } else {
+        line
+    };
+
+    if line.ends_with(b"\r") {
+        &line[..line.len() - 1]
+    } else {
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_lines() {
+        let (tx, rx) = sync::mpsc::channel(16);
+
+        // the last line is still read even though it has no newline
+        read_lines(Cursor::new(&b"1\n\n2\r\n3"[..]), tx);
+
+        let codec = Codec::<u32>::json();
+        let signals: Vec<_> = rx.wait()
+            .map(|line| line.unwrap())
+            .filter(|line| !trim_newline(line).is_empty())
+            .map(|line| (codec.decode)(trim_newline(&line)).unwrap())
+            .collect();
+
+        assert_eq!(signals, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_write_line() {
+        let mut out = vec![];
+        let codec = Codec::<Vec<u32>>::json();
+
+        write_line(&mut out, &codec, &vec![1, 2]).unwrap();
+        write_line(&mut out, &codec, &vec![3]).unwrap();
+
+        assert_eq!(out, b"[1,2]\n[3]\n".to_vec());
+    }
+}
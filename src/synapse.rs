@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+
+use futures::prelude::*;
+use futures::unsync::{mpsc, oneshot};
+
+use super::{Error, Result};
+
+/// a payload tagged with its sequence number
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Packet<T> {
+    /// position of the payload within the session
+    pub seq: u64,
+    /// the payload being delivered
+    pub payload: T,
+}
+
+/// sending half of an exactly-once session
+///
+/// every payload is tagged with a sequence number and held until the
+/// receiver acknowledges it. the transport is up to you: send the packets
+/// returned by `send` through a terminal, feed the acks coming back from the
+/// receiver into `ack`, and periodically (for example on a reactor interval)
+/// resend whatever `retransmit` returns. packets and acks can be lost or
+/// duplicated in transit without losing or duplicating payloads on the
+/// receiving end.
+#[derive(Debug)]
+pub struct Reliable<T> {
+    next_seq: u64,
+    unacked: BTreeMap<u64, T>,
+}
+
+impl<T: Clone> Reliable<T> {
+    /// start a new session
+    pub fn new() -> Self {
+        Self {
+            next_seq: 0,
+            unacked: BTreeMap::new(),
+        }
+    }
+
+    /// tag a payload for delivery and hold it until it is acknowledged
+    pub fn send(&mut self, payload: T) -> Packet<T> {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+
+        self.unacked.insert(seq, payload.clone());
+
+        Packet {
+            seq: seq,
+            payload: payload,
+        }
+    }
+
+    /// mark the packet with the given sequence number as delivered
+    pub fn ack(&mut self, seq: u64) {
+        self.unacked.remove(&seq);
+    }
+
+    /// get every packet that has not been acknowledged yet, in order
+    pub fn retransmit(&self) -> Vec<Packet<T>> {
+        self.unacked
+            .iter()
+            .map(|(seq, payload)| Packet {
+                seq: *seq,
+                payload: payload.clone(),
+            })
+            .collect()
+    }
+
+    /// check if every packet has been acknowledged
+    pub fn is_settled(&self) -> bool {
+        self.unacked.is_empty()
+    }
+}
+
+/// receiving half of an exactly-once session
+///
+/// payloads are released in sequence order and each one is released exactly
+/// once, no matter how many times its packet arrives.
+#[derive(Debug)]
+pub struct ReliableReceiver<T> {
+    next_seq: u64,
+    pending: BTreeMap<u64, T>,
+}
+
+impl<T> ReliableReceiver<T> {
+    /// start a new session
+    pub fn new() -> Self {
+        Self {
+            next_seq: 0,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// receive a packet
+    ///
+    /// returns the sequence number to acknowledge along with any payloads
+    /// that can now be delivered. the ack should always be sent, even for
+    /// duplicates, since the original ack may have been lost.
+    pub fn receive(&mut self, packet: Packet<T>) -> (u64, Vec<T>) {
+        let seq = packet.seq;
+
+        if seq >= self.next_seq {
+            self.pending.entry(seq).or_insert(packet.payload);
+        }
+
+        let mut delivered = vec![];
+
+        while let Some(payload) = self.pending.remove(&self.next_seq) {
+            delivered.push(payload);
+            self.next_seq += 1;
+        }
+
+        (seq, delivered)
+    }
+}
+
+type Request<Req, Resp> = (Req, oneshot::Sender<Resp>);
+
+/// sending half of a request/response junction, see ask
+#[derive(Debug)]
+pub struct AskTerminal<Req, Resp> {
+    tx: mpsc::Sender<Request<Req, Resp>>,
+}
+
+impl<Req, Resp> Clone for AskTerminal<Req, Resp> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl<Req: 'static, Resp: 'static> AskTerminal<Req, Resp> {
+    /// send a request and wait for the response
+    #[async]
+    pub fn ask(self, req: Req) -> Result<Resp> {
+        let (tx, rx) = oneshot::channel();
+
+        await!(
+            self.tx
+                .send((req, tx))
+                .map(|_| ())
+                .map_err(|_| Error::from("unable to send request"))
+        )?;
+
+        await!(rx.map_err(|_| Error::from("unable to receive response")))
+    }
+}
+
+/// receiving half of a request/response junction, see ask
+///
+/// each request is paired with the sender for its response. dropping the
+/// sender without responding fails the ask.
+#[derive(Debug)]
+pub struct AskDendrite<Req, Resp> {
+    rx: mpsc::Receiver<Request<Req, Resp>>,
+}
+
+impl<Req, Resp> Stream for AskDendrite<Req, Resp> {
+    type Item = Request<Req, Resp>;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, ()> {
+        self.rx.poll()
+    }
+}
+
+/// create a junction for sending requests and receiving their responses
+///
+/// this can serve as the terminal and dendrite of a synapse, so any soma can
+/// expose a request/response endpoint. probe::Synapse is built on it.
+pub fn ask<Req, Resp>() -> (AskTerminal<Req, Resp>, AskDendrite<Req, Resp>) {
+    let (tx, rx) = mpsc::channel(10);
+
+    (AskTerminal { tx: tx }, AskDendrite { rx: rx })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio_core::reactor;
+
+    #[test]
+    fn test_dropped_acks() {
+        let mut tx = Reliable::new();
+        let mut rx = ReliableReceiver::new();
+
+        let mut received = vec![];
+        let mut in_flight: Vec<Packet<u32>> =
+            (0..10).map(|i| tx.send(i)).collect();
+
+        let mut round = 0;
+
+        while !tx.is_settled() {
+            for packet in in_flight {
+                let (ack, mut delivered) = rx.receive(packet);
+
+                received.append(&mut delivered);
+
+                // lose every other ack on the first couple of rounds
+                if round >= 2 || ack % 2 == 0 {
+                    tx.ack(ack);
+                }
+            }
+
+            in_flight = tx.retransmit();
+            round += 1;
+
+            assert!(round < 10, "session never settled");
+        }
+
+        assert_eq!(received, (0..10).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn test_out_of_order() {
+        let mut tx = Reliable::new();
+        let mut rx = ReliableReceiver::new();
+
+        let first = tx.send("first");
+        let second = tx.send("second");
+
+        assert_eq!(rx.receive(second.clone()), (1, vec![]));
+        assert_eq!(rx.receive(second), (1, vec![]));
+        assert_eq!(rx.receive(first), (0, vec!["first", "second"]));
+    }
+
+    #[test]
+    fn test_ask() {
+        let mut core = reactor::Core::new().unwrap();
+
+        let (tx, rx) = ask::<u32, String>();
+
+        core.handle().spawn(rx.for_each(|(req, tx)| {
+            tx.send(format!("{}", req * 2)).map_err(|_| ())
+        }));
+
+        let resp = core.run(tx.clone().ask(21)).unwrap();
+
+        assert_eq!(resp, "42");
+
+        // responses fail once nobody is listening
+        let (tx, rx) = ask::<u32, String>();
+
+        drop(rx);
+
+        assert!(core.run(tx.ask(21)).is_err());
+    }
+}
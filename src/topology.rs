@@ -0,0 +1,430 @@
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+use serde_yaml;
+use uuid::Uuid;
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::Marker;
+
+use super::{ErrorKind, Result};
+use organelle::Organelle;
+use soma::{Soma, Synapse};
+
+/// name used in a topology document to refer to the organelle's nucleus
+pub const NUCLEUS: &str = "nucleus";
+
+/// a soma declared in a topology document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SomaDef {
+    /// name used to refer to the soma within the document
+    pub name: String,
+    /// the registered type of the soma
+    #[serde(rename = "type")]
+    pub soma_type: String,
+    /// configuration passed to the soma's factory
+    #[serde(default)]
+    pub config: serde_yaml::Value,
+}
+
+/// a connection declared in a topology document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionDef<S> {
+    /// the soma sending through the synapse
+    pub from: String,
+    /// the soma receiving from the synapse
+    pub to: String,
+    /// the synapse to connect with
+    pub synapse: S,
+}
+
+/// an organelle's somas and connections
+///
+/// ```yaml
+/// somas:
+///   - name: counter
+///     type: counter
+///     config:
+///       limit: 5
+/// connections:
+///   - from: nucleus
+///     to: counter
+///     synapse: Increment
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Topology<S> {
+    /// somas to add to the organelle
+    #[serde(default)]
+    pub somas: Vec<SomaDef>,
+    /// connections to make between the somas
+    #[serde(default)]
+    pub connections: Vec<ConnectionDef<S>>,
+}
+
+impl<S> Topology<S>
+where
+    S: Synapse + DeserializeOwned,
+{
+    /// parse a topology from a YAML document
+    pub fn from_yaml(yaml: &str) -> Result<Self> {
+        Ok(serde_yaml::from_str(yaml)?)
+    }
+}
+
+type Factory<T> =
+    Box<Fn(&mut Organelle<T>, serde_yaml::Value) -> Result<Uuid>>;
+
+/// soma factories that can be referred to by name in a topology document
+pub struct Registry<T: Soma + 'static> {
+    factories: HashMap<String, Factory<T>>,
+}
+
+impl<T: Soma + 'static> Registry<T> {
+    /// create an empty registry
+    pub fn new() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// register a factory that creates a soma from its config
+    pub fn register<U, F>(&mut self, soma_type: &str, factory: F)
+    where
+        U: Soma + 'static,
+        U::Synapse: From<T::Synapse> + Into<T::Synapse>,
+        <U::Synapse as Synapse>::Dendrite: From<<T::Synapse as Synapse>::Dendrite>
+            + Into<<T::Synapse as Synapse>::Dendrite>,
+        <U::Synapse as Synapse>::Terminal: From<<T::Synapse as Synapse>::Terminal>
+            + Into<<T::Synapse as Synapse>::Terminal>,
+        F: Fn(serde_yaml::Value) -> Result<U> + 'static,
+    {
+        self.factories.insert(
+            soma_type.to_string(),
+            Box::new(move |organelle, config| {
+                Ok(organelle.add_soma(factory(config)?))
+            }),
+        );
+    }
+
+    /// parse a YAML document and build its somas and connections into the
+    /// organelle
+    ///
+    /// the organelle's nucleus can be referred to as `nucleus`. returns the
+    /// uuid of each soma by its name in the document.
+    pub fn build(
+        &self,
+        organelle: &mut Organelle<T>,
+        yaml: &str,
+    ) -> Result<HashMap<String, Uuid>>
+    where
+        T::Synapse: DeserializeOwned,
+    {
+        let topology = Topology::<T::Synapse>::from_yaml(yaml)?;
+        let spans = Spans::parse(yaml);
+
+        let mut somas = HashMap::new();
+        somas.insert(NUCLEUS.to_string(), organelle.nucleus());
+
+        // validate everything before touching the organelle
+        for (i, def) in topology.somas.iter().enumerate() {
+            if !self.factories.contains_key(&def.soma_type) {
+                bail!(ErrorKind::InvalidTopology(format!(
+                    "{}unknown soma type {:?} for somas[{}]",
+                    spans.prefix(&format!("somas[{}].type", i)),
+                    def.soma_type,
+                    i
+                )))
+            }
+
+            if def.name == NUCLEUS || somas.contains_key(&def.name) {
+                bail!(ErrorKind::InvalidTopology(format!(
+                    "{}duplicate soma name {:?} for somas[{}]",
+                    spans.prefix(&format!("somas[{}].name", i)),
+                    def.name,
+                    i
+                )))
+            }
+
+            // placeholder until the soma is actually created
+            somas.insert(def.name.clone(), Uuid::nil());
+        }
+
+        for (i, def) in topology.connections.iter().enumerate() {
+            for &(field, name) in &[("from", &def.from), ("to", &def.to)] {
+                if !somas.contains_key(name) {
+                    bail!(ErrorKind::InvalidTopology(format!(
+                        "{}unknown soma {:?} in connections[{}]",
+                        spans.prefix(&format!("connections[{}].{}", i, field)),
+                        name,
+                        i
+                    )))
+                }
+            }
+        }
+
+        for def in topology.somas {
+            let factory = &self.factories[&def.soma_type];
+            let uuid = factory(organelle, def.config)?;
+
+            somas.insert(def.name, uuid);
+        }
+
+        for (i, def) in topology.connections.into_iter().enumerate() {
+            let (from, to) = (somas[&def.from], somas[&def.to]);
+
+            if let Err(e) = organelle.connect(from, to, def.synapse) {
+                bail!(ErrorKind::InvalidTopology(format!(
+                    "{}unable to make connections[{}] ({} -> {}) - {}",
+                    spans.prefix(&format!("connections[{}]", i)),
+                    i,
+                    def.from,
+                    def.to,
+                    e
+                )))
+            }
+        }
+
+        Ok(somas)
+    }
+}
+
+/// the line each node of a topology document starts on
+///
+/// nodes are keyed by their path in the document, like `somas[1].type`.
+/// the document is parsed a second time to find them, since serde_yaml
+/// doesn't keep track of where values came from.
+#[derive(Debug, Default)]
+struct Spans {
+    lines: HashMap<String, usize>,
+    stack: Vec<Node>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Mapping(Option<String>),
+    Sequence(usize),
+}
+
+impl Spans {
+    fn parse(yaml: &str) -> Self {
+        let mut spans = Self::default();
+
+        // anything that fails to parse has already been rejected by serde
+        if let Err(_) = Parser::new(yaml.chars()).load(&mut spans, false) {
+            spans.lines.clear();
+        }
+
+        spans
+    }
+
+    /// the line a node starts on, if it was found
+    fn line(&self, path: &str) -> Option<usize> {
+        self.lines.get(path).cloned()
+    }
+
+    /// prefix for an error message about a node
+    fn prefix(&self, path: &str) -> String {
+        self.line(path)
+            .map(|line| format!("line {}: ", line))
+            .unwrap_or_default()
+    }
+
+    fn path(&self) -> String {
+        let mut path = String::new();
+
+        for node in &self.stack {
+            match node {
+                &Node::Mapping(Some(ref key)) => {
+                    if !path.is_empty() {
+                        path.push('.');
+                    }
+
+                    path.push_str(key);
+                },
+                &Node::Mapping(None) => (),
+                &Node::Sequence(index) => {
+                    path.push_str(&format!("[{}]", index))
+                },
+            }
+        }
+
+        path
+    }
+
+    fn record(&mut self, mark: Marker) {
+        let path = self.path();
+
+        if !path.is_empty() {
+            self.lines.insert(path, mark.line());
+        }
+    }
+
+    /// move on to the next key or item once a value is done
+    fn next(&mut self) {
+        match self.stack.last_mut() {
+            Some(&mut Node::Mapping(ref mut key)) => *key = None,
+            Some(&mut Node::Sequence(ref mut index)) => *index += 1,
+            None => (),
+        }
+    }
+}
+
+impl MarkedEventReceiver for Spans {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::MappingStart(_) => {
+                self.record(mark);
+                self.stack.push(Node::Mapping(None));
+            },
+            Event::SequenceStart(_) => {
+                self.record(mark);
+                self.stack.push(Node::Sequence(0));
+            },
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+                self.next();
+            },
+            Event::Scalar(value, _, _, _) => {
+                let is_key = match self.stack.last() {
+                    Some(&Node::Mapping(None)) => true,
+                    _ => false,
+                };
+
+                if is_key {
+                    if let Some(&mut Node::Mapping(ref mut key)) =
+                        self.stack.last_mut()
+                    {
+                        *key = Some(value);
+                    }
+                } else {
+                    self.record(mark);
+                    self.next();
+                }
+            },
+            Event::Alias(_) => {
+                self.record(mark);
+                self.next();
+            },
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::prelude::*;
+    use tokio_core::reactor;
+
+    use Error;
+    use soma::Impulse;
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    enum TestSynapse {
+        Nothing,
+    }
+
+    impl Synapse for TestSynapse {
+        type Terminal = ();
+        type Dendrite = ();
+
+        fn synapse(self) -> ((), ()) {
+            ((), ())
+        }
+    }
+
+    struct IdleSoma;
+
+    impl Soma for IdleSoma {
+        type Synapse = TestSynapse;
+        type Error = Error;
+
+        #[async(boxed)]
+        fn update(self, _imp: Impulse<Self::Synapse>) -> Result<Self> {
+            Ok(self)
+        }
+    }
+
+    const YAML: &str = "somas:
+  - name: a
+    type: idle
+  - name: b
+    type: missing
+connections:
+  - from: a
+    to: c
+    synapse: Nothing
+";
+
+    fn build_error(yaml: &str) -> String {
+        let core = reactor::Core::new().unwrap();
+        let mut organelle = Organelle::new(IdleSoma, core.handle());
+
+        let mut registry = Registry::new();
+        registry.register("idle", |_| Ok(IdleSoma));
+
+        match registry.build(&mut organelle, yaml) {
+            Err(Error(ErrorKind::InvalidTopology(msg), _)) => msg,
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("invalid topology was built"),
+        }
+    }
+
+    #[test]
+    fn test_spans() {
+        let spans = Spans::parse(YAML);
+
+        assert_eq!(spans.line("somas[1]"), Some(4));
+        assert_eq!(spans.line("somas[1].name"), Some(4));
+        assert_eq!(spans.line("somas[1].type"), Some(5));
+        assert_eq!(spans.line("connections[0]"), Some(7));
+        assert_eq!(spans.line("connections[0].to"), Some(8));
+        assert_eq!(spans.line("connections[1]"), None);
+    }
+
+    #[test]
+    fn test_error_lines() {
+        assert_eq!(
+            build_error(YAML),
+            "line 5: unknown soma type \"missing\" for somas[1]"
+        );
+        assert_eq!(
+            build_error(&YAML.replace("missing", "idle")),
+            "line 8: unknown soma \"c\" in connections[0]"
+        );
+        assert_eq!(
+            build_error(&YAML.replace("name: b", "name: a")),
+            "line 4: duplicate soma name \"a\" for somas[1]"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Topology::<TestSynapse>::from_yaml("somas: [").is_err());
+        // every soma needs a type
+        assert!(
+            Topology::<TestSynapse>::from_yaml("somas:\n  - name: a\n")
+                .is_err()
+        );
+        assert!(
+            Topology::<TestSynapse>::from_yaml(
+                "connections:\n  - from: a\n    to: b\n    synapse: Unknown\n"
+            ).is_err()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let topology = Topology::<TestSynapse>::from_yaml(YAML).unwrap();
+        let yaml = serde_yaml::to_string(&topology).unwrap();
+        let parsed = Topology::<TestSynapse>::from_yaml(&yaml).unwrap();
+
+        assert_eq!(serde_yaml::to_string(&parsed).unwrap(), yaml);
+
+        let names: Vec<_> = parsed.somas.iter().map(|s| &s.name[..]).collect();
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(parsed.somas[1].soma_type, "missing");
+        assert_eq!(parsed.connections[0].to, "c");
+        assert_eq!(parsed.connections[0].synapse, TestSynapse::Nothing);
+    }
+}
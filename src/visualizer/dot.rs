@@ -236,6 +236,11 @@ impl Edge {
         }
     }
 
+    /// turn the edge into a statement with attributes such as a label
+    pub fn with_attrs(self, attrs: Vec<Attribute>) -> Statement {
+        Statement::Edge(self, attrs)
+    }
+
     fn write(&self, writer: &mut Write) -> io::Result<()> {
         match self {
             &Edge::Node(ref node_id) => node_id.write(writer),
@@ -379,8 +384,8 @@ impl Dot {
 pub enum Statement {
     /// declare a standalone node
     Node(Node),
-    /// declare an edge or set of edges
-    Edge(Edge),
+    /// declare an edge or set of edges with optional attributes
+    Edge(Edge, Vec<Attribute>),
     /// apply attributes to a set of nodes/edges
     Selector(Selector),
     /// an attribute statement
@@ -397,7 +402,7 @@ impl From<Node> for Statement {
 
 impl From<Edge> for Statement {
     fn from(edge: Edge) -> Self {
-        Statement::Edge(edge)
+        Statement::Edge(edge, vec![])
     }
 }
 
@@ -429,9 +434,21 @@ impl Statement {
 
                 write!(writer, ";\n")
             },
-            &Statement::Edge(ref edge) => {
+            &Statement::Edge(ref edge, ref attrs) => {
                 write_indents(writer, indents)?;
                 edge.write(writer)?;
+
+                if attrs.len() >= 1 {
+                    write!(writer, " [")?;
+
+                    for attr in attrs {
+                        attr.write(writer)?;
+                        write!(writer, ",")?;
+                    }
+
+                    write!(writer, "]")?;
+                }
+
                 write!(writer, ";\n")
             },
             &Statement::Selector(ref selector) => {
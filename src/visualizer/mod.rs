@@ -1,12 +1,19 @@
 #[allow(dead_code)]
 mod dot;
 
+use std;
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+use std::time::Duration;
 
 use bytes::BufMut;
 use futures::future;
 use futures::prelude::*;
+use futures::sync::mpsc::SendError;
 use hyper;
 use hyper::server::{Http, Service};
 use open;
@@ -17,14 +24,23 @@ use uuid::Uuid;
 use super::{Error, Result};
 use axon::{Axon, Constraint};
 use organelle::Organelle;
-use probe::{self, ConstraintData, SomaData, Synapse, Terminal};
+use probe::{
+    self, ConnectionMeta, ConstraintData, ConstraintStatus, SomaData,
+    SomaMetrics, Synapse, Terminal,
+};
 use soma::{self, Impulse};
+use watch;
 
 /// visualizer settings
 #[derive(Debug, Clone)]
 pub struct Settings {
     open_on_start: bool,
     port: u16,
+    port_retries: u16,
+    bind: IpAddr,
+    allow_origin: Option<String>,
+    refresh_interval: Duration,
+    address: Option<Rc<watch::Publisher<Option<SocketAddr>>>>,
 }
 
 impl Settings {
@@ -40,6 +56,58 @@ impl Settings {
     pub fn port(self, port: u16) -> Self {
         Self { port: port, ..self }
     }
+
+    /// try up to this many of the following ports if the port is taken
+    ///
+    /// this is 0 by default, so the visualizer fails with an error naming
+    /// the port when it is already in use.
+    pub fn port_retries(self, retries: u16) -> Self {
+        Self {
+            port_retries: retries,
+            ..self
+        }
+    }
+
+    /// set the address that the visualizer listens on
+    ///
+    /// this is 127.0.0.1 by default, so the visualizer can only be reached
+    /// from the same machine. bind to 0.0.0.0 to reach it from elsewhere.
+    pub fn bind(self, addr: IpAddr) -> Self {
+        Self { bind: addr, ..self }
+    }
+
+    /// set the origin that browsers allow to call the api from other sites
+    ///
+    /// this is `*` in debug builds and off in release builds. set it to let
+    /// a front-end hosted somewhere else consume the probe api.
+    pub fn allow_origin(self, origin: Option<String>) -> Self {
+        Self {
+            allow_origin: origin,
+            ..self
+        }
+    }
+
+    /// set how often the ui polls when live updates are unavailable
+    pub fn refresh_interval(self, interval: Duration) -> Self {
+        Self {
+            refresh_interval: interval,
+            ..self
+        }
+    }
+
+    /// publish the address the visualizer is listening on once it is bound
+    ///
+    /// this is useful with port 0, where the os picks a free port, or to
+    /// wait until the visualizer is ready to accept requests.
+    pub fn publish_address(
+        self,
+        publisher: watch::Publisher<Option<SocketAddr>>,
+    ) -> Self {
+        Self {
+            address: Some(Rc::new(publisher)),
+            ..self
+        }
+    }
 }
 
 impl Default for Settings {
@@ -47,6 +115,15 @@ impl Default for Settings {
         Self {
             open_on_start: false,
             port: 8080,
+            port_retries: 0,
+            bind: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            allow_origin: if cfg!(debug_assertions) {
+                Some("*".into())
+            } else {
+                None
+            },
+            refresh_interval: Duration::from_secs(1),
+            address: None,
         }
     }
 }
@@ -70,6 +147,21 @@ impl Soma {
         ))
     }
 
+    /// create a visualizer that does not require a probe soma
+    ///
+    /// if no probe is connected, the API endpoints respond with a "probe not
+    /// connected" error instead of probe data.
+    pub fn standalone(settings: Settings) -> Result<Axon<Self>> {
+        Ok(Axon::new(
+            Self {
+                settings: settings,
+                probe: None,
+            },
+            vec![],
+            vec![Constraint::Variadic(Synapse::Probe)],
+        ))
+    }
+
     /// create a standalone organelle to plug into any system
     pub fn organelle(
         settings: Settings,
@@ -102,7 +194,7 @@ impl soma::Soma for Soma {
                 handle.spawn(
                     VisualizerTask::new(
                         self.settings.clone(),
-                        self.probe.unwrap(),
+                        self.probe,
                         handle.clone(),
                     ).run()
                         .or_else(move |e| {
@@ -119,28 +211,36 @@ impl soma::Soma for Soma {
                 })
             },
 
-            _ => bail!("unexpected impulse {:?}", imp),
+            imp => Err(imp.unexpected(&self)),
         }
     }
 }
 
 struct VisualizerTask {
-    probe: Terminal,
-    port: u16,
+    probe: Option<Terminal>,
+    addr: SocketAddr,
+    port_retries: u16,
     open_on_start: bool,
+    allow_origin: Option<String>,
+    refresh_interval: Duration,
+    address: Option<Rc<watch::Publisher<Option<SocketAddr>>>>,
     handle: reactor::Handle,
 }
 
 impl VisualizerTask {
     fn new(
         settings: Settings,
-        probe: Terminal,
+        probe: Option<Terminal>,
         handle: reactor::Handle,
     ) -> Self {
         Self {
             probe: probe,
-            port: settings.port,
+            addr: SocketAddr::new(settings.bind, settings.port),
+            port_retries: settings.port_retries,
             open_on_start: settings.open_on_start,
+            allow_origin: settings.allow_origin,
+            refresh_interval: settings.refresh_interval,
+            address: settings.address,
 
             handle: handle,
         }
@@ -148,22 +248,89 @@ impl VisualizerTask {
 
     #[async]
     fn run(self) -> Result<()> {
-        let addr: SocketAddr = format!("127.0.0.1:{}", self.port).parse()?;
         let stream_handle = self.handle.clone();
-        let hypersf_handle = self.handle.clone();
-        let probe = self.probe;
+        let has_dot = has_dot();
+
+        if !has_dot {
+            eprintln!("graphviz not found, probe/svg will serve raw dot");
+        }
+
+        let mut addr = self.addr;
+        let mut retries = self.port_retries;
+
+        let server = loop {
+            let hypersf_handle = self.handle.clone();
+            let probe = self.probe.clone();
+            let allow_origin = self.allow_origin.clone();
+            let meta =
+                Rc::new(RefCell::new(Meta::new(self.refresh_interval, addr)));
+            let bound_meta = Rc::clone(&meta);
+
+            let result =
+                Http::new().serve_addr_handle(&addr, &self.handle, move || {
+                    Ok(VisualizerService::new(
+                        &hypersf_handle,
+                        probe.clone(),
+                        has_dot,
+                        allow_origin.clone(),
+                        Rc::clone(&meta),
+                    ))
+                });
+
+            match result {
+                Ok(server) => {
+                    // the os picks the port when binding to port 0
+                    addr = server.incoming_ref().local_addr();
+                    bound_meta.borrow_mut().bind = addr.to_string();
+
+                    break server;
+                },
+                Err(hyper::Error::Io(ref e))
+                    if e.kind() == std::io::ErrorKind::AddrInUse =>
+                {
+                    match addr.port().checked_add(1) {
+                        Some(port) if retries > 0 => {
+                            eprintln!(
+                                "visualizer port {} is in use, trying {}",
+                                addr.port(),
+                                port
+                            );
+
+                            retries -= 1;
+                            addr.set_port(port);
+                        },
+                        _ => bail!(
+                            "visualizer port {} is already in use - choose \
+                             another with visualizer::Settings::port or \
+                             allow retries with \
+                             visualizer::Settings::port_retries",
+                            addr.port()
+                        ),
+                    }
+                },
+                Err(e) => return Err(e.into()),
+            }
+        };
+
+        if let Some(ref address) = self.address {
+            address.publish(Some(addr));
+        }
 
         if self.open_on_start {
-            if let Err(e) = open::that(format!("http://{}", addr.to_string())) {
+            // a browser can't connect to the unspecified address
+            let url = if addr.ip().is_unspecified() {
+                format!("http://127.0.0.1:{}", addr.port())
+            } else {
+                format!("http://{}", addr)
+            };
+
+            if let Err(e) = open::that(url) {
                 eprintln!("unable to open default browser: {:#?}", e)
             }
         }
 
         await!(
-            Http::new()
-                .serve_addr_handle(&addr, &self.handle, move || Ok(
-                    VisualizerService::new(&hypersf_handle, probe.clone())
-                ))?
+            server
                 .for_each(move |connection| {
                     stream_handle.spawn(connection.map(|_| ()).or_else(
                         move |e| {
@@ -184,33 +351,102 @@ impl VisualizerTask {
     }
 }
 
+type EventChunk = std::result::Result<hyper::Chunk, hyper::Error>;
+
+/// server configuration that the ui reads to configure itself
+#[derive(Debug, Clone, Serialize)]
+struct Meta {
+    /// how often the ui should poll in milliseconds
+    refresh_ms: u64,
+    /// live updates are sent as server-sent events on probe/events, not over
+    /// a websocket, so this is always false
+    ws_available: bool,
+    /// address the visualizer is listening on
+    bind: String,
+}
+
+impl Meta {
+    fn new(refresh_interval: Duration, addr: SocketAddr) -> Self {
+        Self {
+            refresh_ms: refresh_interval.as_secs() * 1000
+                + u64::from(refresh_interval.subsec_nanos() / 1_000_000),
+            ws_available: false,
+            bind: addr.to_string(),
+        }
+    }
+}
+
 struct VisualizerService {
-    probe: Terminal,
+    probe: Option<Terminal>,
+    handle: reactor::Handle,
+    // whether the graphviz dot binary can be used to lay out svgs
+    has_dot: bool,
+    // origin sent in Access-Control-Allow-Origin, if cors is enabled
+    allow_origin: Option<String>,
+    meta: Rc<RefCell<Meta>>,
 }
 
 impl VisualizerService {
-    fn new(_handle: &reactor::Handle, probe: Terminal) -> Self {
-        Self { probe: probe }
+    fn new(
+        handle: &reactor::Handle,
+        probe: Option<Terminal>,
+        has_dot: bool,
+        allow_origin: Option<String>,
+        meta: Rc<RefCell<Meta>>,
+    ) -> Self {
+        Self {
+            probe: probe,
+            handle: handle.clone(),
+            has_dot: has_dot,
+            allow_origin: allow_origin,
+            meta: meta,
+        }
+    }
+
+    /// answer a cors preflight request
+    fn preflight() -> <Self as Service>::Future {
+        let mut rsp = hyper::Response::new();
+
+        rsp.set_status(hyper::StatusCode::NoContent);
+        rsp.headers_mut()
+            .set_raw("Access-Control-Allow-Methods", "GET, OPTIONS");
+        rsp.headers_mut()
+            .set_raw("Access-Control-Allow-Headers", "Content-Type");
+
+        Box::new(future::ok(rsp))
     }
 
     fn get(&self, req: hyper::Request) -> <Self as Service>::Future {
+        // the ui is compiled in, so it is served no matter where the binary
+        // is run from
         match req.path() {
             "/" | "/index.html" => {
                 let mut rsp = hyper::Response::new();
 
-                rsp.set_body(include_str!("index.html"));
+                set_content_type(&mut rsp, "text/html; charset=utf-8");
+                rsp.set_body(include_str!("index.html").replace(
+                    "{{API_VERSION}}",
+                    &probe::API_VERSION.to_string(),
+                ));
 
                 Box::new(future::ok(rsp))
             },
             "/viz-lite.js" => {
                 let mut rsp = hyper::Response::new();
 
+                set_content_type(&mut rsp, "application/javascript");
                 rsp.set_body(include_str!("viz-lite.js"));
 
                 Box::new(future::ok(rsp))
             },
             _ => Box::new(
-                Self::get_api(req, self.probe.clone()).map_err(|e| e.into()),
+                Self::get_api(
+                    req,
+                    self.probe.clone(),
+                    self.handle.clone(),
+                    self.has_dot,
+                    self.meta.borrow().clone(),
+                ).map_err(|e| e.into()),
             ),
         }
     }
@@ -218,24 +454,161 @@ impl VisualizerService {
     #[async]
     fn get_api(
         req: hyper::Request,
-        probe: Terminal,
+        probe: Option<Terminal>,
+        handle: reactor::Handle,
+        has_dot: bool,
+        meta: Meta,
     ) -> Result<hyper::Response> {
-        if req.path() == "/api/probe/json" {
-            await!(Self::probe_json(probe))
-        } else if req.path() == "/api/probe/dot" {
-            await!(Self::probe_dot(probe))
+        let (version, route) = match api_route(req.path()) {
+            Some(route) => route,
+            None => return await!(Self::not_found(req)),
+        };
+
+        // unversioned paths keep serving the bare v1 shape for older clients
+        let envelope = version.is_some();
+
+        let version = match version {
+            Some(version) if version < 1 || version > probe::API_VERSION => {
+                return await!(Self::unsupported_version(version))
+            },
+            Some(version) => version,
+            None => 1,
+        };
+
+        // the ui needs this even if no probe is connected
+        if route == "meta" {
+            return Self::meta(&meta, version);
+        }
+
+        let probe = match probe {
+            Some(probe) => probe,
+            None => return await!(Self::probe_not_connected()),
+        };
+
+        let filter = probe_filter(&req);
+
+        if route == "probe/json" {
+            let flat = query_param(&req, "format") == Some("flat".into());
+
+            await!(Self::probe_json(probe, filter, flat, version, envelope))
+        } else if route == "probe/graph" {
+            await!(Self::probe_graph(probe, filter, version, envelope))
+        } else if route == "probe/dot" {
+            let download = query_param(&req, "download") == Some("1".into());
+
+            await!(Self::probe_dot(probe, filter, download, version))
+        } else if route == "probe/svg" {
+            await!(Self::probe_svg(probe, filter, has_dot, version))
+        } else if route == "probe/events" {
+            Ok(Self::probe_events(probe, filter, version, envelope, &handle))
         } else {
             await!(Self::not_found(req))
         }
     }
 
+    fn meta(meta: &Meta, version: u32) -> Result<hyper::Response> {
+        let mut rsp = hyper::Response::new();
+        set_version_header(&mut rsp, version);
+
+        set_content_type(&mut rsp, "application/json");
+        rsp.set_body(serde_json::to_string(meta)?);
+
+        Ok(rsp)
+    }
+
+    #[async]
+    fn probe_json(
+        probe: Terminal,
+        filter: Option<probe::Filter>,
+        flat: bool,
+        version: u32,
+        envelope: bool,
+    ) -> Result<hyper::Response> {
+        let mut rsp = hyper::Response::new();
+        set_version_header(&mut rsp, version);
+
+        match await!(probe_data(probe, filter)) {
+            Ok(data) => {
+                let json = if flat {
+                    serde_json::to_value(&data.flatten())
+                } else {
+                    Ok(soma_json(&data))
+                };
+
+                let json = json.and_then(|json| {
+                    let json = downgrade_json(json, version);
+
+                    if envelope {
+                        serde_json::to_string(&probe::Envelope::versioned(
+                            version,
+                            json,
+                        ))
+                    } else {
+                        serde_json::to_string(&json)
+                    }
+                });
+
+                match json {
+                    Ok(json) => {
+                        set_content_type(&mut rsp, "application/json");
+                        rsp.set_body(json);
+                    },
+                    Err(e) => {
+                        eprintln!("unable to serialize probe data - {}", e);
+
+                        rsp.set_status(hyper::StatusCode::InternalServerError);
+                        rsp.set_body(format!(
+                            "unable to serialize probe data - {}",
+                            e
+                        ));
+                    },
+                }
+            },
+            Err(e) => {
+                rsp.set_status(hyper::StatusCode::InternalServerError);
+                rsp.set_body(format!("{:#?}", e));
+            },
+        }
+
+        Ok(rsp)
+    }
+
     #[async]
-    fn probe_json(probe: Terminal) -> Result<hyper::Response> {
+    fn probe_graph(
+        probe: Terminal,
+        filter: Option<probe::Filter>,
+        version: u32,
+        envelope: bool,
+    ) -> Result<hyper::Response> {
         let mut rsp = hyper::Response::new();
+        set_version_header(&mut rsp, version);
 
-        match await!(probe.probe(probe::Settings::new())) {
+        match await!(probe_data(probe, filter)) {
             Ok(data) => {
-                rsp.set_body(serde_json::to_string(&data)?);
+                let graph = probe::render_graph(data);
+
+                let json = if envelope {
+                    serde_json::to_string(&probe::Envelope::versioned(
+                        version,
+                        graph,
+                    ))
+                } else {
+                    serde_json::to_string(&graph)
+                };
+
+                match json {
+                    Ok(json) => {
+                        set_content_type(&mut rsp, "application/json");
+                        rsp.set_body(json);
+                    },
+                    Err(e) => {
+                        rsp.set_status(hyper::StatusCode::InternalServerError);
+                        rsp.set_body(format!(
+                            "unable to serialize probe graph - {}",
+                            e
+                        ));
+                    },
+                }
             },
             Err(e) => {
                 rsp.set_status(hyper::StatusCode::InternalServerError);
@@ -247,11 +620,26 @@ impl VisualizerService {
     }
 
     #[async]
-    fn probe_dot(probe: Terminal) -> Result<hyper::Response> {
+    fn probe_dot(
+        probe: Terminal,
+        filter: Option<probe::Filter>,
+        download: bool,
+        version: u32,
+    ) -> Result<hyper::Response> {
         let mut rsp = hyper::Response::new();
+        set_version_header(&mut rsp, version);
 
-        match await!(probe.probe(probe::Settings::new())) {
+        match await!(probe_data(probe, filter)) {
             Ok(data) => {
+                set_content_type(&mut rsp, "text/vnd.graphviz");
+
+                if download {
+                    rsp.headers_mut().set_raw(
+                        "Content-Disposition",
+                        "attachment; filename=\"probe.dot\"",
+                    );
+                }
+
                 rsp.set_body(render_dot(data)?);
             },
             Err(e) => {
@@ -263,6 +651,118 @@ impl VisualizerService {
         Ok(rsp)
     }
 
+    /// lay the graph out as an svg with graphviz
+    ///
+    /// if graphviz isn't installed, the raw dot is served instead, just like
+    /// probe/dot. the layout blocks the reactor, so keep it to graphs that
+    /// lay out quickly.
+    #[async]
+    fn probe_svg(
+        probe: Terminal,
+        filter: Option<probe::Filter>,
+        has_dot: bool,
+        version: u32,
+    ) -> Result<hyper::Response> {
+        let mut rsp = hyper::Response::new();
+        set_version_header(&mut rsp, version);
+
+        match await!(probe_data(probe, filter)) {
+            Ok(data) => {
+                let dot = render_dot(data)?;
+
+                if has_dot {
+                    set_content_type(&mut rsp, "image/svg+xml");
+                    rsp.set_body(layout_svg(&dot)?);
+                } else {
+                    set_content_type(&mut rsp, "text/vnd.graphviz");
+                    rsp.set_body(dot);
+                }
+            },
+            Err(e) => {
+                rsp.set_status(hyper::StatusCode::InternalServerError);
+                rsp.set_body(format!("{:#?}", e));
+            },
+        }
+
+        Ok(rsp)
+    }
+
+    /// stream probe data as server-sent events whenever the topology changes
+    ///
+    /// see probe::Terminal::subscribe. the stream ends when the client goes
+    /// away or the probe soma stops.
+    fn probe_events(
+        probe: Terminal,
+        filter: Option<probe::Filter>,
+        version: u32,
+        envelope: bool,
+        handle: &reactor::Handle,
+    ) -> hyper::Response {
+        let mut rsp = hyper::Response::new();
+        set_version_header(&mut rsp, version);
+        set_content_type(&mut rsp, "text/event-stream");
+        rsp.headers_mut().set_raw("Cache-Control", "no-cache");
+
+        let (tx, body) = hyper::Body::pair();
+
+        let events = probe
+            .subscribe(probe_settings())
+            .map(move |data| {
+                let data = match filter {
+                    Some(ref filter) => data.filter(filter),
+                    None => data,
+                };
+                let json = downgrade_json(soma_json(&data), version);
+
+                let json = if envelope {
+                    serde_json::to_string(&probe::Envelope::versioned(
+                        version,
+                        json,
+                    ))
+                } else {
+                    serde_json::to_string(&json)
+                };
+
+                match json {
+                    Ok(json) => Ok(format!("data: {}\n\n", json).into()),
+                    Err(e) => Err(Error::from(e).into()),
+                }
+            })
+            .or_else(|e| -> std::result::Result<_, SendError<EventChunk>> {
+                Ok(Err(e.into()))
+            });
+
+        handle.spawn(tx.send_all(events).map(|_| ()).map_err(|_| ()));
+
+        rsp.set_body(body);
+
+        rsp
+    }
+
+    #[async]
+    fn probe_not_connected() -> Result<hyper::Response> {
+        let mut rsp = hyper::Response::new();
+        rsp.set_status(hyper::StatusCode::ServiceUnavailable);
+        rsp.set_body("probe not connected - connect a probe soma to the \
+                      visualizer's Probe terminal");
+
+        Ok(rsp)
+    }
+
+    #[async]
+    fn unsupported_version(version: u32) -> Result<hyper::Response> {
+        let mut rsp = hyper::Response::new();
+        set_version_header(&mut rsp, probe::API_VERSION);
+        rsp.set_status(hyper::StatusCode::NotFound);
+        rsp.set_body(format!(
+            "probe api v{} is not supported, use v1 through v{}",
+            version,
+            probe::API_VERSION
+        ));
+
+        Ok(rsp)
+    }
+
     #[async]
     fn not_found(req: hyper::Request) -> Result<hyper::Response> {
         let mut rsp = hyper::Response::new();
@@ -280,14 +780,236 @@ impl Service for VisualizerService {
     type Future = Box<Future<Item = Self::Response, Error = Self::Error>>;
 
     fn call(&self, req: hyper::Request) -> Self::Future {
-        match req.method() {
-            &hyper::Method::Get => self.get(req),
+        let rsp = match (req.method(), &self.allow_origin) {
+            (&hyper::Method::Get, _) => self.get(req),
+            (&hyper::Method::Options, &Some(_)) => Self::preflight(),
 
             _ => Box::new(Self::not_found(req).map_err(|e| e.into())),
+        };
+
+        match self.allow_origin.clone() {
+            Some(origin) => Box::new(rsp.map(move |mut rsp| {
+                rsp.headers_mut()
+                    .set_raw("Access-Control-Allow-Origin", origin);
+                rsp
+            })),
+            None => rsp,
+        }
+    }
+}
+
+/// split an api path into its optional version and the route after it
+///
+/// `/api/v1/probe/json` gives `(Some(1), "probe/json")` and
+/// `/api/probe/json` gives `(None, "probe/json")`.
+fn api_route(path: &str) -> Option<(Option<u32>, String)> {
+    if !path.starts_with("/api/") {
+        return None;
+    }
+
+    let rest = &path["/api/".len()..];
+
+    if rest.starts_with('v') {
+        let mut split = rest[1..].splitn(2, '/');
+
+        match (split.next().and_then(|v| v.parse().ok()), split.next()) {
+            (Some(version), Some(route)) => {
+                Some((Some(version), route.to_string()))
+            },
+            _ => None,
         }
+    } else {
+        Some((None, rest.to_string()))
+    }
+}
+
+fn set_version_header(rsp: &mut hyper::Response, version: u32) {
+    rsp.headers_mut()
+        .set_raw("X-Probe-Api-Version", version.to_string());
+}
+
+/// rewrite probe json into the shape an older api version promised
+///
+/// v1 only knew one and variadic constraints and axon, organelle, and soma
+/// data. optional and range constraints become variadic, and unavailable
+/// somas become plain somas. fields that were added later are left in,
+/// since clients ignore fields they don't know about.
+fn downgrade_json(
+    mut json: serde_json::Value,
+    version: u32,
+) -> serde_json::Value {
+    if version < 2 {
+        downgrade_v1(&mut json);
+    }
+
+    json
+}
+
+fn downgrade_v1(json: &mut serde_json::Value) {
+    match *json {
+        serde_json::Value::Array(ref mut values) => {
+            for value in values.iter_mut() {
+                downgrade_v1(value);
+            }
+        },
+        serde_json::Value::Object(ref mut map) => {
+            for (_, value) in map.iter_mut() {
+                downgrade_v1(value);
+            }
+
+            let kind = map.get("type")
+                .and_then(|kind| kind.as_str())
+                .map(|kind| kind.to_string());
+
+            match kind.as_ref().map(|kind| kind.as_str()) {
+                Some("optional") => {
+                    let somas = match map.remove("soma") {
+                        Some(serde_json::Value::Null) | None => vec![],
+                        Some(soma) => vec![soma],
+                    };
+
+                    map.insert("type".into(), "variadic".into());
+                    map.insert("somas".into(), somas.into());
+                },
+                Some("range") => {
+                    map.remove("min");
+                    map.remove("max");
+                    map.insert("type".into(), "variadic".into());
+                },
+                Some("unavailable") => {
+                    map.insert("type".into(), "soma".into());
+                    map.insert("synapse".into(), "unavailable".into());
+                    map.insert("name".into(), "unavailable".into());
+                },
+                _ => (),
+            }
+
+            // the flat format names the kind of soma instead of its type
+            let unavailable = map.get("kind").and_then(|kind| kind.as_str())
+                == Some("unavailable");
+
+            if unavailable {
+                map.insert("kind".into(), "soma".into());
+            }
+        },
+        _ => (),
+    }
+}
+
+fn set_content_type(rsp: &mut hyper::Response, content_type: &'static str) {
+    rsp.headers_mut().set_raw("Content-Type", content_type);
+}
+
+/// serialize probe data without letting one soma poison the rest
+///
+/// a soma that fails to serialize is replaced with an `unserializable` entry
+/// noting the error, and the failure is logged.
+fn soma_json(data: &SomaData) -> serde_json::Value {
+    let e = match serde_json::to_value(data) {
+        Ok(value) => return value,
+        Err(e) => e,
+    };
+
+    let mut map = serde_json::Map::new();
+
+    match data {
+        &SomaData::Organelle {
+            ref nucleus,
+            ref somas,
+            uuid,
+            ref name,
+            ref dropped,
+            ref edges,
+        } => {
+            map.insert("type".into(), "organelle".into());
+            map.insert("nucleus".into(), soma_json(nucleus));
+            map.insert(
+                "somas".into(),
+                somas.iter().map(soma_json).collect::<Vec<_>>().into(),
+            );
+            map.insert("uuid".into(), uuid.hyphenated().to_string().into());
+            map.insert("name".into(), name.clone().into());
+
+            if !dropped.is_empty() {
+                if let Ok(dropped) = serde_json::to_value(dropped) {
+                    map.insert("dropped".into(), dropped);
+                }
+            }
+
+            if let Ok(edges) = serde_json::to_value(edges) {
+                map.insert("edges".into(), edges);
+            }
+        },
+        &SomaData::Axon { ref name, .. }
+        | &SomaData::Soma { ref name, .. } => {
+            eprintln!("unable to serialize probe data for {} - {}", name, e);
+
+            map.insert("type".into(), "unserializable".into());
+            map.insert("name".into(), name.clone().into());
+            map.insert("error".into(), e.to_string().into());
+        },
+        &SomaData::Unavailable { uuid, ref reason } => {
+            map.insert("type".into(), "unavailable".into());
+            map.insert("uuid".into(), uuid.hyphenated().to_string().into());
+            map.insert("reason".into(), reason.clone().into());
+        },
+    }
+
+    serde_json::Value::Object(map)
+}
+
+/// settings for the probes made on behalf of http requests
+///
+/// somas that don't answer within a few seconds are left out so that one
+/// stuck soma can't hang the response.
+fn probe_settings() -> probe::Settings {
+    probe::Settings::new()
+        .stable_order(true)
+        .timeout(Duration::from_secs(5))
+}
+
+/// the filter asked for with the `category` or `name` query parameters
+fn probe_filter(req: &hyper::Request) -> Option<probe::Filter> {
+    if let Some(category) = query_param(req, "category") {
+        Some(probe::Filter::Category(category))
+    } else if let Some(name) = query_param(req, "name") {
+        Some(probe::Filter::Name(name))
+    } else {
+        None
     }
 }
 
+/// probe the organelle, keeping only what the filter asks for
+#[async]
+fn probe_data(
+    probe: Terminal,
+    filter: Option<probe::Filter>,
+) -> Result<SomaData> {
+    let data = await!(probe.probe(probe_settings()))?;
+
+    Ok(match filter {
+        Some(filter) => data.filter(&filter),
+        None => data,
+    })
+}
+
+fn query_param(req: &hyper::Request, key: &str) -> Option<String> {
+    req.query().and_then(|query| {
+        query
+            .split('&')
+            .filter_map(|pair| {
+                let mut split = pair.splitn(2, '=');
+
+                match (split.next(), split.next()) {
+                    (Some(k), Some(v)) if k == key => Some(v.to_string()),
+                    (Some(k), None) if k == key => Some(String::new()),
+                    _ => None,
+                }
+            })
+            .next()
+    })
+}
+
 fn render_organelle(
     uuid: Uuid,
     name: String,
@@ -323,52 +1045,26 @@ fn render_organelle(
                 let src_uuid = uuid;
 
                 for t in terminals {
-                    match t {
-                        &ConstraintData::One { ref variant, soma } => {
-                            let tgt_uuid = if let Some(uuid) = remap.get(&soma)
-                            {
-                                *uuid
-                            } else {
-                                soma
-                            };
-
-                            edges.push(dot::NodeId::new(dot::Id::quoted(
-                                src_uuid.to_string(),
-                            )).port(dot::Id::ident(format!("t_{}", variant)))
-                                .connect(
-                                    dot::EdgeOp::Directed,
-                                    dot::NodeId::new(dot::Id::quoted(
-                                        tgt_uuid.to_string(),
-                                    )).port(dot::Id::ident(format!(
-                                        "d_{}",
-                                        variant
-                                    ))),
-                                ));
-                        },
-                        &ConstraintData::Variadic {
-                            ref variant,
-                            ref somas,
-                        } => for uuid in somas {
-                            let tgt_uuid =
-                                if let Some(remapped) = remap.get(&uuid) {
-                                    *remapped
-                                } else {
-                                    *uuid
-                                };
-
-                            edges.push(dot::NodeId::new(dot::Id::quoted(
-                                src_uuid.to_string(),
-                            )).port(dot::Id::ident(format!("t_{}", variant)))
-                                .connect(
-                                    dot::EdgeOp::Directed,
-                                    dot::NodeId::new(dot::Id::quoted(
-                                        tgt_uuid.to_string(),
-                                    )).port(dot::Id::ident(format!(
-                                        "d_{}",
-                                        variant
-                                    ))),
-                                ));
-                        },
+                    for (peer, meta) in t.connections() {
+                        // peers left out of the probe data get no edge
+                        let tgt_uuid = match remap.get(&peer) {
+                            Some(uuid) => *uuid,
+                            None => continue,
+                        };
+
+                        edges.push(dot::NodeId::new(dot::Id::quoted(
+                            src_uuid.to_string(),
+                        )).port(dot::Id::ident(format!("t_{}", t.variant())))
+                            .connect(
+                                dot::EdgeOp::Directed,
+                                dot::NodeId::new(dot::Id::quoted(
+                                    tgt_uuid.to_string(),
+                                )).port(dot::Id::ident(format!(
+                                    "d_{}",
+                                    t.variant()
+                                ))),
+                            )
+                            .with_attrs(edge_attrs(meta)));
                     }
                 }
             },
@@ -384,50 +1080,125 @@ fn render_organelle(
     organelle
 }
 
+fn edge_attrs(meta: Option<&ConnectionMeta>) -> Vec<dot::Attribute> {
+    let label = match meta {
+        Some(&ConnectionMeta {
+            label: Some(ref label),
+            weight: Some(weight),
+        }) => format!("{} ({})", label, weight),
+        Some(&ConnectionMeta {
+            label: Some(ref label),
+            weight: None,
+        }) => label.clone(),
+        Some(&ConnectionMeta {
+            label: None,
+            weight: Some(weight),
+        }) => weight.to_string(),
+        _ => return vec![],
+    };
+
+    vec![
+        dot::Attribute::new(dot::Id::ident("label"), dot::Id::quoted(label)),
+    ]
+}
+
+fn port_label(prefix: &str, constraint: &ConstraintData) -> String {
+    let variant = constraint.variant();
+
+    let label = match constraint {
+        &ConstraintData::Range { min, max, .. } => format!(
+            "<{}_{}> {} [{}..={}]",
+            prefix, variant, variant, min, max
+        ),
+        _ => format!("<{}_{}> {}", prefix, variant, variant),
+    };
+
+    // record fields can't be colored on their own, so mark them instead
+    if constraint.is_satisfied() {
+        label
+    } else {
+        label + " (waiting)"
+    }
+}
+
+/// pick a fill color for a category so that the same one is always used
+fn category_color(category: &str) -> &'static str {
+    const PALETTE: [&str; 8] = [
+        "lightblue",
+        "palegreen",
+        "lightsalmon",
+        "khaki",
+        "plum",
+        "lightcyan",
+        "peachpuff",
+        "lightgray",
+    ];
+
+    let sum = category
+        .bytes()
+        .fold(0usize, |sum, b| sum.wrapping_mul(31).wrapping_add(b as usize));
+
+    PALETTE[sum % PALETTE.len()]
+}
+
 fn render_axon(
     uuid: Uuid,
     name: String,
     terminals: Vec<ConstraintData>,
     dendrites: Vec<ConstraintData>,
+    pending: Vec<ConstraintStatus>,
+    category: Option<String>,
+    metrics: SomaMetrics,
     _remap: &HashMap<Uuid, Uuid>,
 ) -> dot::SubGraph {
     let mut axon = dot::SubGraph::new();
 
+    let mut name = name.replace("<", "\\<").replace(">", "\\>");
+
+    // axons that are still waiting for connections stand out in red
+    let color = if pending.is_empty() { "black" } else { "red" };
+
+    for status in pending {
+        name += &format!(
+            "\\nwaiting for {} more {} {}",
+            status.required - status.connected,
+            status.variant,
+            status.kind
+        );
+    }
+
+    if let (Some(impulses), Some(queued)) = (metrics.impulses, metrics.queued)
+    {
+        name += &format!("\\n{} handled, {} queued", impulses, queued);
+    }
+
     let terminals: Vec<String> = terminals
         .into_iter()
-        .map(|t| match t {
-            ConstraintData::One { variant, .. } => {
-                format!("<t_{}> {}", variant, variant)
-            },
-            ConstraintData::Variadic { variant, .. } => {
-                format!("<t_{}> {}", variant, variant)
-            },
-        })
+        .map(|t| port_label("t", &t))
         .collect();
 
     let terminals = terminals.join(" | ");
 
     let dendrites: Vec<String> = dendrites
         .into_iter()
-        .map(|d| match d {
-            ConstraintData::One { variant, .. } => {
-                format!("<d_{}> {}", variant, variant)
-            },
-            ConstraintData::Variadic { variant, .. } => {
-                format!("<d_{}> {}", variant, variant)
-            },
-        })
+        .map(|d| port_label("d", &d))
         .collect();
 
     let dendrites = dendrites.join(" | ");
 
+    // somas of the same category share a fill color
+    let (style, fill) = match category {
+        Some(ref category) => ("rounded,filled", category_color(category)),
+        None => ("rounded", "white"),
+    };
+
     axon = axon.add(
         dot::Node::new(dot::Id::quoted(uuid.to_string()))
             .add(dot::Attribute::new(
                 dot::Id::ident("label"),
                 dot::Id::quoted(format!(
                     "<name> {} | {{ {{ {} }} | {{ }} | {{ {} }} }} | {{ }}",
-                    name.replace("<", "\\<").replace(">", "\\>"),
+                    name,
                     dendrites,
                     terminals,
                 )),
@@ -438,13 +1209,47 @@ fn render_axon(
             ))
             .add(dot::Attribute::new(
                 dot::Id::ident("style"),
-                dot::Id::ident("rounded"),
+                dot::Id::quoted(style),
+            ))
+            .add(dot::Attribute::new(
+                dot::Id::ident("fillcolor"),
+                dot::Id::ident(fill),
+            ))
+            .add(dot::Attribute::new(
+                dot::Id::ident("color"),
+                dot::Id::ident(color),
+            ))
+            .add(dot::Attribute::new(
+                dot::Id::ident("fontcolor"),
+                dot::Id::ident(color),
             )),
     );
 
     axon
 }
 
+fn render_unavailable(uuid: Uuid, reason: String) -> dot::SubGraph {
+    dot::SubGraph::new().add(
+        dot::Node::new(dot::Id::quoted(uuid.to_string()))
+            .add(dot::Attribute::new(
+                dot::Id::ident("label"),
+                dot::Id::quoted(format!("unavailable\\n{}", reason)),
+            ))
+            .add(dot::Attribute::new(
+                dot::Id::ident("style"),
+                dot::Id::ident("dashed"),
+            ))
+            .add(dot::Attribute::new(
+                dot::Id::ident("color"),
+                dot::Id::ident("gray"),
+            ))
+            .add(dot::Attribute::new(
+                dot::Id::ident("fontcolor"),
+                dot::Id::ident("gray"),
+            )),
+    )
+}
+
 fn render_soma(data: SomaData, remap: &HashMap<Uuid, Uuid>) -> dot::SubGraph {
     match data {
         SomaData::Organelle {
@@ -452,13 +1257,29 @@ fn render_soma(data: SomaData, remap: &HashMap<Uuid, Uuid>) -> dot::SubGraph {
             nucleus,
             somas,
             name,
+            ..
         } => render_organelle(uuid, name, *nucleus, somas, remap),
         SomaData::Axon {
             terminals,
             dendrites,
             uuid,
             name,
-        } => render_axon(uuid, name, terminals, dendrites, remap),
+            pending,
+            category,
+            metrics,
+        } => render_axon(
+            uuid,
+            name,
+            terminals,
+            dendrites,
+            pending,
+            category,
+            metrics,
+            remap,
+        ),
+        SomaData::Unavailable { uuid, reason } => {
+            render_unavailable(uuid, reason)
+        },
         _ => unimplemented!(),
     }
 }
@@ -466,11 +1287,16 @@ fn render_soma(data: SomaData, remap: &HashMap<Uuid, Uuid>) -> dot::SubGraph {
 fn get_uuid(data: &SomaData) -> Option<Uuid> {
     match data {
         &SomaData::Organelle { ref nucleus, .. } => get_uuid(nucleus),
-        &SomaData::Axon { uuid, .. } => Some(uuid),
+        &SomaData::Axon { uuid, .. }
+        | &SomaData::Unavailable { uuid, .. } => Some(uuid),
         _ => None,
     }
 }
 
+/// map each soma to the node its edges should point at
+///
+/// organelles are drawn as clusters, so their edges go to their nucleus
+/// instead. every other soma with a uuid maps to itself.
 fn remap_uuids(data: &SomaData, remap: &mut HashMap<Uuid, Uuid>) {
     match data {
         &SomaData::Organelle {
@@ -489,10 +1315,48 @@ fn remap_uuids(data: &SomaData, remap: &mut HashMap<Uuid, Uuid>) {
                 remap_uuids(soma, remap);
             }
         },
-        _ => (),
+        &SomaData::Axon { uuid, .. } | &SomaData::Unavailable { uuid, .. } => {
+            remap.insert(uuid, uuid);
+        },
+        &SomaData::Soma { .. } => (),
     }
 }
 
+/// check if the graphviz dot binary is installed
+fn has_dot() -> bool {
+    Command::new("dot")
+        .arg("-V")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+/// lay out a dot graph as an svg with the graphviz dot binary
+fn layout_svg(dot: &str) -> Result<String> {
+    let mut child = Command::new("dot")
+        .arg("-Tsvg")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // stdin is closed once it is dropped, so dot knows the graph is done
+    child.stdin.take().unwrap().write_all(dot.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        bail!(
+            "unable to lay out svg - {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(String::from_utf8(output.stdout)?)
+}
+
 fn render_dot(data: SomaData) -> Result<String> {
     let buf = Vec::new();
     let mut writer = buf.writer();
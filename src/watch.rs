@@ -0,0 +1,129 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use futures::prelude::*;
+use futures::task::{self, Task};
+
+use super::{Error, Result};
+
+#[derive(Debug)]
+struct Shared<S> {
+    value: S,
+    closed: bool,
+    tasks: Vec<Task>,
+}
+
+/// publishes the state of a soma
+///
+/// every call to publish replaces the state and wakes any watchers so they
+/// can re-evaluate their predicates against the new value.
+#[derive(Debug)]
+pub struct Publisher<S> {
+    shared: Rc<RefCell<Shared<S>>>,
+}
+
+impl<S> Publisher<S> {
+    /// replace the published state
+    pub fn publish(&self, value: S) {
+        let mut shared = self.shared.borrow_mut();
+
+        shared.value = value;
+
+        for task in shared.tasks.drain(..) {
+            task.notify();
+        }
+    }
+}
+
+impl<S> Drop for Publisher<S> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.borrow_mut();
+
+        shared.closed = true;
+
+        for task in shared.tasks.drain(..) {
+            task.notify();
+        }
+    }
+}
+
+/// observes the state published by a soma
+#[derive(Debug)]
+pub struct Watcher<S> {
+    shared: Rc<RefCell<Shared<S>>>,
+}
+
+impl<S> Clone for Watcher<S> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: Rc::clone(&self.shared),
+        }
+    }
+}
+
+impl<S: Clone> Watcher<S> {
+    /// get the most recently published state
+    pub fn get(&self) -> S {
+        self.shared.borrow().value.clone()
+    }
+
+    /// resolve with the state once the predicate holds
+    ///
+    /// the predicate is evaluated against the latest state each time the
+    /// future is polled, so intermediate states that are replaced before the
+    /// watcher wakes up are never observed.
+    pub fn wait_for<F>(self, predicate: F) -> WaitFor<S, F>
+    where
+        F: Fn(&S) -> bool,
+    {
+        WaitFor {
+            shared: self.shared,
+            predicate: predicate,
+        }
+    }
+}
+
+/// future returned by Watcher::wait_for
+pub struct WaitFor<S, F> {
+    shared: Rc<RefCell<Shared<S>>>,
+    predicate: F,
+}
+
+impl<S, F> Future for WaitFor<S, F>
+where
+    S: Clone,
+    F: Fn(&S) -> bool,
+{
+    type Item = S;
+    type Error = Error;
+
+    fn poll(&mut self) -> Result<Async<S>> {
+        let mut shared = self.shared.borrow_mut();
+
+        if (self.predicate)(&shared.value) {
+            Ok(Async::Ready(shared.value.clone()))
+        } else if shared.closed {
+            bail!("soma state publisher was dropped")
+        } else {
+            shared.tasks.push(task::current());
+
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+/// create a channel for publishing the state of a soma
+pub fn channel<S>(initial: S) -> (Publisher<S>, Watcher<S>) {
+    let shared = Rc::new(RefCell::new(Shared {
+        value: initial,
+        closed: false,
+        tasks: vec![],
+    }));
+
+    (
+        Publisher {
+            shared: Rc::clone(&shared),
+        },
+        Watcher { shared: shared },
+    )
+}
@@ -0,0 +1,507 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use futures::future;
+use futures::prelude::*;
+use futures::stream::FuturesUnordered;
+use futures::unsync::{mpsc, oneshot};
+use uuid::Uuid;
+
+use super::{Error, Result};
+use axon::{Axon, Constraint};
+use soma::{self, Impulse};
+
+/// work queue settings
+#[derive(Debug, Copy, Clone)]
+pub struct Settings {
+    capacity: usize,
+    max_in_flight: usize,
+}
+
+impl Settings {
+    /// set the number of pending tasks held before submitters must wait
+    pub fn capacity(self, capacity: usize) -> Self {
+        Self {
+            capacity: capacity,
+            ..self
+        }
+    }
+
+    /// set the number of unacknowledged tasks each worker may hold
+    pub fn max_in_flight(self, max: usize) -> Self {
+        Self {
+            max_in_flight: max,
+            ..self
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            max_in_flight: 1,
+        }
+    }
+}
+
+/// the part a synapse plays in the work queue
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Role {
+    /// a synapse used to add tasks to the queue
+    Submit,
+    /// a synapse used by workers to take tasks from the queue
+    Work,
+}
+
+/// the synapse for a work queue carrying tasks of type P
+pub struct Synapse<P> {
+    role: Role,
+    task: PhantomData<fn() -> P>,
+}
+
+impl<P> Synapse<P> {
+    /// a synapse used to add tasks to the queue
+    pub fn submit() -> Self {
+        Self {
+            role: Role::Submit,
+            task: PhantomData,
+        }
+    }
+
+    /// a synapse used by workers to take tasks from the queue
+    pub fn work() -> Self {
+        Self {
+            role: Role::Work,
+            task: PhantomData,
+        }
+    }
+
+    /// the part this synapse plays in the work queue
+    pub fn role(&self) -> Role {
+        self.role
+    }
+}
+
+impl<P> Clone for Synapse<P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P> Copy for Synapse<P> {}
+
+impl<P> PartialEq for Synapse<P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.role == other.role
+    }
+}
+
+impl<P> Eq for Synapse<P> {}
+
+impl<P> Hash for Synapse<P> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.role.hash(state);
+    }
+}
+
+impl<P> fmt::Debug for Synapse<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.role)
+    }
+}
+
+struct Request<P> {
+    lease: oneshot::Sender<Lease<P>>,
+}
+
+/// a task handed to a worker
+///
+/// the task stays in the queue until it is acknowledged. if the lease is
+/// dropped without being acknowledged, the task is delivered again.
+pub struct Lease<P> {
+    task: P,
+    ack: oneshot::Sender<()>,
+}
+
+impl<P> Lease<P> {
+    /// the task to work on
+    pub fn task(&self) -> &P {
+        &self.task
+    }
+
+    /// acknowledge that the task is complete and remove it from the queue
+    pub fn ack(self) {
+        if let Err(_) = self.ack.send(()) {
+            // queue does not care anymore
+        }
+    }
+}
+
+impl<P> fmt::Debug for Lease<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Lease").finish()
+    }
+}
+
+enum TerminalKind<P> {
+    Submit(mpsc::Sender<P>),
+    Work(mpsc::Sender<Request<P>>),
+}
+
+/// sender for a work queue synapse
+pub struct Terminal<P> {
+    kind: TerminalKind<P>,
+}
+
+impl<P: 'static> Terminal<P> {
+    /// add a task to the queue, waiting if the queue is full
+    pub fn submit(self, task: P) -> Box<Future<Item = Self, Error = Error>> {
+        match self.kind {
+            TerminalKind::Submit(tx) => Box::new(
+                tx.send(task)
+                    .map(|tx| Terminal {
+                        kind: TerminalKind::Submit(tx),
+                    })
+                    .map_err(|_| Error::from("work queue is closed")),
+            ),
+            TerminalKind::Work(_) => Box::new(future::err(Error::from(
+                "terminal is not for submitting tasks",
+            ))),
+        }
+    }
+
+    /// take the next task from the queue, waiting until one is available
+    pub fn next(self) -> Box<Future<Item = (Self, Lease<P>), Error = Error>> {
+        match self.kind {
+            TerminalKind::Work(tx) => {
+                let (lease_tx, lease_rx) = oneshot::channel();
+
+                Box::new(
+                    tx.send(Request { lease: lease_tx })
+                        .map_err(|_| Error::from("work queue is closed"))
+                        .and_then(move |tx| {
+                            lease_rx
+                                .map(move |lease| {
+                                    (
+                                        Terminal {
+                                            kind: TerminalKind::Work(tx),
+                                        },
+                                        lease,
+                                    )
+                                })
+                                .map_err(|_| {
+                                    Error::from("work queue is closed")
+                                })
+                        }),
+                )
+            },
+            TerminalKind::Submit(_) => Box::new(future::err(Error::from(
+                "terminal is not for taking tasks",
+            ))),
+        }
+    }
+}
+
+impl<P> fmt::Debug for Terminal<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            TerminalKind::Submit(_) => write!(f, "Terminal(Submit)"),
+            TerminalKind::Work(_) => write!(f, "Terminal(Work)"),
+        }
+    }
+}
+
+enum DendriteKind<P> {
+    Submit(mpsc::Receiver<P>),
+    Work(mpsc::Receiver<Request<P>>),
+}
+
+/// receiver for a work queue synapse
+pub struct Dendrite<P> {
+    kind: DendriteKind<P>,
+}
+
+impl<P> fmt::Debug for Dendrite<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            DendriteKind::Submit(_) => write!(f, "Dendrite(Submit)"),
+            DendriteKind::Work(_) => write!(f, "Dendrite(Work)"),
+        }
+    }
+}
+
+impl<P: 'static> soma::Synapse for Synapse<P> {
+    type Terminal = Terminal<P>;
+    type Dendrite = Dendrite<P>;
+
+    fn synapse(self) -> (Terminal<P>, Dendrite<P>) {
+        match self.role {
+            Role::Submit => {
+                let (tx, rx) = mpsc::channel(1);
+
+                (
+                    Terminal {
+                        kind: TerminalKind::Submit(tx),
+                    },
+                    Dendrite {
+                        kind: DendriteKind::Submit(rx),
+                    },
+                )
+            },
+            Role::Work => {
+                let (tx, rx) = mpsc::channel(1);
+
+                (
+                    Terminal {
+                        kind: TerminalKind::Work(tx),
+                    },
+                    Dendrite {
+                        kind: DendriteKind::Work(rx),
+                    },
+                )
+            },
+        }
+    }
+}
+
+/// soma that distributes tasks to workers with at-least-once delivery
+///
+/// producers connect to the queue with Synapse::submit and workers with
+/// Synapse::work. a worker asks for a task with Terminal::next and
+/// acknowledges it with Lease::ack once it is done. a task whose lease is
+/// dropped without an ack, including when the worker fails, goes back to the
+/// front of the queue, so tasks must be Clone and workers should expect to
+/// see a task more than once.
+pub struct Soma<P> {
+    settings: Settings,
+    submissions: Vec<mpsc::Receiver<P>>,
+    workers: Vec<(Uuid, mpsc::Receiver<Request<P>>)>,
+}
+
+impl<P: Clone + 'static> Soma<P> {
+    /// create a work queue soma
+    pub fn axon(settings: Settings) -> Axon<Self> {
+        Axon::new(
+            Self {
+                settings: settings,
+                submissions: vec![],
+                workers: vec![],
+            },
+            vec![
+                Constraint::Variadic(Synapse::submit()),
+                Constraint::Variadic(Synapse::work()),
+            ],
+            vec![],
+        )
+    }
+}
+
+impl<P: Clone + 'static> soma::Soma for Soma<P> {
+    type Synapse = Synapse<P>;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddDendrite(uuid, _, dendrite) => {
+                match dendrite.kind {
+                    DendriteKind::Submit(rx) => self.submissions.push(rx),
+                    DendriteKind::Work(rx) => self.workers.push((uuid, rx)),
+                }
+
+                Ok(self)
+            },
+
+            Impulse::Start(_, _, handle) => {
+                let (submit_tx, submit_rx) = mpsc::channel(1);
+                let (request_tx, request_rx) = mpsc::channel(1);
+
+                for rx in self.submissions {
+                    handle.spawn(
+                        submit_tx
+                            .clone()
+                            .send_all(rx.map_err(|_| unreachable!()))
+                            .map(|_| ())
+                            .map_err(|_| ()),
+                    );
+                }
+
+                for (worker, rx) in self.workers {
+                    handle.spawn(
+                        request_tx
+                            .clone()
+                            .send_all(
+                                rx.map(move |req| (worker, req))
+                                    .map_err(|_| unreachable!()),
+                            )
+                            .map(|_| ())
+                            .map_err(|_| ()),
+                    );
+                }
+
+                handle.spawn(QueueTask {
+                    settings: self.settings,
+                    submissions: submit_rx,
+                    requests: request_rx,
+                    submissions_done: false,
+                    requests_done: false,
+
+                    pending: VecDeque::new(),
+                    waiting: VecDeque::new(),
+                    in_flight: HashMap::new(),
+                    leased: HashMap::new(),
+                    acks: FuturesUnordered::new(),
+                    next_lease: 0,
+                });
+
+                Ok(Self {
+                    settings: self.settings,
+                    submissions: vec![],
+                    workers: vec![],
+                })
+            },
+
+            imp => Err(imp.unexpected(&self)),
+        }
+    }
+}
+
+struct QueueTask<P> {
+    settings: Settings,
+    submissions: mpsc::Receiver<P>,
+    requests: mpsc::Receiver<(Uuid, Request<P>)>,
+    submissions_done: bool,
+    requests_done: bool,
+
+    pending: VecDeque<P>,
+    waiting: VecDeque<(Uuid, Request<P>)>,
+    in_flight: HashMap<u64, (Uuid, P)>,
+    leased: HashMap<Uuid, usize>,
+    acks: FuturesUnordered<Box<Future<Item = (u64, bool), Error = ()>>>,
+    next_lease: u64,
+}
+
+impl<P: Clone + 'static> QueueTask<P> {
+    /// hand pending tasks to waiting workers that have room for them
+    fn dispatch(&mut self) -> bool {
+        let mut progress = false;
+        let mut waiting = VecDeque::new();
+
+        while let Some((worker, req)) = self.waiting.pop_front() {
+            let leased = self.leased.get(&worker).cloned().unwrap_or(0);
+
+            if self.pending.is_empty()
+                || leased >= self.settings.max_in_flight
+            {
+                waiting.push_back((worker, req));
+                continue;
+            }
+
+            let task = self.pending.pop_front().unwrap();
+            let (ack_tx, ack_rx) = oneshot::channel();
+
+            let lease = Lease {
+                task: task.clone(),
+                ack: ack_tx,
+            };
+
+            match req.lease.send(lease) {
+                Ok(()) => {
+                    let id = self.next_lease;
+                    self.next_lease += 1;
+
+                    self.in_flight.insert(id, (worker, task));
+                    self.leased.insert(worker, leased + 1);
+                    self.acks.push(Box::new(
+                        ack_rx.then(move |result| Ok((id, result.is_ok()))),
+                    ));
+                },
+                // the worker stopped waiting, keep the task for someone else
+                Err(lease) => self.pending.push_front(lease.task),
+            }
+
+            progress = true;
+        }
+
+        self.waiting = waiting;
+
+        progress
+    }
+
+    /// check if the queue has nothing left to do
+    ///
+    /// the queue is done once no more tasks can arrive and every task has
+    /// been acknowledged, or once no worker is left to take them. dropping
+    /// the queue closes it for any submitters or workers still waiting.
+    fn finished(&self) -> Async<()> {
+        let settled = self.in_flight.is_empty();
+        let drained = self.submissions_done && self.pending.is_empty();
+        let abandoned = self.requests_done && self.waiting.is_empty();
+
+        if settled && (drained || abandoned) {
+            Async::Ready(())
+        } else {
+            Async::NotReady
+        }
+    }
+}
+
+impl<P: Clone + 'static> Future for QueueTask<P> {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        loop {
+            let mut progress = false;
+
+            while let Async::Ready(Some((id, acked))) = self.acks.poll()? {
+                if let Some((worker, task)) = self.in_flight.remove(&id) {
+                    if let Some(leased) = self.leased.get_mut(&worker) {
+                        *leased -= 1;
+                    }
+
+                    if !acked {
+                        self.pending.push_front(task);
+                    }
+                }
+
+                progress = true;
+            }
+
+            while !self.submissions_done
+                && self.pending.len() < self.settings.capacity
+            {
+                match self.submissions.poll()? {
+                    Async::Ready(Some(task)) => {
+                        self.pending.push_back(task);
+                        progress = true;
+                    },
+                    Async::Ready(None) => self.submissions_done = true,
+                    Async::NotReady => break,
+                }
+            }
+
+            while !self.requests_done {
+                match self.requests.poll()? {
+                    Async::Ready(Some(req)) => {
+                        self.waiting.push_back(req);
+                        progress = true;
+                    },
+                    Async::Ready(None) => self.requests_done = true,
+                    Async::NotReady => break,
+                }
+            }
+
+            if self.dispatch() {
+                progress = true;
+            }
+
+            if !progress {
+                return Ok(self.finished());
+            }
+        }
+    }
+}
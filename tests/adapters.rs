@@ -0,0 +1,194 @@
+#![feature(proc_macro, conservative_impl_trait, generators)]
+
+#[macro_use]
+extern crate error_chain;
+
+extern crate futures_await as futures;
+extern crate organelle;
+extern crate tokio_core;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use futures::future::Either;
+use futures::prelude::*;
+use futures::stream;
+use futures::unsync::mpsc;
+use organelle::*;
+use tokio_core::reactor;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum TestSynapse {
+    Input,
+    Output,
+}
+
+impl organelle::Synapse for TestSynapse {
+    type Terminal = mpsc::Sender<u32>;
+    type Dendrite = mpsc::Receiver<u32>;
+
+    fn synapse(self) -> (Self::Terminal, Self::Dendrite) {
+        mpsc::channel(10)
+    }
+}
+
+fn receive(rx: mpsc::Receiver<u32>) -> Box<Stream<Item = u32, Error = Error>> {
+    Box::new(rx.map_err(|_| -> Error { unreachable!() }))
+}
+
+fn emit(
+    tx: &mpsc::Sender<u32>,
+    value: u32,
+) -> Box<Future<Item = (), Error = Error>> {
+    Box::new(
+        tx.clone()
+            .send(value)
+            .map(|_| ())
+            .map_err(|_| Error::from("unable to emit value")),
+    )
+}
+
+/// nucleus that sends its values through Input and collects them from Output
+///
+/// the organelle is stopped once the expected number of values is collected.
+struct Harness {
+    send: Vec<u32>,
+    expected: u64,
+    input: Option<mpsc::Sender<u32>>,
+    output: Option<mpsc::Receiver<u32>>,
+    values: Rc<RefCell<Vec<u32>>>,
+}
+
+impl Harness {
+    fn axon(
+        send: Vec<u32>,
+        expected: u64,
+        values: &Rc<RefCell<Vec<u32>>>,
+    ) -> Axon<Self> {
+        let terminals = if send.is_empty() {
+            vec![]
+        } else {
+            vec![Constraint::One(TestSynapse::Input)]
+        };
+
+        Axon::new(
+            Harness {
+                send: send,
+                expected: expected,
+                input: None,
+                output: None,
+                values: Rc::clone(values),
+            },
+            vec![Constraint::One(TestSynapse::Output)],
+            terminals,
+        )
+    }
+}
+
+impl Soma for Harness {
+    type Synapse = TestSynapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddTerminal(_, TestSynapse::Input, tx) => {
+                self.input = Some(tx);
+            },
+            Impulse::AddDendrite(_, TestSynapse::Output, rx) => {
+                self.output = Some(rx);
+            },
+            Impulse::Start(_, main_tx, _) => {
+                if let Some(input) = self.input.take() {
+                    await!(
+                        input
+                            .send_all(stream::iter_ok(self.send.clone()))
+                            .map_err(|_| Error::from("unable to send values"))
+                    )?;
+                }
+
+                let output = self.output.take().unwrap();
+                let values = await!(
+                    output
+                        .take(self.expected)
+                        .collect()
+                        .map_err(|_| Error::from("unable to collect values"))
+                )?;
+
+                *self.values.borrow_mut() = values;
+
+                await!(
+                    main_tx
+                        .send(Impulse::Stop)
+                        .map_err(|_| Error::from("unable to stop"))
+                )?;
+            },
+            _ => bail!("unexpected impulse"),
+        }
+
+        Ok(self)
+    }
+}
+
+fn run(core: &mut reactor::Core, organelle: Organelle<Axon<Harness>>) {
+    let handle = core.handle();
+    let timeout =
+        reactor::Timeout::new(Duration::from_secs(5), &handle).unwrap();
+
+    match core.run(organelle.run(handle).select2(timeout)) {
+        Ok(Either::A(_)) => (),
+        Ok(Either::B(_)) => panic!("values never made it through"),
+        Err(Either::A((e, _))) => panic!("organelle failed: {:#?}", e),
+        Err(Either::B((e, _))) => panic!("timeout failed: {:#?}", e),
+    }
+}
+
+#[test]
+fn test_map() {
+    let mut core = reactor::Core::new().unwrap();
+
+    let values = Rc::new(RefCell::new(vec![]));
+    let mut organelle = Organelle::new(
+        Harness::axon(vec![1, 2, 3], 3, &values),
+        core.handle(),
+    );
+
+    let nucleus = organelle.nucleus();
+    let double = organelle.add_soma(map(
+        TestSynapse::Input,
+        TestSynapse::Output,
+        receive,
+        |value| value * 2,
+        emit,
+    ));
+
+    organelle.connect(nucleus, double, TestSynapse::Input).unwrap();
+    organelle.connect(double, nucleus, TestSynapse::Output).unwrap();
+
+    run(&mut core, organelle);
+
+    assert_eq!(*values.borrow(), vec![2, 4, 6]);
+}
+
+#[test]
+fn test_from_stream() {
+    let mut core = reactor::Core::new().unwrap();
+
+    let values = Rc::new(RefCell::new(vec![]));
+    let mut organelle =
+        Organelle::new(Harness::axon(vec![], 3, &values), core.handle());
+
+    let nucleus = organelle.nucleus();
+    let source = organelle.add_soma(from_stream(
+        TestSynapse::Output,
+        stream::iter_ok(vec![1, 2, 3]),
+        emit,
+    ));
+
+    organelle.connect(source, nucleus, TestSynapse::Output).unwrap();
+
+    run(&mut core, organelle);
+
+    assert_eq!(*values.borrow(), vec![1, 2, 3]);
+}
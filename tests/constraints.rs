@@ -5,12 +5,17 @@ extern crate error_chain;
 
 extern crate futures_await as futures;
 extern crate organelle;
+extern crate serde_json;
 extern crate tokio_core;
+extern crate uuid;
+
+use std::borrow::Cow;
 
 use futures::prelude::*;
 use futures::unsync;
 use organelle::*;
 use tokio_core::reactor;
+use uuid::Uuid;
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
 enum Synapse {
@@ -42,6 +47,33 @@ impl organelle::Synapse for Synapse {
     }
 }
 
+/// a synapse from another crate that GiverSoma and TakerSoma know nothing of
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+enum OuterSynapse {
+    Give,
+}
+
+impl organelle::Synapse for OuterSynapse {
+    type Terminal = Terminal;
+    type Dendrite = Dendrite;
+
+    fn synapse(self) -> (Self::Terminal, Self::Dendrite) {
+        match self {
+            OuterSynapse::Give => {
+                organelle::Synapse::synapse(Synapse::GiveSomething)
+            },
+        }
+    }
+}
+
+fn to_outer(_: Synapse) -> OuterSynapse {
+    OuterSynapse::Give
+}
+
+fn from_outer(_: OuterSynapse) -> Synapse {
+    Synapse::GiveSomething
+}
+
 struct GiverSoma {
     tx: Option<unsync::mpsc::Sender<()>>,
 }
@@ -127,21 +159,55 @@ impl Soma for TakerSoma {
     }
 }
 
+struct IdleSoma;
+
+impl IdleSoma {
+    fn optional() -> Axon<Self> {
+        Axon::new(
+            IdleSoma,
+            vec![Constraint::Optional(Synapse::GiveSomething)],
+            vec![],
+        )
+    }
+
+    fn range(min: usize, max: usize) -> Axon<Self> {
+        Axon::new(
+            IdleSoma,
+            vec![Constraint::Range {
+                synapse: Synapse::GiveSomething,
+                min: min,
+                max: max,
+            }],
+            vec![],
+        )
+    }
+}
+
+impl Soma for IdleSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    fn category(&self) -> Option<Cow<str>> {
+        Some(Cow::Borrowed("idle"))
+    }
+
+    #[async(boxed)]
+    fn update(self, _imp: Impulse<Self::Synapse>) -> Result<Self> {
+        Ok(self)
+    }
+}
+
 #[test]
 fn test_invalid_input() {
-    let mut core = reactor::Core::new().unwrap();
-    let handle = core.handle();
+    let core = reactor::Core::new().unwrap();
 
-    let mut organelle = Organelle::new(GiverSoma::axon(), handle.clone());
+    let mut organelle = Organelle::new(GiverSoma::axon(), core.handle());
 
     let giver1 = organelle.nucleus();
     let giver2 = organelle.add_soma(GiverSoma::axon());
 
-    organelle
-        .connect(giver1, giver2, Synapse::GiveSomething)
-        .unwrap();
-
-    if let Err(e) = core.run(organelle.run(handle)) {
+    // connect checks the constraints of both somas before wiring anything
+    if let Err(e) = organelle.connect(giver1, giver2, Synapse::GiveSomething) {
         match e.kind() {
             &ErrorKind::InvalidSynapse(ref msg) => {
                 println!("got expected error: {}", *msg)
@@ -197,3 +263,412 @@ fn test_require_one() {
         }
     }
 }
+
+#[test]
+fn test_optional() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    // nothing needs to be connected
+    core.run(IdleSoma::optional().run(handle.clone())).unwrap();
+
+    let mut organelle = Organelle::new(IdleSoma::optional(), handle.clone());
+
+    let optional = organelle.nucleus();
+    let giver1 = organelle.add_soma(GiverSoma::axon());
+    let giver2 = organelle.add_soma(GiverSoma::axon());
+
+    organelle
+        .connect(giver1, optional, Synapse::GiveSomething)
+        .unwrap();
+
+    // but no more than one can be
+    match organelle.connect(giver2, optional, Synapse::GiveSomething) {
+        Err(e) => match e.kind() {
+            &ErrorKind::InvalidSynapse(_) => (),
+            _ => panic!("unexpected error: {:#?}", e),
+        },
+        Ok(_) => panic!("IdleSoma should only accept one input"),
+    }
+}
+
+#[test]
+fn test_range() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    match core.run(IdleSoma::range(2, 3).run(handle.clone())) {
+        Err(e) => match e.kind() {
+            &ErrorKind::MissingSynapse(ref msg) => assert_eq!(
+                msg,
+                "role GiveSomething requires 2..=3 connections, got 0"
+            ),
+            _ => panic!("unexpected error: {:#?}", e),
+        },
+        Ok(_) => panic!("IdleSoma has too few inputs, so it should fail"),
+    }
+
+    let mut organelle = Organelle::new(IdleSoma::range(2, 3), handle.clone());
+
+    let range = organelle.nucleus();
+
+    for _ in 0..3 {
+        let giver = organelle.add_soma(GiverSoma::axon());
+
+        organelle
+            .connect(giver, range, Synapse::GiveSomething)
+            .unwrap();
+    }
+
+    let giver = organelle.add_soma(GiverSoma::axon());
+
+    match organelle.connect(giver, range, Synapse::GiveSomething) {
+        Err(e) => match e.kind() {
+            &ErrorKind::InvalidSynapse(_) => (),
+            _ => panic!("unexpected error: {:#?}", e),
+        },
+        Ok(_) => panic!("IdleSoma should accept at most 3 inputs"),
+    }
+}
+
+#[test]
+fn test_peers() {
+    let mut core = reactor::Core::new().unwrap();
+
+    let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+    let mut axon = IdleSoma::range(0, 3);
+
+    for &uuid in &[a, b] {
+        let (_, rx) = organelle::Synapse::synapse(Synapse::GiveSomething);
+
+        axon = core.run(axon.update(Impulse::AddDendrite(
+            uuid,
+            Synapse::GiveSomething,
+            rx,
+        ))).unwrap();
+    }
+
+    assert_eq!(axon.dendrite_peers(Synapse::GiveSomething), vec![a, b]);
+    assert!(axon.terminal_peers(Synapse::GiveSomething).is_empty());
+}
+
+#[test]
+fn test_category() {
+    let mut core = reactor::Core::new().unwrap();
+
+    let axon = Axon::new(IdleSoma, vec![], vec![]);
+    let (_, data) = core.run(axon.probe(probe::Settings::new())).unwrap();
+
+    match data {
+        SomaData::Axon { category, .. } => {
+            assert_eq!(category, Some("idle".to_string()))
+        },
+        data => panic!("unexpected probe data: {:#?}", data),
+    }
+}
+
+#[test]
+fn test_satisfied() {
+    let mut core = reactor::Core::new().unwrap();
+
+    let mut axon = IdleSoma::range(2, 3);
+    let (_, rx) = organelle::Synapse::synapse(Synapse::GiveSomething);
+
+    axon = core.run(axon.update(Impulse::AddDendrite(
+        Uuid::new_v4(),
+        Synapse::GiveSomething,
+        rx,
+    ))).unwrap();
+
+    let (_, data) = core.run(axon.probe(probe::Settings::new())).unwrap();
+
+    let constraint = match data {
+        SomaData::Axon { ref dendrites, .. } => dendrites[0].clone(),
+        ref data => panic!("unexpected probe data: {:#?}", data),
+    };
+
+    // one of the two required dendrites is connected
+    assert!(!constraint.is_satisfied());
+
+    let json = serde_json::to_value(&constraint).unwrap();
+    assert_eq!(json["type"], "range");
+    assert_eq!(json["satisfied"], false);
+}
+
+#[test]
+fn test_unexpected_names_soma() {
+    let e = Impulse::<Synapse>::Pause.unexpected(&IdleSoma);
+
+    assert_eq!(
+        e.to_string(),
+        format!("unexpected Pause impulse for soma {}", IdleSoma.name())
+    );
+}
+
+#[test]
+fn test_axon_passes_impulses_through() {
+    let mut core = reactor::Core::new().unwrap();
+
+    let (soma, record) = recording(IdleSoma);
+    let axon = Axon::new(soma, vec![], vec![]);
+
+    core.run(axon.update(Impulse::StopDraining)).unwrap();
+
+    assert_eq!(record.kinds(), vec![ImpulseKind::StopDraining]);
+}
+
+#[test]
+fn test_add_constraint() {
+    let mut core = reactor::Core::new().unwrap();
+
+    let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+    let mut axon = Axon::new(IdleSoma, vec![], vec![]);
+
+    axon.add_dendrite_constraint(Constraint::Variadic(Synapse::GiveSomething))
+        .unwrap();
+
+    for &uuid in &[a, b] {
+        let (_, rx) = organelle::Synapse::synapse(Synapse::GiveSomething);
+
+        axon = core.run(axon.update(Impulse::AddDendrite(
+            uuid,
+            Synapse::GiveSomething,
+            rx,
+        ))).unwrap();
+    }
+
+    // two dendrites are already connected, so this is rejected
+    assert!(
+        axon.add_dendrite_constraint(Constraint::One(Synapse::GiveSomething))
+            .is_err()
+    );
+    assert_eq!(axon.dendrite_peers(Synapse::GiveSomething), vec![a, b]);
+
+    axon.add_dendrite_constraint(Constraint::Range {
+        synapse: Synapse::GiveSomething,
+        min: 1,
+        max: 2,
+    }).unwrap();
+    assert_eq!(axon.dendrite_peers(Synapse::GiveSomething), vec![a, b]);
+}
+
+#[test]
+fn test_invalid_range() {
+    let mut axon = IdleSoma::range(0, 3);
+
+    assert!(
+        axon.add_dendrite_constraint(Constraint::Range {
+            synapse: Synapse::GiveSomething,
+            min: 3,
+            max: 1,
+        }).is_err()
+    );
+
+    // the constraint it had is kept
+    let status = axon.constraint_status();
+    assert_eq!(status.len(), 1);
+    assert_eq!(status[0].required, 0);
+}
+
+#[test]
+#[should_panic(expected = "invalid constraint")]
+fn test_invalid_range_in_new() {
+    IdleSoma::range(3, 1);
+}
+
+fn gives(synapse: Synapse) -> bool {
+    synapse == Synapse::GiveSomething
+}
+
+#[test]
+fn test_add_constraint_over_predicate() {
+    let mut core = reactor::Core::new().unwrap();
+
+    let mut axon =
+        Axon::new(IdleSoma, vec![Constraint::VariadicWhere(gives)], vec![]);
+
+    let mut connect = |axon: Axon<IdleSoma>| {
+        let (_, rx) = organelle::Synapse::synapse(Synapse::GiveSomething);
+
+        core.run(axon.update(Impulse::AddDendrite(
+            Uuid::new_v4(),
+            Synapse::GiveSomething,
+            rx,
+        )))
+    };
+
+    axon = connect(axon).unwrap();
+    axon = connect(axon).unwrap();
+
+    // both dendrites were connected under the predicate, but would now fall
+    // under the new constraint, so it is rejected
+    assert!(
+        axon.add_dendrite_constraint(Constraint::One(Synapse::GiveSomething))
+            .is_err()
+    );
+
+    // the predicate still accepts any number of them
+    axon = connect(axon).unwrap();
+    assert_eq!(axon.dendrite_peers(Synapse::GiveSomething).len(), 3);
+}
+
+#[test]
+fn test_predicate() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let axon = || {
+        Axon::new(IdleSoma, vec![Constraint::OneWhere(gives)], vec![])
+    };
+
+    match core.run(axon().run(handle)) {
+        Err(e) => match e.kind() {
+            &ErrorKind::MissingSynapse(ref msg) => {
+                assert_eq!(msg, "missing required dendrite: <predicate>")
+            },
+            _ => panic!("unexpected error: {:#?}", e),
+        },
+        Ok(_) => panic!("IdleSoma has no input, so it should fail"),
+    }
+
+    let uuid = Uuid::new_v4();
+    let (_, rx) = organelle::Synapse::synapse(Synapse::GiveSomething);
+
+    let axon = core.run(axon().update(Impulse::AddDendrite(
+        uuid,
+        Synapse::GiveSomething,
+        rx,
+    ))).unwrap();
+
+    assert_eq!(axon.dendrite_peers(Synapse::GiveSomething), vec![uuid]);
+
+    // the predicate still only accepts one
+    let (_, rx) = organelle::Synapse::synapse(Synapse::GiveSomething);
+
+    assert!(
+        core.run(axon.update(Impulse::AddDendrite(
+            Uuid::new_v4(),
+            Synapse::GiveSomething,
+            rx,
+        ))).is_err()
+    );
+}
+
+#[test]
+fn test_start_lists_violations() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let axon = Axon::new(
+        IdleSoma,
+        vec![Constraint::One(Synapse::GiveSomething)],
+        vec![Constraint::Range {
+            synapse: Synapse::GiveSomething,
+            min: 1,
+            max: 2,
+        }],
+    );
+
+    // every unsatisfied constraint is reported at once
+    match core.run(axon.run(handle)) {
+        Err(e) => match e.kind() {
+            &ErrorKind::MissingSynapse(ref msg) => assert_eq!(
+                msg,
+                "missing required dendrite: GiveSomething; role GiveSomething \
+                 requires 1..=2 connections, got 0"
+            ),
+            _ => panic!("unexpected error: {:#?}", e),
+        },
+        Ok(_) => panic!("IdleSoma has no inputs or outputs, so it should fail"),
+    }
+}
+
+#[test]
+fn test_map_synapse() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let mut organelle = Organelle::new(
+        GiverSoma::axon().map_synapse(to_outer, from_outer),
+        handle.clone(),
+    );
+
+    let giver = organelle.nucleus();
+    let taker = organelle
+        .add_soma(TakerSoma::axon().map_synapse(to_outer, from_outer));
+
+    organelle
+        .connect(giver, taker, OuterSynapse::Give)
+        .unwrap();
+
+    core.run(organelle.run(handle)).unwrap();
+}
+
+#[test]
+fn test_error_with_context() {
+    let axon = GiverSoma::axon();
+
+    let e = axon.error_with_context("unable to give", "out of things");
+
+    assert!(
+        e.to_string().contains("GiverSoma (unstarted) - unable to give"),
+        "unexpected error message: {}",
+        e
+    );
+    assert_eq!(
+        e.iter().nth(1).map(|cause| cause.to_string()),
+        Some("out of things".to_string())
+    );
+}
+
+/// fails the organelle from inside its axon as soon as it starts
+struct BrokenSoma;
+
+impl BrokenSoma {
+    fn axon() -> Axon<Self> {
+        Axon::new(BrokenSoma, vec![], vec![])
+    }
+}
+
+impl Soma for BrokenSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(uuid, main_tx, _) => {
+                await!(Axon::<Self>::fail_with(
+                    uuid,
+                    &main_tx,
+                    "unable to start",
+                    "out of things"
+                ))?;
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+#[test]
+fn test_fail_with() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let organelle = Organelle::new(BrokenSoma::axon(), handle.clone());
+    let context = format!(
+        "BrokenSoma ({}) - unable to start",
+        organelle.nucleus()
+    );
+
+    let e = core.run(organelle.run(handle)).unwrap_err();
+
+    assert!(
+        e.iter().any(|cause| cause.to_string().contains(&context)),
+        "unexpected error: {}",
+        e
+    );
+    assert!(e.iter().any(|cause| cause.to_string() == "out of things"));
+}
@@ -0,0 +1,119 @@
+#![feature(proc_macro, conservative_impl_trait, generators)]
+
+#[macro_use]
+extern crate error_chain;
+
+extern crate futures_await as futures;
+extern crate organelle;
+extern crate tokio_core;
+
+use std::time::Duration;
+
+use futures::prelude::*;
+use organelle::*;
+use organelle::health::{self, Status};
+use tokio_core::reactor;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Answer {
+    Healthy,
+    Unhealthy,
+    Silent,
+}
+
+/// soma that answers health checks the same way every time
+struct CheckedSoma {
+    answer: Answer,
+    rx: Option<health::Dendrite>,
+}
+
+impl CheckedSoma {
+    fn axon(answer: Answer) -> Axon<Self> {
+        Axon::new(
+            CheckedSoma {
+                answer: answer,
+                rx: None,
+            },
+            vec![Constraint::One(health::Synapse::Health)],
+            vec![],
+        )
+    }
+}
+
+impl Soma for CheckedSoma {
+    type Synapse = health::Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddDendrite(_, health::Synapse::Health, rx) => Ok(Self {
+                answer: self.answer,
+                rx: Some(rx),
+            }),
+            Impulse::Start(_, _, handle) => {
+                let rx = self.rx.unwrap();
+
+                match self.answer {
+                    Answer::Healthy => rx.serve(&handle, || Status::Healthy),
+                    Answer::Unhealthy => rx.serve(&handle, || {
+                        Status::Unhealthy("broken".into())
+                    }),
+                    // hold on to the dendrite without ever answering
+                    Answer::Silent => {
+                        return Ok(Self {
+                            answer: self.answer,
+                            rx: Some(rx),
+                        })
+                    },
+                }
+
+                Ok(Self {
+                    answer: self.answer,
+                    rx: None,
+                })
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+#[test]
+fn test_health_report() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let (axon, watcher) = health::Soma::axon(Duration::from_millis(50));
+    let mut organelle = Organelle::new(axon, handle.clone());
+
+    let healthy = organelle.add_soma(CheckedSoma::axon(Answer::Healthy));
+    let unhealthy = organelle.add_soma(CheckedSoma::axon(Answer::Unhealthy));
+    let silent = organelle.add_soma(CheckedSoma::axon(Answer::Silent));
+
+    for &soma in &[healthy, unhealthy, silent] {
+        organelle
+            .connect(organelle.nucleus(), soma, health::Synapse::Health)
+            .unwrap();
+    }
+
+    handle.spawn(
+        organelle
+            .run(handle.clone())
+            .map_err(|e| panic!("organelle failed: {:#?}", e)),
+    );
+
+    let mut report = core.run(watcher.wait_for(|report| report.checked == 3))
+        .unwrap();
+
+    assert!(!report.is_healthy());
+
+    report.unhealthy.sort_by_key(|&(uuid, _)| uuid != unhealthy);
+
+    assert_eq!(
+        report.unhealthy,
+        vec![
+            (unhealthy, "broken".to_string()),
+            (silent, "health check timed out".to_string()),
+        ]
+    );
+}
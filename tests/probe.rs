@@ -0,0 +1,162 @@
+#![feature(proc_macro, conservative_impl_trait, generators)]
+
+#[macro_use]
+extern crate error_chain;
+
+extern crate futures_await as futures;
+extern crate organelle;
+extern crate tokio_core;
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use futures::future::Either;
+use futures::prelude::*;
+use futures::unsync::mpsc;
+use organelle::*;
+use tokio_core::reactor;
+
+/// nucleus that subscribes to the probe once the organelle starts
+struct Subscriber {
+    probe: Option<probe::Terminal>,
+    updates: mpsc::UnboundedSender<SomaData>,
+}
+
+impl Subscriber {
+    fn axon(updates: mpsc::UnboundedSender<SomaData>) -> Axon<Self> {
+        Axon::new(
+            Subscriber {
+                probe: None,
+                updates: updates,
+            },
+            vec![],
+            vec![Constraint::One(probe::Synapse::Probe)],
+        )
+    }
+}
+
+impl Soma for Subscriber {
+    type Synapse = probe::Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddTerminal(_, probe::Synapse::Probe, tx) => Ok(Self {
+                probe: Some(tx),
+                updates: self.updates,
+            }),
+            Impulse::Start(_, _, handle) => {
+                // somas that haven't answered in time show up as unavailable
+                let settings =
+                    probe::Settings::new().timeout(Duration::from_millis(10));
+
+                handle.spawn(
+                    self.probe
+                        .unwrap()
+                        .subscribe(settings)
+                        .map_err(|e| -> () { panic!("{:#?}", e) })
+                        .forward(self.updates.clone().sink_map_err(|_| ()))
+                        .map(|_| ()),
+                );
+
+                Ok(Self {
+                    probe: None,
+                    updates: self.updates,
+                })
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// takes a while to fail on its first start, then starts right away
+struct LateSoma {
+    attempts: Rc<Cell<u32>>,
+}
+
+impl Soma for LateSoma {
+    type Synapse = probe::Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(_, _, handle) => {
+                self.attempts.set(self.attempts.get() + 1);
+
+                if self.attempts.get() == 1 {
+                    await!(reactor::Timeout::new(
+                        Duration::from_millis(100),
+                        &handle
+                    )?)?;
+
+                    bail!("failed to start")
+                }
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+#[test]
+fn test_subscribe_to_topology_changes() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let (tx, rx) = mpsc::unbounded();
+    let mut organelle = Organelle::new(Subscriber::axon(tx), handle.clone());
+
+    let nucleus = organelle.nucleus();
+    let probe = organelle.add_soma(probe::Soma::axon());
+    organelle
+        .connect(nucleus, probe, probe::Synapse::Probe)
+        .unwrap();
+
+    let attempts = Rc::new(Cell::new(0));
+    let late = organelle.add_soma_with_policy(
+        move || {
+            Axon::new(
+                LateSoma {
+                    attempts: Rc::clone(&attempts),
+                },
+                vec![],
+                vec![],
+            )
+        },
+        RestartPolicy::MaxRetries(1),
+    );
+
+    handle.spawn(
+        organelle
+            .run(handle.clone())
+            .map_err(|e| panic!("organelle failed: {:#?}", e)),
+    );
+
+    // the second update is only pushed because the restart changed the
+    // topology, nothing else feeds the probe soma
+    let timeout =
+        reactor::Timeout::new(Duration::from_secs(5), &handle).unwrap();
+
+    let updates = match core.run(rx.take(2).collect().select2(timeout)) {
+        Ok(Either::A((updates, _))) => updates,
+        _ => panic!("no update after the restart"),
+    };
+
+    let late: Vec<_> = updates
+        .into_iter()
+        .map(|data| data.into_subtree(late).unwrap())
+        .collect();
+
+    match late[0] {
+        SomaData::Unavailable { .. } => (),
+        ref data => panic!("unexpected probe data: {:#?}", data),
+    }
+    match late[1] {
+        SomaData::Axon { .. } => (),
+        ref data => panic!("unexpected probe data: {:#?}", data),
+    }
+}
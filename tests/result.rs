@@ -0,0 +1,158 @@
+#![feature(proc_macro, conservative_impl_trait, generators)]
+
+#[macro_use]
+extern crate error_chain;
+
+extern crate futures_await as futures;
+extern crate organelle;
+extern crate tokio_core;
+
+use futures::prelude::*;
+use futures::unsync;
+use organelle::*;
+use tokio_core::reactor;
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+enum Synapse {
+    Number,
+}
+
+#[derive(Debug)]
+enum Terminal {
+    Number(unsync::mpsc::Sender<u32>),
+}
+
+#[derive(Debug)]
+enum Dendrite {
+    Number(unsync::mpsc::Receiver<u32>),
+}
+
+impl organelle::Synapse for Synapse {
+    type Terminal = Terminal;
+    type Dendrite = Dendrite;
+
+    fn synapse(self) -> (Self::Terminal, Self::Dendrite) {
+        match self {
+            Synapse::Number => {
+                let (tx, rx) = unsync::mpsc::channel(1);
+
+                (Terminal::Number(tx), Dendrite::Number(rx))
+            },
+        }
+    }
+}
+
+struct CounterSoma {
+    tx: Option<unsync::mpsc::Sender<u32>>,
+}
+
+impl CounterSoma {
+    fn axon() -> Axon<Self> {
+        Axon::new(
+            CounterSoma { tx: None },
+            vec![],
+            vec![Constraint::One(Synapse::Number)],
+        )
+    }
+}
+
+impl Soma for CounterSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddTerminal(_, Synapse::Number, Terminal::Number(tx)) => {
+                Ok(Self { tx: Some(tx) })
+            },
+            Impulse::Start(_, _, _) => {
+                await!(
+                    self.tx
+                        .unwrap()
+                        .send_all(futures::stream::iter_ok(1..11))
+                        .map_err(|_| Error::from("unable to send numbers"))
+                )?;
+
+                Ok(Self { tx: None })
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+struct SumSoma {
+    rx: Option<unsync::mpsc::Receiver<u32>>,
+    result: Option<ResultSender<u32>>,
+}
+
+impl SumSoma {
+    fn axon(result: ResultSender<u32>) -> Axon<Self> {
+        Axon::new(
+            SumSoma {
+                rx: None,
+                result: Some(result),
+            },
+            vec![Constraint::One(Synapse::Number)],
+            vec![],
+        )
+    }
+}
+
+impl Soma for SumSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddDendrite(_, Synapse::Number, Dendrite::Number(rx)) => {
+                Ok(Self {
+                    rx: Some(rx),
+                    result: self.result,
+                })
+            },
+            Impulse::Start(_, tx, _) => {
+                let total = await!(
+                    self.rx
+                        .unwrap()
+                        .fold(0, |total, n| Ok::<_, ()>(total + n))
+                        .map_err(|_| Error::from("unable to sum numbers"))
+                )?;
+
+                self.result.unwrap().send(total)?;
+
+                await!(
+                    tx.send(Impulse::Stop)
+                        .map_err(|_| Error::from("unable to stop"))
+                )?;
+
+                Ok(Self {
+                    rx: None,
+                    result: None,
+                })
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+#[test]
+fn test_sum_result() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let mut organelle = Organelle::new(CounterSoma::axon(), handle.clone());
+
+    let result = organelle.result_sender::<u32>();
+    let sum = organelle.add_soma(SumSoma::axon(result));
+
+    organelle
+        .connect(organelle.nucleus(), sum, Synapse::Number)
+        .unwrap();
+
+    let total =
+        core.run(organelle.run_with_result::<u32>(handle)).unwrap();
+
+    assert_eq!(total, 55);
+}
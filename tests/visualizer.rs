@@ -0,0 +1,346 @@
+#![cfg(feature = "visualizer")]
+#![feature(proc_macro, conservative_impl_trait, generators)]
+
+#[macro_use]
+extern crate error_chain;
+
+extern crate futures_await as futures;
+extern crate hyper;
+extern crate organelle;
+extern crate serde_json;
+extern crate tokio_core;
+
+use std::net::SocketAddr;
+use std::time;
+
+use futures::prelude::*;
+use organelle::*;
+use tokio_core::reactor;
+
+/// spawn a standalone visualizer on a free port and wait until it is bound
+fn serve(
+    core: &mut reactor::Core,
+    settings: visualizer::Settings,
+) -> SocketAddr {
+    let handle = core.handle();
+    let (publisher, watcher) = watch::channel(None);
+
+    let visualizer = visualizer::Soma::standalone(
+        settings.port(0).publish_address(publisher),
+    ).unwrap();
+
+    handle.spawn(
+        visualizer
+            .run(handle.clone())
+            .map_err(|e| panic!("visualizer failed: {:#?}", e)),
+    );
+
+    core.run(watcher.wait_for(Option::is_some))
+        .unwrap()
+        .unwrap()
+}
+
+#[test]
+fn test_missing_probe() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let addr = serve(&mut core, visualizer::Settings::default());
+
+    let client = hyper::Client::new(&handle);
+    let uri: hyper::Uri = format!("http://{}/api/probe/json", addr)
+        .parse()
+        .unwrap();
+
+    let request = client.get(uri).and_then(|rsp| {
+        let status = rsp.status();
+
+        rsp.body().concat2().map(move |body| (status, body))
+    });
+
+    let (status, body) = core.run(request).unwrap();
+
+    assert_eq!(status, hyper::StatusCode::ServiceUnavailable);
+    assert!(
+        String::from_utf8_lossy(&body).contains("probe not connected"),
+        "unexpected body: {:?}",
+        body
+    );
+}
+
+#[test]
+fn test_embedded_ui() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let addr = serve(&mut core, visualizer::Settings::default());
+
+    let client = hyper::Client::new(&handle);
+
+    // the ui is compiled into the binary, so it is served no matter which
+    // directory the visualizer runs from
+    for path in &["/", "/index.html", "/viz-lite.js"] {
+        let uri: hyper::Uri =
+            format!("http://{}{}", addr, path).parse().unwrap();
+
+        let request = client.get(uri).and_then(|rsp| {
+            let status = rsp.status();
+            let content_type = rsp.headers()
+                .get_raw("Content-Type")
+                .and_then(|raw| raw.one())
+                .map(|raw| String::from_utf8_lossy(raw).into_owned());
+
+            rsp.body()
+                .concat2()
+                .map(move |body| (status, content_type, body))
+        });
+
+        let (status, content_type, body) = core.run(request).unwrap();
+
+        assert_eq!(
+            status,
+            hyper::StatusCode::Ok,
+            "unexpected status for {}",
+            path
+        );
+        assert!(!body.is_empty(), "empty body for {}", path);
+        assert!(content_type.is_some(), "no content type for {}", path);
+    }
+}
+
+#[test]
+fn test_cors_preflight() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let addr = serve(
+        &mut core,
+        visualizer::Settings::default()
+            .allow_origin(Some("http://localhost:3000".into())),
+    );
+
+    let client = hyper::Client::new(&handle);
+    let uri: hyper::Uri = format!("http://{}/api/probe/json", addr)
+        .parse()
+        .unwrap();
+
+    let request = client
+        .request(hyper::Request::new(hyper::Method::Options, uri))
+        .map(|rsp| {
+            let origin = rsp.headers()
+                .get_raw("Access-Control-Allow-Origin")
+                .and_then(|raw| raw.one())
+                .map(|raw| String::from_utf8_lossy(raw).into_owned());
+
+            (rsp.status(), origin)
+        });
+
+    let (status, origin) = core.run(request).unwrap();
+
+    assert_eq!(status, hyper::StatusCode::NoContent);
+    assert_eq!(origin, Some("http://localhost:3000".to_string()));
+}
+
+#[test]
+fn test_port_in_use() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    // let the os pick a free port, then keep it taken for the test
+    let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = taken.local_addr().unwrap().port();
+
+    // without retries, the error names the port so it can be changed
+    let visualizer = visualizer::Soma::standalone(
+        visualizer::Settings::default().port(port),
+    ).unwrap();
+
+    let e = core.run(visualizer.run(handle.clone())).unwrap_err();
+
+    assert!(
+        format!("{}", e).contains(&port.to_string()),
+        "unexpected error: {}",
+        e
+    );
+
+    // with retries, the visualizer moves on to the next port
+    let (publisher, watcher) = watch::channel(None);
+
+    let visualizer = visualizer::Soma::standalone(
+        visualizer::Settings::default()
+            .port(port)
+            .port_retries(8)
+            .publish_address(publisher),
+    ).unwrap();
+
+    handle.spawn(
+        visualizer
+            .run(handle.clone())
+            .map_err(|e| panic!("visualizer failed: {:#?}", e)),
+    );
+
+    let addr = core.run(watcher.wait_for(Option::is_some))
+        .unwrap()
+        .unwrap();
+
+    assert!(addr.port() > port, "visualizer bound to a taken port");
+
+    let client = hyper::Client::new(&handle);
+    let uri: hyper::Uri = format!("http://{}/", addr).parse().unwrap();
+
+    let request = client.get(uri).map(|rsp| rsp.status());
+
+    assert_eq!(core.run(request).unwrap(), hyper::StatusCode::Ok);
+}
+
+#[test]
+fn test_meta() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let addr = serve(
+        &mut core,
+        visualizer::Settings::default()
+            .refresh_interval(time::Duration::from_millis(250)),
+    );
+
+    let client = hyper::Client::new(&handle);
+    let uri: hyper::Uri =
+        format!("http://{}/api/meta", addr).parse().unwrap();
+
+    let request = client.get(uri).and_then(|rsp| rsp.body().concat2());
+
+    let body = core.run(request).unwrap();
+    let meta: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    // the meta endpoint is served even though no probe is connected, and
+    // reports the port the os picked rather than port 0
+    assert_eq!(meta["refresh_ms"], 250);
+    assert_eq!(meta["ws_available"], false);
+    assert_eq!(meta["bind"], addr.to_string());
+}
+
+/// soma with an optional probe dendrite that is never connected
+struct LonelySoma;
+
+impl Soma for LonelySoma {
+    type Synapse = probe::Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(_, _, _) => Ok(self),
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+fn collect_types(json: &serde_json::Value, types: &mut Vec<String>) {
+    match *json {
+        serde_json::Value::Array(ref values) => for value in values {
+            collect_types(value, types);
+        },
+        serde_json::Value::Object(ref map) => {
+            if let Some(kind) = map.get("type").and_then(|kind| kind.as_str())
+            {
+                types.push(kind.to_string());
+            }
+
+            for (_, value) in map {
+                collect_types(value, types);
+            }
+        },
+        _ => (),
+    }
+}
+
+#[test]
+fn test_older_api_versions() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let (publisher, watcher) = watch::channel(None);
+
+    let mut organelle = visualizer::Soma::organelle(
+        visualizer::Settings::default()
+            .port(0)
+            .publish_address(publisher),
+        handle.clone(),
+    ).unwrap();
+
+    organelle.add_soma(Axon::new(
+        LonelySoma,
+        vec![Constraint::Optional(probe::Synapse::Probe)],
+        vec![],
+    ));
+
+    handle.spawn(
+        organelle
+            .run(handle.clone())
+            .map_err(|e| panic!("organelle failed: {:#?}", e)),
+    );
+
+    let addr = core.run(watcher.wait_for(Option::is_some))
+        .unwrap()
+        .unwrap();
+
+    let client = hyper::Client::new(&handle);
+
+    let mut fetch = |path: &str| {
+        let uri: hyper::Uri =
+            format!("http://{}{}", addr, path).parse().unwrap();
+
+        let request = client.get(uri).and_then(|rsp| {
+            let status = rsp.status();
+            let version = rsp.headers()
+                .get_raw("X-Probe-Api-Version")
+                .and_then(|raw| raw.one())
+                .map(|raw| String::from_utf8_lossy(raw).into_owned());
+
+            rsp.body()
+                .concat2()
+                .map(move |body| (status, version, body))
+        });
+
+        core.run(request).unwrap()
+    };
+
+    // v1 clients get the shape they were written against
+    let (status, version, body) = fetch("/api/v1/probe/json");
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let mut types = vec![];
+    collect_types(&json["data"], &mut types);
+
+    assert_eq!(status, hyper::StatusCode::Ok);
+    assert_eq!(version, Some("1".to_string()));
+    assert_eq!(json["version"], 1);
+    assert!(types.contains(&"variadic".to_string()), "{:?}", types);
+    assert!(!types.contains(&"optional".to_string()), "{:?}", types);
+
+    // unversioned paths serve the same shape without an envelope
+    let (status, _, body) = fetch("/api/probe/json");
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let mut types = vec![];
+    collect_types(&json, &mut types);
+
+    assert_eq!(status, hyper::StatusCode::Ok);
+    assert!(!types.contains(&"optional".to_string()), "{:?}", types);
+
+    // the current version reports the optional constraint as it is
+    let (status, version, body) =
+        fetch(&format!("/api/v{}/probe/json", probe::API_VERSION));
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let mut types = vec![];
+    collect_types(&json["data"], &mut types);
+
+    assert_eq!(status, hyper::StatusCode::Ok);
+    assert_eq!(version, Some(probe::API_VERSION.to_string()));
+    assert!(types.contains(&"optional".to_string()), "{:?}", types);
+
+    // versions from the future are not served
+    let (status, _, _) =
+        fetch(&format!("/api/v{}/probe/json", probe::API_VERSION + 1));
+
+    assert_eq!(status, hyper::StatusCode::NotFound);
+}
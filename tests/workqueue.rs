@@ -0,0 +1,247 @@
+#![feature(proc_macro, conservative_impl_trait, generators)]
+
+#[macro_use]
+extern crate error_chain;
+
+extern crate futures_await as futures;
+extern crate organelle;
+extern crate tokio_core;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use futures::prelude::*;
+use organelle::*;
+use organelle::workqueue::{self, Lease};
+use tokio_core::reactor;
+
+type Synapse = workqueue::Synapse<u32>;
+type Terminal = workqueue::Terminal<u32>;
+
+struct ProducerSoma {
+    tx: Option<Terminal>,
+}
+
+impl ProducerSoma {
+    fn axon() -> Axon<Self> {
+        Axon::new(
+            ProducerSoma { tx: None },
+            vec![],
+            vec![Constraint::One(Synapse::submit())],
+        )
+    }
+}
+
+impl Soma for ProducerSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddTerminal(_, _, tx) => Ok(Self { tx: Some(tx) }),
+            Impulse::Start(_, _, _) => {
+                await!(self.tx.unwrap().submit(7))?;
+
+                Ok(Self { tx: None })
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// worker that drops its first lease as if it had crashed
+struct FlakyWorkerSoma {
+    tx: Option<Terminal>,
+    seen: Rc<RefCell<Vec<u32>>>,
+}
+
+impl FlakyWorkerSoma {
+    fn axon(seen: Rc<RefCell<Vec<u32>>>) -> Axon<Self> {
+        Axon::new(
+            FlakyWorkerSoma {
+                tx: None,
+                seen: seen,
+            },
+            vec![],
+            vec![Constraint::One(Synapse::work())],
+        )
+    }
+
+    #[async]
+    fn work(
+        tx: Terminal,
+        seen: Rc<RefCell<Vec<u32>>>,
+        main_tx: futures::unsync::mpsc::Sender<Impulse<Synapse>>,
+    ) -> Result<()> {
+        let (tx, lease): (Terminal, Lease<u32>) = await!(tx.next())?;
+        seen.borrow_mut().push(*lease.task());
+
+        // never acknowledged
+        drop(lease);
+
+        let (_tx, lease) = await!(tx.next())?;
+        seen.borrow_mut().push(*lease.task());
+
+        lease.ack();
+
+        await!(
+            main_tx
+                .send(Impulse::Stop)
+                .map_err(|_| Error::from("unable to stop"))
+        )?;
+
+        Ok(())
+    }
+}
+
+impl Soma for FlakyWorkerSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddTerminal(_, _, tx) => Ok(Self {
+                tx: Some(tx),
+                seen: self.seen,
+            }),
+            Impulse::Start(_, main_tx, handle) => {
+                handle.spawn(
+                    Self::work(
+                        self.tx.unwrap(),
+                        Rc::clone(&self.seen),
+                        main_tx,
+                    ).map_err(|e| panic!("worker failed: {:#?}", e)),
+                );
+
+                Ok(Self {
+                    tx: None,
+                    seen: self.seen,
+                })
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+#[test]
+fn test_redelivery() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let mut organelle = Organelle::new(
+        workqueue::Soma::axon(workqueue::Settings::default()),
+        handle.clone(),
+    );
+
+    let seen = Rc::new(RefCell::new(vec![]));
+
+    let producer = organelle.add_soma(ProducerSoma::axon());
+    let worker = organelle.add_soma(FlakyWorkerSoma::axon(Rc::clone(&seen)));
+
+    organelle
+        .connect(producer, organelle.nucleus(), Synapse::submit())
+        .unwrap();
+    organelle
+        .connect(worker, organelle.nucleus(), Synapse::work())
+        .unwrap();
+
+    core.run(organelle.run(handle)).unwrap();
+
+    assert_eq!(*seen.borrow(), vec![7, 7]);
+}
+
+/// worker that finishes one task, then waits for another
+struct LastWorkerSoma {
+    tx: Option<Terminal>,
+    closed: Rc<RefCell<bool>>,
+}
+
+impl LastWorkerSoma {
+    fn axon(closed: Rc<RefCell<bool>>) -> Axon<Self> {
+        Axon::new(
+            LastWorkerSoma {
+                tx: None,
+                closed: closed,
+            },
+            vec![],
+            vec![Constraint::One(Synapse::work())],
+        )
+    }
+
+    #[async]
+    fn work(
+        tx: Terminal,
+        closed: Rc<RefCell<bool>>,
+        main_tx: futures::unsync::mpsc::Sender<Impulse<Synapse>>,
+    ) -> Result<()> {
+        let (tx, lease): (Terminal, Lease<u32>) = await!(tx.next())?;
+        lease.ack();
+
+        // the only producer is done, so the queue closes instead
+        *closed.borrow_mut() = await!(tx.next()).is_err();
+
+        await!(
+            main_tx
+                .send(Impulse::Stop)
+                .map_err(|_| Error::from("unable to stop"))
+        )?;
+
+        Ok(())
+    }
+}
+
+impl Soma for LastWorkerSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddTerminal(_, _, tx) => Ok(Self {
+                tx: Some(tx),
+                closed: self.closed,
+            }),
+            Impulse::Start(_, main_tx, handle) => {
+                handle.spawn(
+                    Self::work(
+                        self.tx.unwrap(),
+                        Rc::clone(&self.closed),
+                        main_tx,
+                    ).map_err(|e| panic!("worker failed: {:#?}", e)),
+                );
+
+                Ok(Self {
+                    tx: None,
+                    closed: self.closed,
+                })
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+#[test]
+fn test_queue_closes_once_drained() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let mut organelle = Organelle::new(
+        workqueue::Soma::axon(workqueue::Settings::default()),
+        handle.clone(),
+    );
+
+    let closed = Rc::new(RefCell::new(false));
+
+    let nucleus = organelle.nucleus();
+    let producer = organelle.add_soma(ProducerSoma::axon());
+    let worker = organelle.add_soma(LastWorkerSoma::axon(Rc::clone(&closed)));
+
+    organelle.connect(producer, nucleus, Synapse::submit()).unwrap();
+    organelle.connect(worker, nucleus, Synapse::work()).unwrap();
+
+    core.run(organelle.run(handle)).unwrap();
+
+    assert!(*closed.borrow());
+}